@@ -0,0 +1,16 @@
+use std::alloc::{self, Layout};
+
+pub fn libc_alloc() {
+    unsafe {
+        let p = libc::malloc(16);
+        libc::free(p);
+    }
+}
+
+pub fn std_alloc() {
+    let layout = Layout::new::<u64>();
+    unsafe {
+        let p = alloc::alloc(layout);
+        alloc::dealloc(p, layout);
+    }
+}
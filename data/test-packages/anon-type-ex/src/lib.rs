@@ -0,0 +1,27 @@
+use std::env;
+
+pub struct Buf {
+    pub data: [u8; {
+        let n = 4;
+        n * 2
+    }],
+}
+
+pub fn new_buf() -> Buf {
+    let len = {
+        let n = 4;
+        n * 2
+    };
+    let data: [u8; {
+        let n = 4;
+        n * 2
+    }] = [0; 8];
+    assert_eq!(data.len(), len);
+    Buf { data }
+}
+
+pub fn closure_var() -> Option<String> {
+    let get = |name: &str| env::var(name).ok();
+    let home = get("HOME");
+    home
+}
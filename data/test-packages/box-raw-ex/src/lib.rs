@@ -0,0 +1,18 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+pub fn leak(b: Box<u32>) -> *mut u32 {
+    Box::into_raw(b)
+}
+
+/// # Safety
+/// `p` must come from `leak`
+pub unsafe fn reclaim(p: *mut u32) -> Box<u32> {
+    Box::from_raw(p)
+}
+
+pub fn share(s: Rc<String>, a: Arc<String>) -> (*const String, Arc<String>) {
+    let p = Rc::into_raw(s);
+    let q = Arc::into_raw(a);
+    (p, unsafe { Arc::from_raw(q) })
+}
@@ -0,0 +1,24 @@
+use std::env;
+
+pub struct Handler {
+    pub f: Box<dyn Fn() -> Option<String>>,
+}
+
+pub fn called_locally() -> Option<String> {
+    let get = || env::var("HOME").ok();
+    get()
+}
+
+pub fn returned() -> impl Fn() -> Option<String> {
+    || env::var("HOME").ok()
+}
+
+pub fn stored_in_field() -> Handler {
+    let f = Box::new(|| env::var("PATH").ok());
+    Handler { f }
+}
+
+pub fn returned_via_local() -> Box<dyn Fn() -> Option<String>> {
+    let f = move || env::var("USER").ok();
+    Box::new(f)
+}
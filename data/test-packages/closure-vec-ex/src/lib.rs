@@ -0,0 +1,15 @@
+use std::fs;
+use std::process::Command;
+
+pub fn run_handlers() {
+    let mut handlers: Vec<Box<dyn Fn()>> = Vec::new();
+    handlers.push(Box::new(|| {
+        let _ = fs::remove_file("cache.txt");
+    }));
+    handlers.push(Box::new(|| {
+        let _ = Command::new("cleanup").status();
+    }));
+    for handler in &handlers {
+        handler();
+    }
+}
@@ -0,0 +1,12 @@
+mod storage {
+    pub fn save(data: &str) -> std::io::Result<()> {
+        std::fs::write("data.txt", data)
+    }
+}
+
+pub fn save_all(items: &[&str]) -> std::io::Result<()> {
+    for item in items {
+        storage::save(item)?;
+    }
+    Ok(())
+}
@@ -22,3 +22,13 @@ pub fn unsafe_deref() -> Option<u32> {
     }
     Some(1)
 }
+
+fn pick<'a>(_hint: *const i32, r: &'a i32) -> &'a i32 {
+    r
+}
+
+pub fn safe_deref() -> i32 {
+    let x: i32 = 5;
+    let p: *const i32 = &x;
+    *pick(p, &x)
+}
@@ -0,0 +1,74 @@
+/*
+    Example of calling methods through dyn trait objects,
+    with two implementors that have different effects
+*/
+
+use std::fs;
+use std::io::{self, Write};
+use std::process::Command;
+
+pub trait Logger {
+    fn log(&self, msg: &str);
+}
+
+pub struct FileLogger;
+
+impl Logger for FileLogger {
+    fn log(&self, msg: &str) {
+        fs::write("log.txt", msg).unwrap();
+    }
+}
+
+pub struct CmdLogger;
+
+impl Logger for CmdLogger {
+    fn log(&self, msg: &str) {
+        Command::new("logger").arg(msg).status().unwrap();
+    }
+}
+
+pub fn log_dyn(logger: &dyn Logger, msg: &str) {
+    logger.log(msg);
+}
+
+// Implementors of a trait from another crate
+
+pub struct FileSink;
+
+impl Write for FileSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        fs::write("out.txt", buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct CmdSink;
+
+impl Write for CmdSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Command::new("logger").arg(String::from_utf8_lossy(buf).as_ref()).status()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn write_dyn(out: &mut dyn Write, buf: &[u8]) -> io::Result<usize> {
+    out.write(buf)
+}
+
+// Calls through generic types bounded by the traits
+
+pub fn log_generic<L: Logger>(logger: &L, msg: &str) {
+    logger.log(msg);
+}
+
+pub fn write_generic<W: Write>(out: &mut W, buf: &[u8]) -> io::Result<usize> {
+    out.write(buf)
+}
@@ -0,0 +1,26 @@
+extern "C" {
+    fn abs(x: i32) -> i32;
+}
+
+// the unsafe block is needed for the FFI call
+pub fn c_abs(x: i32) -> i32 {
+    unsafe { abs(x) }
+}
+
+// nothing in this block requires unsafe
+pub fn double(x: i32) -> i32 {
+    unsafe { x * 2 }
+}
+
+// the inner block is empty, but the outer one is needed for the FFI call
+pub fn nested(x: i32) -> i32 {
+    unsafe {
+        let y = abs(x);
+        unsafe { y * 2 }
+    }
+}
+
+// set_len is reported as a sink, but is still an unsafe call
+pub fn clear(v: &mut Vec<u8>) {
+    unsafe { v.set_len(0) }
+}
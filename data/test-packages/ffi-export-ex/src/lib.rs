@@ -0,0 +1,31 @@
+#[no_mangle]
+pub extern "C" fn exported_add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[export_name = "renamed_export"]
+extern "C" fn private_export() -> i32 {
+    1
+}
+
+pub extern "C" fn public_extern() -> i32 {
+    2
+}
+
+// Not exported: private, and its symbol name is mangled
+extern "C" fn callback() -> i32 {
+    3
+}
+
+pub fn register() -> extern "C" fn() -> i32 {
+    callback
+}
+
+pub struct Counter;
+
+impl Counter {
+    #[no_mangle]
+    pub extern "C" fn counter_new() -> i32 {
+        0
+    }
+}
@@ -0,0 +1,25 @@
+use std::fs;
+
+pub struct Store;
+
+pub trait Load {
+    fn load(&self) -> String {
+        fs::read_to_string("default").unwrap()
+    }
+}
+
+impl Store {
+    pub fn remove(&self) {
+        fs::remove_file("store").unwrap();
+    }
+}
+
+impl Load for Store {
+    fn load(&self) -> String {
+        fs::read_to_string("store").unwrap()
+    }
+}
+
+pub fn clear() {
+    fs::remove_dir_all("stores").unwrap();
+}
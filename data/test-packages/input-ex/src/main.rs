@@ -0,0 +1,28 @@
+use std::env;
+use std::io::{self, BufRead};
+use std::process::Command;
+
+fn first_arg() -> Option<String> {
+    env::args().nth(1)
+}
+
+fn read_line() -> String {
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line).unwrap();
+    line
+}
+
+fn home() -> String {
+    env::var("HOME").unwrap_or_default()
+}
+
+fn run(program: &str) {
+    Command::new("echo").arg(program).status().unwrap();
+    Command::new(program).status().unwrap();
+}
+
+fn main() {
+    let arg = first_arg().unwrap_or_else(read_line);
+    println!("{} {}", arg, home());
+    run(&arg);
+}
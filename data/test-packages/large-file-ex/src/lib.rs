@@ -0,0 +1,9 @@
+mod generated;
+
+pub fn home() -> Option<String> {
+    std::env::var("HOME").ok()
+}
+
+pub fn lookup(i: usize) -> u32 {
+    generated::lookup(i)
+}
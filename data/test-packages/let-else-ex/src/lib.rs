@@ -0,0 +1,28 @@
+/*
+    Examples of effects in the branches of let-else, if let and while let
+*/
+
+use std::env;
+use std::fs;
+use std::process;
+
+pub fn let_else() -> String {
+    let Ok(home) = env::var("HOME") else {
+        process::exit(1);
+    };
+    home
+}
+
+pub fn if_let(path: &str) {
+    if let Ok(data) = fs::read_to_string(path) {
+        fs::write("copy.txt", data).unwrap();
+    } else {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+pub fn while_let(paths: &mut Vec<String>) {
+    while let Some(path) = paths.pop() {
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+use std::env;
+use std::fs;
+
+macro_rules! opaque {
+    ($($t:tt)*) => {};
+}
+
+pub fn print_home() {
+    println!("{}", env::var("HOME").unwrap());
+}
+
+pub fn read_config() -> Vec<String> {
+    let config = vec![fs::read_to_string("config.toml").unwrap(); 2];
+    assert!(fs::metadata("config.toml").is_ok(), "{}", config.len());
+    config
+}
+
+pub fn opaque_call() {
+    opaque!(env::remove_var("HOME"));
+}
+
+pub fn remove_config() {
+    fs::remove_file("config.toml").unwrap();
+}
@@ -0,0 +1,26 @@
+use std::fs::File;
+
+// defines a function that writes through a raw pointer
+macro_rules! my_unsafe_fn {
+    ($name:ident, $addr:expr) => {
+        pub fn $name() {
+            unsafe {
+                *($addr as *mut u32) = 0;
+            }
+        }
+    };
+}
+
+macro_rules! create_files {
+    ($($path:expr),*) => {
+        $(
+            File::create($path).unwrap();
+        )*
+    };
+}
+
+my_unsafe_fn!(clear_flag, 0x1000usize);
+
+pub fn create_logs() {
+    create_files!("a.log", "b.log");
+}
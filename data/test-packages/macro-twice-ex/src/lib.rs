@@ -0,0 +1,15 @@
+/*
+    A macro that expands its argument twice, so the tokens in the argument
+    map to two places in the expansion
+*/
+
+macro_rules! twice {
+    ($e:expr) => {{
+        let _ = $e;
+        $e
+    }};
+}
+
+pub fn remove_twice() -> std::io::Result<()> {
+    twice!(std::fs::remove_file("a.txt"))
+}
@@ -0,0 +1,15 @@
+use std::mem;
+
+pub struct Node {
+    pub value: Vec<u8>,
+}
+
+pub fn swap_nodes(a: &mut Node, b: &mut Node) {
+    std::mem::swap(&mut a.value, &mut b.value);
+}
+
+pub fn reset(n: &mut Node) -> Vec<u8> {
+    let old = mem::replace(&mut n.value, vec![0]);
+    let _ = mem::take(&mut n.value);
+    old
+}
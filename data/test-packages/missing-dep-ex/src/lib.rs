@@ -0,0 +1,3 @@
+pub fn read_config() -> std::io::Result<String> {
+    std::fs::read_to_string("config.toml")
+}
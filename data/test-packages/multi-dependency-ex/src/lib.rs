@@ -0,0 +1,8 @@
+pub fn read_and_deref() -> Option<u32> {
+    dependency_ex::read_fn()?;
+    dependency_ex::unsafe_deref()
+}
+
+pub fn assign(u: &mut union_ex::MyUnion) {
+    union_ex::assign_field(u);
+}
@@ -0,0 +1,13 @@
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+pub fn empty_string() -> alloc::string::String {
+    alloc::string::String::new()
+}
+
+pub fn buffer(n: usize) -> Vec<u8> {
+    Vec::with_capacity(n)
+}
@@ -0,0 +1,48 @@
+use std::ptr;
+
+#[repr(C, packed)]
+pub struct Header {
+    pub tag: u8,
+    pub len: u32,
+    pub bytes: [u8; 4],
+}
+
+// reading a packed field by value copies it out and is fine
+pub fn len_copy(h: &Header) -> u32 {
+    h.len
+}
+
+// taking a reference to a packed field may create an unaligned reference
+// (newer compilers reject this, but it didn't need an unsafe block)
+pub fn len_ref(h: &Header) -> &u32 {
+    &h.len
+}
+
+// fields with an alignment of 1 are never unaligned
+pub fn bytes_ref(h: &Header) -> &[u8; 4] {
+    &h.bytes
+}
+
+// raw pointers to packed fields don't need to be aligned
+pub fn len_addr(h: &Header) -> *const u32 {
+    ptr::addr_of!(h.len)
+}
+
+pub fn len_raw(h: &mut Header) -> *mut u32 {
+    &raw mut h.len
+}
+
+pub struct Plain {
+    pub len: [u8; 4],
+}
+
+// fields of structs that aren't packed can be referenced freely
+pub fn plain_ref(p: &Plain) -> &[u8; 4] {
+    &p.len
+}
+
+// the closure reads the field by value, only the closure is referenced
+pub fn len_closure(h: &Header) -> u32 {
+    let f = &|| h.len;
+    f()
+}
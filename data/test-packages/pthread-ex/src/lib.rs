@@ -0,0 +1,13 @@
+use std::ptr;
+
+extern "C" fn run(_: *mut libc::c_void) -> *mut libc::c_void {
+    ptr::null_mut()
+}
+
+pub fn raw_thread() {
+    unsafe {
+        let mut thread: libc::pthread_t = std::mem::zeroed();
+        libc::pthread_create(&mut thread, ptr::null(), run, ptr::null_mut());
+        libc::pthread_join(thread, ptr::null_mut());
+    }
+}
@@ -0,0 +1,20 @@
+use std::ops::Add;
+
+pub fn second(p: *const u8) -> u8 {
+    unsafe { *p.add(1) }
+}
+
+pub fn back_and_forth(p: *mut u8) -> *mut u8 {
+    unsafe {
+        let q = p.offset(2);
+        q.sub(2)
+    }
+}
+
+pub fn cast_offset(v: &mut [u8]) -> *mut u8 {
+    (v.as_mut_ptr() as *mut u8).wrapping_offset(1)
+}
+
+pub fn not_a_pointer(x: u32) -> u32 {
+    x.add(1)
+}
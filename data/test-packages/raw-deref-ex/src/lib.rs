@@ -0,0 +1,22 @@
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub fn read_mmio() -> u8 {
+    unsafe { *(0x1000 as *const u8) }
+}
+
+pub fn read_local() -> i32 {
+    let x = 5;
+    unsafe { *(&x as *const i32) }
+}
+
+pub fn read_point(p: &Point) -> i32 {
+    unsafe { (*(p as *const Point)).x }
+}
+
+pub fn read_ref(p: &Point) -> i32 {
+    let r = &p.y;
+    *r
+}
@@ -0,0 +1,7 @@
+/*
+    Example of calling a dependency that is renamed in Cargo.toml
+*/
+
+pub fn clean(path: &str) {
+    perms::remove(path);
+}
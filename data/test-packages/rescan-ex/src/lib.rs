@@ -0,0 +1,9 @@
+mod other;
+
+pub fn lib_var() -> bool {
+    std::env::var("HOME").is_ok()
+}
+
+pub fn call_other() -> bool {
+    other::other_var()
+}
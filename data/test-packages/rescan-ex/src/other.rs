@@ -0,0 +1,3 @@
+pub fn other_var() -> bool {
+    std::env::var("PATH").is_ok()
+}
@@ -0,0 +1,7 @@
+pub fn uninit_buffer() -> Vec<u8> {
+    let mut v = Vec::with_capacity(100);
+    unsafe {
+        v.set_len(100);
+    }
+    v
+}
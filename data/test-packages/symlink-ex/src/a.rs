@@ -0,0 +1,3 @@
+pub fn has_var(name: &str) -> bool {
+    std::env::var(name).is_ok()
+}
@@ -0,0 +1 @@
+a.rs
\ No newline at end of file
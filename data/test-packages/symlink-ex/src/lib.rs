@@ -0,0 +1,5 @@
+mod a;
+
+pub fn home() -> bool {
+    a::has_var("HOME")
+}
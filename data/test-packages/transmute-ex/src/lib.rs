@@ -0,0 +1,19 @@
+use std::mem;
+
+#[repr(C)]
+pub struct Pair {
+    pub a: u16,
+    pub b: u16,
+}
+
+pub fn pair(x: [u16; 2]) -> Pair {
+    unsafe { mem::transmute(x) }
+}
+
+pub fn pair_turbofish(x: [u16; 2]) -> Pair {
+    unsafe { std::mem::transmute::<[u16; 2], Pair>(x) }
+}
+
+pub fn pair_safe(x: [u16; 2]) -> Pair {
+    Pair { a: x[0], b: x[1] }
+}
@@ -0,0 +1,11 @@
+pub fn read() -> Option<()> {
+    trusted_wrapper_ex::read()
+}
+
+pub fn home() -> bool {
+    std::env::var("HOME").is_ok()
+}
+
+pub fn assign(u: &mut union_ex::MyUnion) {
+    union_ex::assign_field(u);
+}
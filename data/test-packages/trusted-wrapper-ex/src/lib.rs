@@ -0,0 +1,3 @@
+pub fn read() -> Option<()> {
+    dependency_ex::read_fn()
+}
@@ -0,0 +1,22 @@
+pub union MyUnion {
+    pub f1: i32,
+    pub f2: u32,
+}
+
+// assigning to a union field is safe
+pub fn assign_field(u: &mut MyUnion) {
+    u.f1 = 10;
+}
+
+// taking a mutable reference to a union field is unsafe
+pub fn borrow_field_mut(u: &mut MyUnion) {
+    let r = unsafe { &mut u.f1 };
+    *r = 10;
+}
+
+// assigning through a mutable reference is still unsafe
+pub fn assign_through_borrow(u: &mut MyUnion) {
+    unsafe {
+        *(&mut u.f2) = 10;
+    }
+}
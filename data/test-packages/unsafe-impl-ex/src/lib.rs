@@ -0,0 +1,18 @@
+use std::cell::Cell;
+
+pub struct Shared {
+    value: Cell<u32>,
+}
+
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+pub struct RawBox<T>(pub *mut T);
+
+unsafe impl<T> Send for RawBox<T> {}
+
+impl Clone for Shared {
+    fn clone(&self) -> Self {
+        Shared { value: Cell::new(self.value.get()) }
+    }
+}
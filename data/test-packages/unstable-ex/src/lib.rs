@@ -0,0 +1,13 @@
+// `UnixStream::peer_cred` is only available on nightly, so this crate only
+// builds with `--cfg nightly` on a nightly toolchain
+#![cfg_attr(nightly, feature(peer_credentials_unix_socket))]
+
+use std::os::unix::net::UnixStream;
+
+pub fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    stream.peer_cred().ok().map(|cred| cred.uid)
+}
+
+pub fn write_file() {
+    std::fs::write("out.txt", "hello").unwrap();
+}
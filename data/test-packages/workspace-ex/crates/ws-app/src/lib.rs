@@ -0,0 +1,6 @@
+pub fn config_or_env() -> String {
+    match std::env::var("APP_CONFIG") {
+        Ok(config) => config,
+        Err(_) => ws_core::read_config(),
+    }
+}
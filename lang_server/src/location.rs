@@ -1,7 +1,10 @@
 use anyhow::{anyhow, Error};
 use cargo_scan::{audit_file::SafetyAnnotation, effect::SrcLoc};
 use lsp_types::{Location, Position, Range, Url};
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 /// Converts Cargo Scan's `SrcLoc` to an appropriate
 /// LSP `Location` to send to the LSP client
@@ -38,6 +41,17 @@ pub fn to_src_loc(location: &Location) -> Result<SrcLoc, Error> {
     Ok(SrcLoc::new(&path, start_line, start_col, end_line, end_col))
 }
 
+/// Checks whether the LSP `Position` in the document at `uri`
+/// falls within Cargo Scan's `SrcLoc`
+pub fn contains_position(loc: &SrcLoc, uri: &Url, position: Position) -> bool {
+    let path = PathBuf::from(uri.path());
+    let pos = (position.line as usize + 1, position.character as usize);
+
+    Path::new(&loc.filepath_string()) == path
+        && (loc.start_line(), loc.start_col()) <= pos
+        && pos <= (loc.end_line(), loc.end_col())
+}
+
 pub fn convert_annotation(annotation: String) -> SafetyAnnotation {
     match annotation.as_str() {
         "Safe" => SafetyAnnotation::Safe,
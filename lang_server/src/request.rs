@@ -7,19 +7,30 @@ use std::{
 use anyhow::{anyhow, Error};
 use home::home_dir;
 use log::info;
-use lsp_types::Location;
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, Command, Hover,
+    HoverContents, HoverParams, Location, MarkupContent, MarkupKind,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use cargo_scan::{
-    audit_file::{AuditFile, EffectInfo, EffectTree},
+    audit_file::{AuditFile, EffectInfo, EffectTree, SafetyAnnotation},
     effect::{self, EffectInstance},
     scan_stats::{get_crate_stats_default, CrateStats},
+    scanner::ScanResults,
     util::load_cargo_toml,
 };
 use serde_with::serde_as;
 
-use crate::location::from_src_loc;
+use crate::{
+    location::{contains_position, from_src_loc},
+    notification::AuditNotificationParams,
+};
+
+/// Command run by the code actions that annotate an effect, with the
+/// `AuditNotificationParams` as its argument
+pub const MARK_EFFECT_COMMAND: &str = "cargo-scan.mark_effect";
 
 #[derive(Serialize, Deserialize, Eq, PartialEq, Hash, Debug, Clone)]
 pub struct EffectsResponse {
@@ -211,3 +222,69 @@ impl CallerCheckedResponse {
         serde_json::to_value(self).map_err(Error::new)
     }
 }
+
+/// Describe the effects of the call under the cursor, if there are any
+pub fn hover_req(scan_res: &ScanResults, params: &HoverParams) -> Result<Value, Error> {
+    let doc_pos = &params.text_document_position_params;
+    let effects = scan_res
+        .effects
+        .iter()
+        .filter(|e| {
+            contains_position(e.call_loc(), &doc_pos.text_document.uri, doc_pos.position)
+        })
+        .collect::<Vec<_>>();
+
+    let hover = effects.first().map(|e| Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: effects
+                .iter()
+                .map(|e| format!("**{}**: `{}`", e.eff_type().to_csv(), e.callee()))
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+        }),
+        range: from_src_loc(e.call_loc()).ok().map(|l| l.range),
+    });
+
+    serde_json::to_value(hover).map_err(Error::new)
+}
+
+/// Offer to mark the unaudited effects under the cursor as safe or
+/// caller-checked
+pub fn code_action_req(
+    audit_file: &AuditFile,
+    params: &CodeActionParams,
+) -> Result<Value, Error> {
+    let mut actions = vec![];
+    for (effect, tree) in audit_file.audit_trees.iter() {
+        let uri = &params.text_document.uri;
+        let unaudited = tree.has_annotation(SafetyAnnotation::Skipped)
+            || tree.has_annotation(SafetyAnnotation::Truncated);
+        if !unaudited || !contains_position(effect.call_loc(), uri, params.range.start) {
+            continue;
+        }
+
+        for (title, annotation) in [
+            ("Mark effect Safe", "Safe"),
+            ("Mark effect Caller-checked", "Caller-Checked"),
+        ] {
+            let args = AuditNotificationParams {
+                safety_annotation: annotation.to_string(),
+                effect: EffectsResponse::new(effect)?,
+                chain_audit_mode: false,
+            };
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("{}: {}", title, effect.callee()),
+                kind: Some(CodeActionKind::QUICKFIX),
+                command: Some(Command {
+                    title: title.to_string(),
+                    command: MARK_EFFECT_COMMAND.to_string(),
+                    arguments: Some(vec![serde_json::to_value(args)?]),
+                }),
+                ..CodeAction::default()
+            }));
+        }
+    }
+
+    serde_json::to_value(actions).map_err(Error::new)
+}
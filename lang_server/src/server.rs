@@ -3,7 +3,7 @@ use std::{collections::HashMap, error::Error, path::PathBuf, str::FromStr};
 use anyhow::anyhow;
 use cargo_scan::{
     audit_chain::Create,
-    audit_file::{AuditFile, EffectInfo},
+    audit_file::{AuditFile, SafetyAnnotation},
     auditing::chain::{Command, CommandRunner, OuterArgs},
     effect::{self},
     ident::CanonicalPath,
@@ -14,21 +14,24 @@ use home::home_dir;
 use log::{debug, info};
 use lsp_server::{Connection, Message};
 use lsp_types::{
-    notification::Notification, request::Request, InitializeParams, ServerCapabilities,
-    TextDocumentSyncCapability, TextDocumentSyncKind,
+    notification::{Notification, PublishDiagnostics},
+    request::{CodeActionRequest, ExecuteCommand, HoverRequest, Request},
+    CodeActionParams, CodeActionProviderCapability, ExecuteCommandOptions,
+    ExecuteCommandParams, HoverParams, HoverProviderCapability, InitializeParams,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    location::to_src_loc,
+    location::convert_annotation,
     notification::{AuditNotification, AuditNotificationParams},
     request::{
-        audit_req, scan_req, AuditCommandResponse, CallerCheckedResponse,
-        EffectsResponse, ScanCommandResponse,
+        audit_req, code_action_req, hover_req, scan_req, AuditCommandResponse,
+        CallerCheckedResponse, EffectsResponse, ScanCommandResponse, MARK_EFFECT_COMMAND,
     },
     util::{
-        add_callers_to_tree, find_effect_instance, get_all_chain_effects,
-        get_new_audit_locs,
+        add_callers_to_audit, get_all_chain_effects, get_new_audit_locs,
+        unaudited_diagnostics,
     },
 };
 
@@ -82,6 +85,12 @@ pub fn run_server() -> anyhow::Result<(), Box<dyn Error + Sync + Send>> {
         text_document_sync: Some(TextDocumentSyncCapability::Kind(
             TextDocumentSyncKind::FULL,
         )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![MARK_EFFECT_COMMAND.to_string()],
+            ..ExecuteCommandOptions::default()
+        }),
         ..ServerCapabilities::default()
     };
 
@@ -143,6 +152,7 @@ fn runner(
                             effects.insert(eff.clone(), tree.get_all_annotations());
                         });
 
+                        publish_diagnostics(conn, &af)?;
                         audit_file = Some(af);
                         audit_file_path = fp;
                         let res = AuditCommandResponse::new(&effects)?.to_json_value()?;
@@ -155,26 +165,20 @@ fn runner(
                     CallerCheckedCommand::METHOD => {
                         let effect = EffectsResponse::from_json_value(req.params)?;
                         let caller_path = CanonicalPath::new_owned(effect.get_caller());
-                        let callee_loc = to_src_loc(&effect.location)?;
 
                         let new_audit_locs = get_new_audit_locs(&scan_res, &caller_path)?;
                         let callers =
                             CallerCheckedResponse::new(&effect, &new_audit_locs)?;
 
                         if let Some(af) = audit_file.as_mut() {
-                            for tree in find_effect_instance(af, effect)? {
-                                let curr_effect = EffectInfo {
-                                    caller_path: caller_path.clone(),
-                                    callee_loc: callee_loc.clone(),
-                                };
-                                add_callers_to_tree(
-                                    new_audit_locs.clone(),
-                                    tree,
-                                    curr_effect,
-                                );
-                            }
-                            af.recalc_pub_caller_checked(&scan_res.pub_fns);
-                            af.save_to_file(audit_file_path.clone())?;
+                            add_callers_to_audit(
+                                af,
+                                &scan_res,
+                                effect,
+                                &new_audit_locs,
+                                &audit_file_path,
+                            )?;
+                            publish_diagnostics(conn, af)?;
                         }
 
                         // send the new audit locations to the client
@@ -185,6 +189,67 @@ fn runner(
                             error: None,
                         }))?;
                     }
+                    HoverRequest::METHOD => {
+                        let params: HoverParams = serde_json::from_value(req.params)?;
+                        let res = hover_req(&scan_res, &params)?;
+                        conn.sender.send(Message::Response(lsp_server::Response {
+                            id: req.id,
+                            result: Some(res),
+                            error: None,
+                        }))?;
+                    }
+                    CodeActionRequest::METHOD => {
+                        let params: CodeActionParams =
+                            serde_json::from_value(req.params)?;
+                        let res = match audit_file.as_ref() {
+                            Some(af) => code_action_req(af, &params)?,
+                            None => serde_json::Value::Null,
+                        };
+                        conn.sender.send(Message::Response(lsp_server::Response {
+                            id: req.id,
+                            result: Some(res),
+                            error: None,
+                        }))?;
+                    }
+                    ExecuteCommand::METHOD => {
+                        let params: ExecuteCommandParams =
+                            serde_json::from_value(req.params)?;
+                        let arg = params.arguments.into_iter().next();
+                        if let (MARK_EFFECT_COMMAND, Some(arg), Some(af)) =
+                            (params.command.as_str(), arg, audit_file.as_mut())
+                        {
+                            let params: AuditNotificationParams =
+                                serde_json::from_value(arg)?;
+                            if convert_annotation(params.safety_annotation.clone())
+                                == SafetyAnnotation::CallerChecked
+                            {
+                                let caller_path =
+                                    CanonicalPath::new_owned(params.effect.get_caller());
+                                let new_audit_locs =
+                                    get_new_audit_locs(&scan_res, &caller_path)?;
+                                add_callers_to_audit(
+                                    af,
+                                    &scan_res,
+                                    params.effect,
+                                    &new_audit_locs,
+                                    &audit_file_path,
+                                )?;
+                            } else {
+                                AuditNotification::annotate_effects_in_single_audit(
+                                    params,
+                                    af,
+                                    &scan_res,
+                                    audit_file_path.clone(),
+                                )?;
+                            }
+                            publish_diagnostics(conn, af)?;
+                        }
+                        conn.sender.send(Message::Response(lsp_server::Response {
+                            id: req.id,
+                            result: Some(serde_json::Value::Null),
+                            error: None,
+                        }))?;
+                    }
                     "cargo-scan.create_chain" => {
                         let outer_args = OuterArgs::default();
                         let root_crate_id = load_cargo_toml(&root_crate_path)?;
@@ -264,6 +329,7 @@ fn runner(
                             &scan_res,
                             audit_file_path.clone(),
                         )?;
+                        publish_diagnostics(conn, af)?;
                     }
                 }
             }
@@ -272,3 +338,19 @@ fn runner(
 
     Ok(())
 }
+
+/// Send a warning for each unaudited effect in the audit file to the client
+fn publish_diagnostics(
+    conn: &lsp_server::Connection,
+    audit_file: &AuditFile,
+) -> anyhow::Result<()> {
+    for params in unaudited_diagnostics(audit_file)? {
+        let notification = Message::Notification(lsp_server::Notification {
+            method: PublishDiagnostics::METHOD.to_string(),
+            params: serde_json::to_value(params)?,
+        });
+        conn.sender.send(notification)?;
+    }
+
+    Ok(())
+}
@@ -1,15 +1,22 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
 
 use anyhow::{anyhow, Error};
 use cargo_scan::{
     audit_chain::{collect_propagated_sinks, AuditChain},
     audit_file::{AuditFile, EffectInfo, EffectTree, SafetyAnnotation},
-    effect::EffectInstance,
+    effect::{EffectInstance, EffectType, Severity},
     ident::CanonicalPath,
     scanner::ScanResults,
 };
+use lsp_types::{Diagnostic, DiagnosticSeverity, PublishDiagnosticsParams, Url};
 
-use crate::{location::to_src_loc, request::EffectsResponse};
+use crate::{
+    location::{from_src_loc, to_src_loc},
+    request::EffectsResponse,
+};
 
 pub fn find_effect_instance(
     audit_file: &mut AuditFile,
@@ -55,6 +62,29 @@ pub fn add_callers_to_tree(
     }
 }
 
+/// Mark the effect as caller-checked in the audit file, with the given
+/// callers as its new locations to audit, and save the audit file
+pub fn add_callers_to_audit(
+    audit_file: &mut AuditFile,
+    scan_res: &ScanResults,
+    effect: EffectsResponse,
+    new_audit_locs: &[EffectTree],
+    audit_file_path: &Path,
+) -> Result<(), Error> {
+    let caller_path = CanonicalPath::new_owned(effect.get_caller());
+    let callee_loc = to_src_loc(&effect.location)?;
+
+    for tree in find_effect_instance(audit_file, effect)? {
+        let curr_effect = EffectInfo {
+            caller_path: caller_path.clone(),
+            callee_loc: callee_loc.clone(),
+        };
+        add_callers_to_tree(new_audit_locs.to_vec(), tree, curr_effect);
+    }
+    audit_file.recalc_pub_caller_checked(&scan_res.pub_fns);
+    audit_file.save_to_file(audit_file_path.to_path_buf())
+}
+
 pub fn get_all_chain_effects(
     chain_manifest: &Path,
 ) -> Result<HashMap<EffectInstance, Vec<(EffectInfo, String)>>, Error> {
@@ -69,3 +99,46 @@ pub fn get_all_chain_effects(
     chain.remove_cross_crate_effects(removed_sinks, &chain.root_crate()?)?;
     collect_propagated_sinks(&mut chain)
 }
+
+/// Get a warning for each base effect in the audit file that still has
+/// unaudited locations, grouped by file. Every file with base effects is
+/// included, even without warnings, so that warnings for effects that have
+/// since been audited are cleared.
+pub fn unaudited_diagnostics(
+    audit_file: &AuditFile,
+) -> Result<Vec<PublishDiagnosticsParams>, Error> {
+    let mut diagnostics: BTreeMap<Url, Vec<Diagnostic>> = BTreeMap::new();
+
+    for (effect, tree) in audit_file.audit_trees.iter() {
+        let location = from_src_loc(effect.call_loc())?;
+        let file_diagnostics = diagnostics.entry(location.uri).or_default();
+        if !tree.has_annotation(SafetyAnnotation::Skipped)
+            && !tree.has_annotation(SafetyAnnotation::Truncated)
+        {
+            continue;
+        }
+
+        let eff_type = EffectType::from(effect.eff_type());
+        let severity = match eff_type.severity() {
+            Severity::Low => DiagnosticSeverity::INFORMATION,
+            Severity::Medium => DiagnosticSeverity::WARNING,
+            Severity::High => DiagnosticSeverity::ERROR,
+        };
+        file_diagnostics.push(Diagnostic {
+            range: location.range,
+            severity: Some(severity),
+            source: Some("cargo-scan".to_string()),
+            message: format!("Unaudited {} effect: {}", eff_type, effect.callee()),
+            ..Diagnostic::default()
+        });
+    }
+
+    Ok(diagnostics
+        .into_iter()
+        .map(|(uri, diagnostics)| PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        })
+        .collect())
+}
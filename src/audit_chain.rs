@@ -5,9 +5,10 @@ use cargo::util::context::GlobalContext;
 use cargo_lock::{Dependency, Lockfile, Package};
 use cargo_toml::Manifest;
 use clap::Args as ClapArgs;
-use log::info;
+use log::{info, warn};
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::{Dfs, DfsPostOrder};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::fs::{create_dir_all, remove_file, File};
@@ -17,6 +18,7 @@ use std::mem;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use toml;
+use url::Url;
 
 use crate::audit_file::{AuditFile, AuditVersion, DefaultAuditType, EffectInfo};
 use crate::effect::{EffectInstance, EffectType, DEFAULT_EFFECT_TYPES};
@@ -30,6 +32,10 @@ pub struct AuditChain {
     crate_path: PathBuf,
     crate_policies: HashMap<CrateId, (PathBuf, AuditVersion)>,
     scanned_effects: Vec<EffectType>,
+    /// Crates trusted with `--trust`. Their dependencies are only audited if
+    /// another crate depends on them.
+    #[serde(default)]
+    trusted_crates: HashSet<CrateId>,
 }
 
 impl AuditChain {
@@ -43,6 +49,7 @@ impl AuditChain {
             crate_path,
             crate_policies: HashMap::new(),
             scanned_effects,
+            trusted_crates: HashSet::new(),
         }
     }
 
@@ -89,6 +96,12 @@ impl AuditChain {
         self.crate_policies.insert(crate_id, (audit_file_loc, version));
     }
 
+    /// Where to cache the scan results of a crate in the chain: next to its
+    /// audit file, with a `.scan` extension
+    pub fn scan_cache_path(&self, crate_id: &CrateId) -> Option<PathBuf> {
+        self.crate_policies.get(crate_id).map(|(p, _)| p.with_extension("scan"))
+    }
+
     pub fn read_audit_file(&mut self, crate_id: &CrateId) -> Result<Option<AuditFile>> {
         let (audit_file_path, expected_version) = self
             .crate_policies
@@ -319,6 +332,13 @@ pub struct Create {
     #[clap(short = 'v', long)]
     pub download_version: Option<String>,
 
+    /// Registry mirror to download the root crate from instead of crates.io:
+    /// a base URL serving `<name>/<version>/download`, or a `file://` URL of
+    /// a directory of `<name>-<version>.crate` files
+    #[clap(long, requires = "download_root_crate")]
+    #[serde(default)]
+    pub registry: Option<Url>,
+
     /// The types of Effects the audit should track. Defaults to all unsafe
     /// behavior.
     #[clap(long, value_parser, num_args = 1.., default_values_t = [
@@ -327,12 +347,39 @@ pub struct Create {
         EffectType::UnsafeCall,
         EffectType::RawPointer,
         EffectType::UnionField,
+        EffectType::PackedFieldAccess,
         EffectType::StaticMut,
         EffectType::StaticExt,
         EffectType::FnPtrCreation,
         EffectType::ClosureCreation,
+        EffectType::Transmute,
+        EffectType::RawAlloc,
+        EffectType::CStrPtr,
+        EffectType::RawThread,
+        EffectType::UntrustedInput,
+        EffectType::PtrOffset,
+        EffectType::FFIExport,
     ])]
     pub effect_types: Vec<EffectType>,
+
+    /// Maximum number of crates to scan in parallel when creating audit
+    /// files. Defaults to the number of CPUs.
+    #[clap(short = 'j', long)]
+    pub jobs: Option<usize>,
+
+    /// Crates to trust (can be repeated). They aren't scanned and their
+    /// audits have no effects, so none of their functions are sinks for the
+    /// crates that use them. Their dependencies aren't audited either, unless
+    /// an untrusted crate depends on them.
+    #[clap(long = "trust", value_name = "CRATE")]
+    #[serde(default)]
+    pub trusted_crates: Vec<String>,
+
+    /// Maximum depth of the caller-checked trees in the default audits;
+    /// deeper callers are marked truncated, to review manually
+    #[clap(long)]
+    #[serde(default)]
+    pub max_depth: Option<usize>,
 }
 
 impl Create {
@@ -352,7 +399,11 @@ impl Create {
             force_overwrite,
             download_root_crate,
             download_version,
+            registry: None,
             effect_types,
+            jobs: None,
+            trusted_crates: Vec::new(),
+            max_depth: None,
         }
     }
 }
@@ -375,7 +426,11 @@ impl Default for Create {
             force_overwrite: false,
             download_root_crate: None,
             download_version: None,
+            registry: None,
             effect_types: DEFAULT_EFFECT_TYPES.to_vec(),
+            jobs: None,
+            trusted_crates: Vec::new(),
+            max_depth: None,
         }
     }
 }
@@ -427,6 +482,14 @@ fn make_dependency_graph(
     (graph, package_map, root_idx)
 }
 
+/// Remove the edges out of the trusted nodes, so the dependencies of trusted
+/// crates are only reached through crates that aren't trusted
+fn remove_trusted_deps(graph: &mut DiGraph<String, ()>, trusted: &HashSet<NodeIndex>) {
+    graph.retain_edges(|g, e| {
+        g.edge_endpoints(e).is_some_and(|(from, _)| !trusted.contains(&from))
+    });
+}
+
 fn collect_dependency_sinks(
     chain: &mut AuditChain,
     deps: &Vec<Dependency>,
@@ -443,19 +506,54 @@ fn collect_dependency_sinks(
     Ok(sinks)
 }
 
-/// Creates a new default audit file for the given package and returns the path to
-/// the saved audit file
-#[allow(clippy::too_many_arguments)]
-fn make_new_audit_file(
+/// A default audit file that still has to be created by scanning its crate
+struct PendingAuditFile<'a> {
+    package: &'a Package,
+    package_path: PathBuf,
+    audit_file_path: PathBuf,
+    /// None for a trusted crate, which isn't scanned
+    audit_type: Option<DefaultAuditType>,
+    sinks: HashSet<CanonicalPath>,
+}
+
+impl PendingAuditFile<'_> {
+    /// Scan the crate and save its default audit file
+    fn create(
+        self,
+        relevant_effects: &[EffectType],
+        quick_mode: bool,
+        max_depth: Option<usize>,
+    ) -> Result<(Self, AuditVersion)> {
+        info!("Making default audit for {} v{}", self.package.name, self.package.version);
+        let audit_file = match self.audit_type {
+            Some(audit_type) => AuditFile::new_default_with_sinks(
+                &self.package_path,
+                self.sinks.clone(),
+                audit_type,
+                relevant_effects,
+                quick_mode,
+                max_depth,
+            )?,
+            None => {
+                AuditFile::empty(self.package_path.clone(), relevant_effects.to_vec())?
+            }
+        };
+        audit_file.save_to_file(self.audit_file_path.clone())?;
+
+        Ok((self, audit_file.version))
+    }
+}
+
+/// Adds the existing audit file for the given package to the chain, or returns
+/// the audit file that has to be created for it if there isn't one
+fn prepare_new_audit_file<'a>(
     chain: &mut AuditChain,
-    package: &Package,
+    package: &'a Package,
     root_name: &str,
     args: &Create,
     crate_path: &Path,
-    audit_type: DefaultAuditType,
-    relevant_effects: &[EffectType],
-    quick_mode: bool,
-) -> Result<()> {
+    audit_type: Option<DefaultAuditType>,
+) -> Result<Option<PendingAuditFile<'a>>> {
     let audit_file_path = PathBuf::from(format!(
         "{}/{}-{}.audit",
         args.audit_path,
@@ -491,24 +589,50 @@ fn make_new_audit_file(
                 })?;
             chain.add_crate_audit_file(package, audit_file_path, audit_file.version);
 
-            return Ok(());
+            return Ok(None);
         }
     }
 
-    info!("Making default audit for {} v{}", package.name, package.version);
-    let sinks = collect_dependency_sinks(chain, &package.dependencies)?;
-    let audit_file = AuditFile::new_default_with_sinks(
-        &package_path,
-        sinks,
+    // The dependencies of trusted crates may not have been audited
+    let sinks = match audit_type {
+        Some(_) => collect_dependency_sinks(chain, &package.dependencies)?,
+        None => HashSet::new(),
+    };
+    Ok(Some(PendingAuditFile {
+        package,
+        package_path,
+        audit_file_path,
         audit_type,
-        relevant_effects,
-        quick_mode,
-    )?;
-    audit_file.save_to_file(audit_file_path.clone())?;
+        sinks,
+    }))
+}
 
-    chain.add_crate_audit_file(package, audit_file_path, audit_file.version);
+/// Groups the packages reachable from the root by their height in the
+/// dependency graph, so every package comes after all of its dependencies and
+/// packages in the same group don't depend on each other.
+fn dependency_levels(
+    graph: &DiGraph<String, ()>,
+    root_node: NodeIndex,
+) -> Vec<Vec<NodeIndex>> {
+    let mut heights: HashMap<NodeIndex, usize> = HashMap::new();
+    let mut levels: Vec<Vec<NodeIndex>> = Vec::new();
+
+    let mut traverse = DfsPostOrder::new(graph, root_node);
+    while let Some(node) = traverse.next(graph) {
+        let height = graph
+            .neighbors(node)
+            .filter_map(|dep| heights.get(&dep))
+            .map(|h| h + 1)
+            .max()
+            .unwrap_or(0);
+        heights.insert(node, height);
+        if levels.len() <= height {
+            levels.resize(height + 1, Vec::new());
+        }
+        levels[height].push(node);
+    }
 
-    Ok(())
+    levels
 }
 
 pub fn create_new_audit_chain(
@@ -547,32 +671,70 @@ pub fn create_new_audit_chain(
         }));
 
     info!("Creating dependency graph");
-    let (graph, package_map, root_node) =
+    let (mut graph, package_map, root_node) =
         make_dependency_graph(&lockfile.packages, &root_name);
-    let mut traverse = DfsPostOrder::new(&graph, root_node);
-    while let Some(node) = traverse.next(&graph) {
-        let package = package_map.get(&node).unwrap();
 
-        let audit_type = if node == root_node {
-            DefaultAuditType::Empty
-        } else {
-            DefaultAuditType::CallerChecked
-        };
+    let trusted_crates =
+        args.trusted_crates.iter().map(String::as_str).collect::<HashSet<_>>();
+    for name in &trusted_crates {
+        if !lockfile.packages.iter().any(|p| p.name.as_str() == *name) {
+            warn!("Trusted crate {} isn't a dependency of the crate", name);
+        }
+    }
+    let trusted_nodes = package_map
+        .iter()
+        .filter(|(node, p)| {
+            **node != root_node && trusted_crates.contains(p.name.as_str())
+        })
+        .map(|(node, _)| *node)
+        .collect::<HashSet<_>>();
+    remove_trusted_deps(&mut graph, &trusted_nodes);
+
+    // A package's audit file needs the sinks from its dependencies' audit
+    // files, so dependencies are done first. Packages at the same level of
+    // the graph are independent, so their crates are scanned in parallel.
+    let pool =
+        rayon::ThreadPoolBuilder::new().num_threads(args.jobs.unwrap_or(0)).build()?;
+    for level in dependency_levels(&graph, root_node) {
+        let mut pending = Vec::new();
+        for node in level {
+            let package = package_map.get(&node).unwrap();
+
+            let audit_type = if node == root_node {
+                Some(DefaultAuditType::Empty)
+            } else if trusted_nodes.contains(&node) {
+                info!("Trusting {} v{}", package.name, package.version);
+                chain.trusted_crates.insert(CrateId::from(package));
+                None
+            } else {
+                Some(DefaultAuditType::CallerChecked)
+            };
+
+            let crate_download_path = crate_paths
+                .get(&CrateId::from(package))
+                .context("Unresolved path for a crate")?;
+
+            if let Some(p) = prepare_new_audit_file(
+                &mut chain,
+                package,
+                &root_name,
+                &args,
+                crate_download_path,
+                audit_type,
+            )? {
+                pending.push(p);
+            }
+        }
 
-        let crate_download_path = crate_paths
-            .get(&CrateId::from(package))
-            .context("Unresolved path for a crate")?;
-
-        make_new_audit_file(
-            &mut chain,
-            package,
-            &root_name,
-            &args,
-            crate_download_path,
-            audit_type,
-            &args.effect_types,
-            quick_mode,
-        )?;
+        let created = pool.install(|| {
+            pending
+                .into_par_iter()
+                .map(|p| p.create(&args.effect_types, quick_mode, args.max_depth))
+                .collect::<Result<Vec<_>>>()
+        })?;
+        for (p, version) in created {
+            chain.add_crate_audit_file(p.package, p.audit_file_path, version);
+        }
     }
 
     info!("Finished creating audit chain");
@@ -590,8 +752,14 @@ pub fn collect_propagated_sinks(
     let root_name = chain.root_crate()?;
     let lockfile = chain.load_lockfile()?;
 
-    let (graph, package_map, root_node) =
+    let (mut graph, package_map, root_node) =
         make_dependency_graph(&lockfile.packages, &root_name.to_string());
+    let trusted_nodes = package_map
+        .iter()
+        .filter(|(_, p)| chain.trusted_crates.contains(&CrateId::from(*p)))
+        .map(|(node, _)| *node)
+        .collect::<HashSet<_>>();
+    remove_trusted_deps(&mut graph, &trusted_nodes);
     let mut traverse = Dfs::new(&graph, root_node);
     while let Some(node) = traverse.next(&graph) {
         let package = package_map.get(&node).unwrap();
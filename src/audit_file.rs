@@ -1,11 +1,12 @@
 use super::effect::{EffectInstance, SrcLoc};
-use crate::auditing::util::{hash_dir, MAX_AUDIT_FILE_SIZE};
+use crate::auditing::info::effect_label;
+use crate::auditing::util::{hash_dir, pattern_matches, MAX_AUDIT_FILE_SIZE};
 use crate::effect::{Effect, EffectType};
 use crate::ident::CanonicalPath;
 use crate::scanner;
 use crate::scanner::ScanResults;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::io::Write;
@@ -13,17 +14,22 @@ use std::path::Path as FilePath;
 use std::path::PathBuf;
 
 use anyhow::{anyhow, Result};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
 /// SafetyAnnotation is really a lattice with `Skipped` as the top element, and
-/// `Unsafe` as the bottom element.
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+/// `Unsafe` as the bottom element. `Truncated` sits at the top with `Skipped`:
+/// the callers past it haven't been audited yet.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum SafetyAnnotation {
     Skipped,
     Safe,
     Unsafe,
     CallerChecked,
+    /// A caller-checked tree was cut off here at the maximum depth, so the
+    /// callers of this location still need to be reviewed manually
+    Truncated,
 }
 
 impl fmt::Display for SafetyAnnotation {
@@ -34,6 +40,7 @@ impl fmt::Display for SafetyAnnotation {
             SafetyAnnotation::Safe => write!(f, "Safe"),
             SafetyAnnotation::Unsafe => write!(f, "Unsafe"),
             SafetyAnnotation::CallerChecked => write!(f, "Caller-checked"),
+            SafetyAnnotation::Truncated => write!(f, "Truncated (review manually)"),
         }
     }
 }
@@ -71,6 +78,18 @@ impl EffectTree {
         }
     }
 
+    /// Returns true if any location in the tree has the annotation. Branch
+    /// nodes are caller-checked.
+    pub fn has_annotation(&self, annotation: SafetyAnnotation) -> bool {
+        match self {
+            EffectTree::Leaf(_, a) => *a == annotation,
+            EffectTree::Branch(_, next) => {
+                annotation == SafetyAnnotation::CallerChecked
+                    || next.iter().any(|t| t.has_annotation(annotation))
+            }
+        }
+    }
+
     pub fn get_all_annotations(&self) -> Vec<(EffectInfo, String)> {
         match self {
             EffectTree::Leaf(i, a) => vec![(i.clone(), a.to_string())],
@@ -117,6 +136,20 @@ impl EffectTree {
             _ => {}
         }
     }
+    /// Move all locations in the tree from under `old_base` to under
+    /// `new_base` (see `SrcLoc::rebase`)
+    pub fn rebase(&mut self, old_base: &FilePath, new_base: &FilePath) {
+        match self {
+            EffectTree::Leaf(e, _) => e.callee_loc.rebase(old_base, new_base),
+            EffectTree::Branch(e, next) => {
+                e.callee_loc.rebase(old_base, new_base);
+                for t in next {
+                    t.rebase(old_base, new_base);
+                }
+            }
+        }
+    }
+
     pub fn get_effect_infos(&self) -> HashSet<EffectInfo> {
         match self {
             EffectTree::Leaf(e, _) => vec![e.clone()].into_iter().collect::<HashSet<_>>(),
@@ -217,7 +250,10 @@ impl AuditFile {
     }
 
     /// Mark caller-checked functions but don't add a caller to the tree more
-    /// than once (so we don't get an infinite cycle).
+    /// than once (so we don't get an infinite cycle). Callers more than
+    /// `max_depth` calls away from the base effect are not added; the leaves
+    /// at the maximum depth are marked `Truncated` instead.
+    #[allow(clippy::too_many_arguments)]
     fn mark_caller_checked_recurse(
         base_effect: &EffectInstance,
         tree: &mut EffectTree,
@@ -225,6 +261,8 @@ impl AuditFile {
         scan_res: &ScanResults,
         prev_callers: &mut HashSet<CanonicalPath>,
         tree_size: &mut i32,
+        depth: usize,
+        max_depth: Option<usize>,
     ) -> Result<()> {
         // TODO: Make this configurable/obsolete
         // if *tree_size > MAX_CALLER_CHECKED_TREE_SIZE {
@@ -252,6 +290,8 @@ impl AuditFile {
                 .collect::<Vec<_>>();
             if callers.is_empty() {
                 *annotation = SafetyAnnotation::CallerChecked;
+            } else if max_depth.is_some_and(|max| depth >= max) {
+                *annotation = SafetyAnnotation::Truncated;
             } else {
                 for eff in callers.iter_mut() {
                     *tree_size += 1;
@@ -273,6 +313,8 @@ impl AuditFile {
                         scan_res,
                         prev_callers,
                         tree_size,
+                        depth + 1,
+                        max_depth,
                     )?;
                 }
                 *tree = EffectTree::Branch(effect_info.clone(), callers);
@@ -282,13 +324,15 @@ impl AuditFile {
         Ok(())
     }
 
-    /// Mark all callers of functions in the effect tree to be caller-checked.
+    /// Mark all callers of functions in the effect tree to be caller-checked,
+    /// up to `max_depth` calls away from the base effect.
     fn mark_caller_checked(
         base_effect: &EffectInstance,
         tree: &mut EffectTree,
         pub_caller_checked: &mut HashMap<CanonicalPath, HashSet<EffectInstance>>,
         scan_res: &ScanResults,
         tree_size: &mut i32,
+        max_depth: Option<usize>,
     ) -> Result<()> {
         let mut callers = HashSet::new();
         callers.insert(base_effect.caller().clone());
@@ -299,6 +343,8 @@ impl AuditFile {
             scan_res,
             &mut callers,
             tree_size,
+            0,
+            max_depth,
         )
     }
 
@@ -309,7 +355,10 @@ impl AuditFile {
         pub_fns: &HashSet<CanonicalPath>,
     ) {
         match tree {
-            EffectTree::Leaf(info, SafetyAnnotation::CallerChecked) => {
+            // Truncated leaves are added to the sinks just like caller-checked
+            // ones when the tree is created
+            EffectTree::Leaf(info, SafetyAnnotation::CallerChecked)
+            | EffectTree::Leaf(info, SafetyAnnotation::Truncated) => {
                 if pub_fns.contains(&info.caller_path) {
                     pub_caller_checked
                         .get_mut(&info.caller_path)
@@ -382,6 +431,31 @@ impl AuditFile {
         self.pub_caller_checked = pub_caller_checked;
     }
 
+    /// Returns the public functions marked caller-checked, grouped by the types
+    /// of the base effects that flow into them. These are the sinks the crate
+    /// exports to its dependents. `main` can't be called by dependents, so it's
+    /// left out.
+    pub fn exported_sinks(&self) -> BTreeMap<EffectType, Vec<CanonicalPath>> {
+        let mut sinks: BTreeMap<EffectType, Vec<CanonicalPath>> = BTreeMap::new();
+        for (pub_fn, effects) in &self.pub_caller_checked {
+            if pub_fn.is_main() {
+                continue;
+            }
+            let effect_types = effects
+                .iter()
+                .map(|e| EffectType::from(e.eff_type()))
+                .collect::<HashSet<_>>();
+            for effect_type in effect_types {
+                sinks.entry(effect_type).or_default().push(pub_fn.clone());
+            }
+        }
+        for pub_fns in sinks.values_mut() {
+            pub_fns.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        }
+
+        sinks
+    }
+
     /// Returns the list of all safe public functions (these include all the
     /// public functions which have been removed since the last audit update).
     pub fn safe_pub_fns(&self) -> HashSet<CanonicalPath> {
@@ -410,11 +484,13 @@ impl AuditFile {
         self.audit_trees.values().any(tree_walk)
     }
 
-    /// Returns the total number of unaudited leaf nodes.
+    /// Returns the total number of unaudited leaf nodes, including truncated
+    /// ones.
     fn total_unaudited_effects(t: &EffectTree) -> usize {
         let mut total = 0;
         match t {
-            EffectTree::Leaf(_, SafetyAnnotation::Skipped) => {
+            EffectTree::Leaf(_, SafetyAnnotation::Skipped)
+            | EffectTree::Leaf(_, SafetyAnnotation::Truncated) => {
                 total += 1;
             }
             EffectTree::Leaf(_, _) => (),
@@ -478,6 +554,118 @@ impl AuditFile {
         }
     }
 
+    /// Produce a Markdown report of the audit, to attach to PRs and security
+    /// reviews. It starts with the same summary as `print_audit_stats`, then
+    /// lists each base effect grouped by file, with its annotation and the
+    /// public functions it makes caller-checked.
+    pub fn to_markdown_report(&self) -> String {
+        fn has_unsafe(tree: &EffectTree) -> bool {
+            match tree {
+                EffectTree::Leaf(_, a) => *a == SafetyAnnotation::Unsafe,
+                EffectTree::Branch(_, ts) => ts.iter().any(has_unsafe),
+            }
+        }
+        fn annotation_str(tree: &EffectTree) -> String {
+            match tree {
+                EffectTree::Leaf(_, a) => a.to_string(),
+                EffectTree::Branch(..) if has_unsafe(tree) => {
+                    "Caller-checked (unsafe caller)".to_string()
+                }
+                EffectTree::Branch(..)
+                    if AuditFile::total_unaudited_effects(tree) > 0 =>
+                {
+                    "Caller-checked (callers not fully audited)".to_string()
+                }
+                EffectTree::Branch(..) => SafetyAnnotation::CallerChecked.to_string(),
+            }
+        }
+        // Keep table cells on one row
+        fn cell(s: &str) -> String {
+            s.replace('|', "\\|").replace('\n', " ")
+        }
+
+        let (_, unaudited_total) = self.unaudited_effects();
+        let num_unsafe = self.audit_trees.values().filter(|t| has_unsafe(t)).count();
+        let num_caller_checked = self
+            .audit_trees
+            .values()
+            .filter(|t| {
+                matches!(
+                    t,
+                    EffectTree::Branch(..)
+                        | EffectTree::Leaf(_, SafetyAnnotation::CallerChecked)
+                )
+            })
+            .count();
+
+        let mut report = String::new();
+        let crate_name = self
+            .base_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.base_dir.to_string_lossy().to_string());
+        report.push_str(&format!("# Audit report: {}\n\n", crate_name));
+        report.push_str(&format!("- Total base effects: {}\n", self.audit_trees.len()));
+        report.push_str(&format!("- Unsafe: {}\n", num_unsafe));
+        report.push_str(&format!("- Caller-checked: {}\n", num_caller_checked));
+        report.push_str(&format!(
+            "- Public functions marked caller-checked: {}\n",
+            self.pub_caller_checked.len()
+        ));
+        if self.has_unsafe_effect() {
+            report.push_str("- Package marked **UNSAFE**\n");
+        }
+        if unaudited_total == 0 {
+            report.push_str("- Package fully audited\n");
+        } else {
+            report.push_str(&format!(
+                "- Unaudited locations remaining: {}\n",
+                unaudited_total
+            ));
+        }
+
+        // Invert the caller-checked map so we can look up the public functions
+        // each effect flows into
+        let mut effect_pub_fns: HashMap<&EffectInstance, Vec<&str>> = HashMap::new();
+        for (pub_fn, effects) in &self.pub_caller_checked {
+            for e in effects {
+                effect_pub_fns.entry(e).or_default().push(pub_fn.as_str());
+            }
+        }
+
+        let mut by_file: BTreeMap<String, Vec<&EffectInstance>> = BTreeMap::new();
+        for e in self.audit_trees.keys() {
+            by_file.entry(e.call_loc().filepath_string()).or_default().push(e);
+        }
+
+        for (file, mut effects) in by_file {
+            effects
+                .sort_by_key(|e| (e.call_loc().start_line(), e.call_loc().start_col()));
+
+            report.push_str(&format!("\n## {}\n\n", file));
+            report.push_str(
+                "| Line | Function | Effect | Annotation | Caller-checked public functions |\n",
+            );
+            report.push_str("|---|---|---|---|---|\n");
+            for e in effects {
+                let mut pub_fns = effect_pub_fns.get(e).cloned().unwrap_or_default();
+                pub_fns.sort();
+                let pub_fns =
+                    pub_fns.iter().map(|f| format!("`{}`", f)).collect::<Vec<_>>();
+                report.push_str(&format!(
+                    "| {} | `{}` | {} | {} | {} |\n",
+                    e.call_loc().start_line(),
+                    e.caller_path(),
+                    cell(&effect_label(e, &EffectInfo::from_instance(e))),
+                    annotation_str(&self.audit_trees[e]),
+                    pub_fns.join(", "),
+                ));
+            }
+        }
+
+        report
+    }
+
     /// Removes any effect trees which have the given sink as the root. Returns
     /// the removed effects.
     pub fn remove_sinks_from_tree(
@@ -516,17 +704,20 @@ impl AuditFile {
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        max_file_size: Option<u64>,
     ) -> Result<(AuditFile, ScanResults)> {
         let mut audit_file =
             AuditFile::empty(crate_path.to_path_buf(), relevant_effects.to_vec())?;
         let ident_sinks =
             sinks.iter().map(|x| x.clone().to_path()).collect::<HashSet<_>>();
-        let scan_res = scanner::scan_crate_with_sinks(
-            crate_path,
-            ident_sinks,
-            relevant_effects,
-            quick,
-        )?;
+        let options = scanner::ScanOptions {
+            sinks: ident_sinks,
+            quick_mode: quick,
+            max_file_size,
+            ..scanner::ScanOptions::default()
+        };
+        let scan_res =
+            scanner::scan_crate_with_options(crate_path, relevant_effects, &options)?;
         audit_file.set_base_audit_trees(scan_res.effects_set());
 
         Ok((audit_file, scan_res))
@@ -542,6 +733,7 @@ impl AuditFile {
             HashSet::new(),
             relevant_effects,
             quick_mode,
+            None,
         )
     }
 
@@ -555,6 +747,8 @@ impl AuditFile {
             HashSet::new(),
             relevant_effects,
             quick,
+            None,
+            None,
         )
     }
 
@@ -563,24 +757,61 @@ impl AuditFile {
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        max_depth: Option<usize>,
     ) -> Result<AuditFile> {
         Self::new_caller_checked_default_with_sinks_and_results(
             crate_path,
             sinks,
             relevant_effects,
             quick,
+            None,
+            max_depth,
         )
         .map(|x| x.0)
     }
 
+    /// Scan the crate and mark the effects of public functions caller-checked.
+    /// Source files larger than `max_file_size` bytes are skipped. Callers more
+    /// than `max_depth` calls away from an effect are left out of its tree,
+    /// which is marked `Truncated` where it was cut off.
     pub fn new_caller_checked_default_with_sinks_and_results(
         crate_path: &FilePath,
         sinks: HashSet<CanonicalPath>,
         relevant_effects: &[EffectType],
         quick: bool,
+        max_file_size: Option<u64>,
+        max_depth: Option<usize>,
     ) -> Result<(AuditFile, ScanResults)> {
-        let (mut audit_file, scan_res) =
-            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick)?;
+        let options = scanner::ScanOptions {
+            sinks: sinks.iter().map(|x| x.clone().to_path()).collect(),
+            quick_mode: quick,
+            max_file_size,
+            ..scanner::ScanOptions::default()
+        };
+        let scan_res =
+            scanner::scan_crate_with_options(crate_path, relevant_effects, &options)?;
+        let audit_file = Self::new_caller_checked_from_results(
+            crate_path,
+            relevant_effects,
+            &scan_res,
+            max_depth,
+        )?;
+
+        Ok((audit_file, scan_res))
+    }
+
+    /// Create an audit file for the results of an existing scan of the crate,
+    /// with the effects of public functions marked caller-checked as in
+    /// `new_caller_checked_default_with_sinks_and_results`
+    pub fn new_caller_checked_from_results(
+        crate_path: &FilePath,
+        relevant_effects: &[EffectType],
+        scan_res: &ScanResults,
+        max_depth: Option<usize>,
+    ) -> Result<AuditFile> {
+        let mut audit_file =
+            AuditFile::empty(crate_path.to_path_buf(), relevant_effects.to_vec())?;
+        audit_file.set_base_audit_trees(scan_res.effects_set());
 
         let mut total_size = 0i32;
         let mut pub_caller_checked = HashMap::new();
@@ -590,8 +821,9 @@ impl AuditFile {
                 e,
                 t,
                 &mut pub_caller_checked,
-                &scan_res,
+                scan_res,
                 &mut tree_size,
+                max_depth,
             )?;
             total_size += tree_size;
             // TODO: Make this configurable/obsolete
@@ -602,7 +834,7 @@ impl AuditFile {
 
         audit_file.pub_caller_checked = pub_caller_checked;
 
-        Ok((audit_file, scan_res))
+        Ok(audit_file)
     }
 
     pub fn new_empty_default_with_sinks(
@@ -620,6 +852,7 @@ impl AuditFile {
             ident_sinks,
             relevant_effects,
             quick,
+            &[],
         )?;
         audit_file.set_base_audit_trees(scan_res.effects_set());
 
@@ -633,7 +866,7 @@ impl AuditFile {
         quick: bool,
     ) -> Result<AuditFile> {
         let (mut audit_file, _scan_res) =
-            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick)?;
+            Self::scan_with_sinks(crate_path, sinks, relevant_effects, quick, None)?;
         for (_, mut t) in audit_file.audit_trees.iter_mut() {
             if let EffectTree::Leaf(_, a) = &mut t {
                 *a = SafetyAnnotation::Safe;
@@ -649,6 +882,7 @@ impl AuditFile {
         audit_type: DefaultAuditType,
         relevant_effects: &[EffectType],
         quick: bool,
+        max_depth: Option<usize>,
     ) -> Result<AuditFile> {
         match audit_type {
             DefaultAuditType::CallerChecked => {
@@ -657,6 +891,7 @@ impl AuditFile {
                     sinks,
                     relevant_effects,
                     quick,
+                    max_depth,
                 )
             }
             DefaultAuditType::Empty => Self::new_empty_default_with_sinks(
@@ -685,4 +920,298 @@ impl AuditFile {
             .cloned()
             .collect::<HashSet<CanonicalPath>>()
     }
+
+    /// Move the audit to a crate at `new_base`, e.g. the same crate checked out
+    /// at a different path
+    fn rebased(&self, new_base: &FilePath) -> AuditFile {
+        let old_base = self.base_dir.as_path();
+        let rebase_effect = |e: &EffectInstance| {
+            let mut e = e.clone();
+            e.rebase(old_base, new_base);
+            e
+        };
+
+        let mut rebased = self.clone();
+        rebased.base_dir = new_base.to_path_buf();
+        rebased.audit_trees = self
+            .audit_trees
+            .iter()
+            .map(|(e, t)| {
+                let mut t = t.clone();
+                t.rebase(old_base, new_base);
+                (rebase_effect(e), t)
+            })
+            .collect();
+        rebased.pub_caller_checked = self
+            .pub_caller_checked
+            .iter()
+            .map(|(f, effects)| (f.clone(), effects.iter().map(rebase_effect).collect()))
+            .collect();
+        rebased
+    }
+
+    /// Merge the annotations from `other`, an audit of the same version of the
+    /// crate (possibly at a different path), into this audit. For each effect
+    /// in both audits, an audited tree is kept over an unaudited one; if both
+    /// are audited differently, this audit's tree is kept and the conflict is
+    /// reported. Effects only in `other` are added unaudited.
+    pub fn merge(&mut self, other: &AuditFile) -> Result<MergeReport> {
+        if self.hash != other.hash {
+            return Err(anyhow!(
+                "Can't merge audits of different versions of a crate ({} and {})",
+                self.base_dir.display(),
+                other.base_dir.display()
+            ));
+        }
+        let other = other.rebased(&self.base_dir);
+        let is_unaudited =
+            |t: &EffectTree| t.get_leaf_annotation() == Some(SafetyAnnotation::Skipped);
+
+        let mut report = MergeReport::default();
+        for (e, theirs) in &other.audit_trees {
+            match self.audit_trees.get_mut(e) {
+                None => {
+                    self.audit_trees.insert(
+                        e.clone(),
+                        EffectTree::Leaf(
+                            EffectInfo::from_instance(e),
+                            SafetyAnnotation::Skipped,
+                        ),
+                    );
+                    report.added.push(e.clone());
+                }
+                Some(ours) if is_unaudited(theirs) || ours == theirs => (),
+                Some(ours) if is_unaudited(ours) => {
+                    *ours = theirs.clone();
+                    // Keep the public functions the adopted tree flows into
+                    for (f, effects) in &other.pub_caller_checked {
+                        if effects.contains(e) {
+                            self.pub_caller_checked
+                                .entry(f.clone())
+                                .or_default()
+                                .insert(e.clone());
+                        }
+                    }
+                    report.adopted.push(e.clone());
+                }
+                Some(ours) => report.conflicts.push(MergeConflict {
+                    effect: e.clone(),
+                    ours: ours.clone(),
+                    theirs: theirs.clone(),
+                }),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Gets the full difference between the effect trees of `old` and `new`:
+    /// the base effects that were added or removed, and every location in the
+    /// trees whose annotation changed. Effects are matched by their
+    /// fingerprint (see `EffectInstance::fingerprint`), so line numbers may
+    /// shift between the two audits (e.g. for different versions of a crate).
+    pub fn diff(old: &AuditFile, new: &AuditFile) -> AuditDiff {
+        let mut diff = AuditDiff::default();
+        let mut new_effects = EffectInstance::fingerprints(new.audit_trees.keys());
+        for (fingerprint, old_e) in EffectInstance::fingerprints(old.audit_trees.keys()) {
+            match new_effects.remove(&fingerprint) {
+                Some(new_e) => diff.changed.extend(AuditDiff::tree_changes(
+                    new_e,
+                    &old.audit_trees[old_e],
+                    &new.audit_trees[new_e],
+                )),
+                None => diff.removed.push(old_e.clone()),
+            }
+        }
+        diff.added.extend(new_effects.into_values().cloned());
+
+        let loc_key = |l: &SrcLoc| (l.filepath_string(), l.start_line(), l.start_col());
+        diff.added.sort_by_key(|e| loc_key(e.call_loc()));
+        diff.removed.sort_by_key(|e| loc_key(e.call_loc()));
+        diff.changed.sort_by_key(|c| loc_key(&c.location.callee_loc));
+        diff
+    }
+
+    /// Gets the base effects matching the query, with their effect trees,
+    /// sorted by location
+    pub fn query_effects(
+        &self,
+        query: &EffectQuery,
+    ) -> Vec<(&EffectInstance, &EffectTree)> {
+        let mut effects = self
+            .audit_trees
+            .iter()
+            .filter(|(e, t)| query.matches(e, t))
+            .collect::<Vec<_>>();
+        let loc_key = |l: &SrcLoc| (l.filepath_string(), l.start_line(), l.start_col());
+        effects.sort_by_key(|(e, _)| loc_key(e.call_loc()));
+        effects
+    }
+}
+
+/// Criteria for selecting base effects from an audit file, see
+/// `AuditFile::query_effects`. Unset criteria match every effect.
+#[derive(Debug, Clone, Default)]
+pub struct EffectQuery {
+    /// Match effects of any of these types
+    pub effect_types: Vec<EffectType>,
+    /// Match effects with this annotation anywhere in their tree
+    pub annotation: Option<SafetyAnnotation>,
+    /// Match effects in files whose path matches this pattern (see
+    /// `pattern_matches`)
+    pub file: Option<String>,
+    /// Match effects whose callee path matches this pattern
+    pub callee: Option<String>,
+}
+
+impl EffectQuery {
+    pub fn matches(&self, effect: &EffectInstance, tree: &EffectTree) -> bool {
+        (self.effect_types.is_empty()
+            || self.effect_types.contains(&EffectType::from(effect.eff_type())))
+            && self.annotation.map_or(true, |a| tree.has_annotation(a))
+            && self.file.as_ref().map_or(true, |p| {
+                pattern_matches(p, &effect.call_loc().filepath_string())
+            })
+            && self
+                .callee
+                .as_ref()
+                .map_or(true, |p| pattern_matches(p, effect.callee_path()))
+    }
+}
+
+/// An effect audited differently in two audits being merged
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub effect: EffectInstance,
+    pub ours: EffectTree,
+    pub theirs: EffectTree,
+}
+
+/// The result of `AuditFile::merge`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MergeReport {
+    /// Effects whose audited tree was taken from the other audit
+    pub adopted: Vec<EffectInstance>,
+    /// Effects that were only in the other audit, added as unaudited
+    pub added: Vec<EffectInstance>,
+    /// Effects audited differently in the two audits, which need to be
+    /// resolved by hand
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// A location in an effect tree whose annotation changed between two audits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationChange {
+    /// The base effect the location belongs to, as of the new audit
+    pub effect: EffectInstance,
+    /// The location in the effect tree; the base effect itself, or one of
+    /// the callers it was marked caller-checked through
+    pub location: EffectInfo,
+    pub old: SafetyAnnotation,
+    pub new: SafetyAnnotation,
+}
+
+/// The difference between two audit files, see `AuditFile::diff`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditDiff {
+    pub added: Vec<EffectInstance>,
+    pub removed: Vec<EffectInstance>,
+    pub changed: Vec<AnnotationChange>,
+}
+
+impl AuditDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Flatten a tree into each of its locations and their annotations, where
+    /// branches are caller-checked
+    fn tree_annotations(tree: &EffectTree) -> Vec<(&EffectInfo, SafetyAnnotation)> {
+        match tree {
+            EffectTree::Leaf(i, a) => vec![(i, *a)],
+            EffectTree::Branch(i, next) => {
+                let mut res = vec![(i, SafetyAnnotation::CallerChecked)];
+                res.extend(next.iter().flat_map(Self::tree_annotations));
+                res
+            }
+        }
+    }
+
+    /// The annotation changes between two trees of the same base effect.
+    /// Callers are matched by path in source order; a caller that is only in
+    /// one of the trees counts as skipped in the other.
+    fn tree_changes(
+        effect: &EffectInstance,
+        old_tree: &EffectTree,
+        new_tree: &EffectTree,
+    ) -> Vec<AnnotationChange> {
+        let mut old_annotations: HashMap<&CanonicalPath, Vec<_>> = HashMap::new();
+        for (info, a) in Self::tree_annotations(old_tree) {
+            old_annotations.entry(&info.caller_path).or_default().push((info, a));
+        }
+
+        let mut changes = Vec::new();
+        let mut change = |location: &EffectInfo, old, new| {
+            if old != new {
+                changes.push(AnnotationChange {
+                    effect: effect.clone(),
+                    location: location.clone(),
+                    old,
+                    new,
+                });
+            }
+        };
+        for (info, new_a) in Self::tree_annotations(new_tree) {
+            let old_a = match old_annotations.get_mut(&info.caller_path) {
+                Some(olds) if !olds.is_empty() => olds.remove(0).1,
+                _ => SafetyAnnotation::Skipped,
+            };
+            change(info, old_a, new_a);
+        }
+        for (info, old_a) in old_annotations.into_values().flatten() {
+            change(info, old_a, SafetyAnnotation::Skipped);
+        }
+        changes
+    }
+}
+
+impl fmt::Display for AuditDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences between the audits");
+        }
+        if !self.added.is_empty() {
+            writeln!(f, "Added effects ({}):", self.added.len())?;
+            for e in &self.added {
+                writeln!(f, "  + {} -> {} ({})", e.caller(), e.callee(), e.call_loc())?;
+            }
+        }
+        if !self.removed.is_empty() {
+            writeln!(f, "Removed effects ({}):", self.removed.len())?;
+            for e in &self.removed {
+                writeln!(f, "  - {} -> {} ({})", e.caller(), e.callee(), e.call_loc())?;
+            }
+        }
+        if !self.changed.is_empty() {
+            writeln!(f, "Changed annotations ({}):", self.changed.len())?;
+            for c in &self.changed {
+                writeln!(
+                    f,
+                    "  ~ {} -> {}: {} at {} ({}): {} => {}",
+                    c.effect.caller(),
+                    c.effect.callee(),
+                    if c.location.caller_path == *c.effect.caller() {
+                        "effect"
+                    } else {
+                        "caller"
+                    },
+                    c.location.caller_path,
+                    c.location.callee_loc,
+                    c.old,
+                    c.new
+                )?;
+            }
+        }
+        Ok(())
+    }
 }
@@ -3,9 +3,10 @@ use std::collections::{HashMap, HashSet};
 use crate::audit_chain::AuditChain;
 use crate::audit_file::{EffectInfo, EffectTree};
 use crate::auditing::info::*;
+use crate::auditing::util::pattern_matches;
 use crate::effect::{Effect, EffectInstance};
 use crate::ident::CanonicalPath;
-use crate::scanner::scan_crate;
+use crate::scanner::{scan_crate, scan_crate_cached, ScanOptions};
 use crate::sink::Sink;
 use crate::{
     audit_file::{AuditFile, SafetyAnnotation},
@@ -13,6 +14,7 @@ use crate::{
 };
 use anyhow::{anyhow, Result};
 use inquire::{validator::Validation, Text};
+use petgraph::visit::Bfs;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AuditStatus {
@@ -21,24 +23,65 @@ pub enum AuditStatus {
     AuditChildEffect,
     AuditParentEffect,
     ExpandContext,
+    FilterEffects,
+    UndoAnnotation,
+}
+
+/// The effect trees from before each annotation made during an audit, so the
+/// user can go back and change them
+#[derive(Debug, Default)]
+pub struct AuditHistory {
+    prev_trees: Vec<(EffectInstance, EffectTree)>,
+}
+
+impl AuditHistory {
+    pub fn is_empty(&self) -> bool {
+        self.prev_trees.is_empty()
+    }
+
+    /// Record the tree for `effect` from before it was audited, if auditing
+    /// changed it
+    pub fn record(
+        &mut self,
+        audit_file: &AuditFile,
+        effect: &EffectInstance,
+        prev_tree: EffectTree,
+    ) {
+        if audit_file.audit_trees.get(effect) != Some(&prev_tree) {
+            self.prev_trees.push((effect.clone(), prev_tree));
+        }
+    }
+
+    /// Restore the tree of the last annotated effect, returning that effect
+    pub fn undo(&mut self, audit_file: &mut AuditFile) -> Option<EffectInstance> {
+        let (effect, prev_tree) = self.prev_trees.pop()?;
+        audit_file.audit_trees.insert(effect.clone(), prev_tree);
+        Some(effect)
+    }
 }
 
 // Returns Some SafetyAnnotation if the user selects one, None if the user
 // chooses to exit early, or an Error
 fn get_user_annotation(
     allow_effect_origin: bool,
+    allow_filter: bool,
+    allow_undo: bool,
 ) -> Result<(Option<SafetyAnnotation>, AuditStatus)> {
     let ans;
     loop {
         if let Ok(a) = Text::new(&format!(
             r#"Select how to mark this effect:
-  (s)afe, (u)nsafe, (c)aller checked,{} (e)xpand context, ask me (l)ater, e(x)it tool
+  (s)afe, (u)nsafe, (c)aller checked,{}{}{} (e)xpand context, ask me (l)ater, e(x)it tool
 "#,
-            if allow_effect_origin { " audit effect (o)rigin," } else { "" }
+            if allow_effect_origin { " audit effect (o)rigin," } else { "" },
+            if allow_filter { " (f)ilter effects by callee," } else { "" },
+            if allow_undo { " go (b)ack to the last effect," } else { "" }
         ))
         .with_validator(move |x: &str| match x {
             "s" | "u" | "c" | "e" | "l" | "x" => Ok(Validation::Valid),
             "o" if allow_effect_origin => Ok(Validation::Valid),
+            "f" if allow_filter => Ok(Validation::Valid),
+            "b" if allow_undo => Ok(Validation::Valid),
             _ => Ok(Validation::Invalid("Invalid input".into())),
         })
         .prompt()
@@ -55,11 +98,69 @@ fn get_user_annotation(
         "l" => Ok((Some(SafetyAnnotation::Skipped), AuditStatus::ContinueAudit)),
         "o" => Ok((None, AuditStatus::AuditChildEffect)),
         "e" => Ok((None, AuditStatus::ExpandContext)),
+        "f" => Ok((None, AuditStatus::FilterEffects)),
+        "b" => Ok((None, AuditStatus::UndoAnnotation)),
         "x" => Ok((None, AuditStatus::EarlyExit)),
         _ => Err(anyhow!("Invalid annotation selection")),
     }
 }
 
+/// Set the annotation of every unaudited (skipped or truncated) leaf effect
+/// whose callee path matches `pattern` (see `pattern_matches`). Returns the
+/// number of effects annotated.
+pub fn annotate_matching_effects(
+    audit_file: &mut AuditFile,
+    pattern: &str,
+    annotation: SafetyAnnotation,
+) -> usize {
+    let mut annotated = 0;
+    for (e, t) in audit_file.audit_trees.iter_mut() {
+        if matches!(
+            t.get_leaf_annotation(),
+            Some(SafetyAnnotation::Skipped | SafetyAnnotation::Truncated)
+        ) && pattern_matches(pattern, e.callee_path())
+        {
+            t.set_annotation(annotation);
+            annotated += 1;
+        }
+    }
+    annotated
+}
+
+/// Ask the user for a callee pattern and an annotation, and apply it to all
+/// matching unaudited effects
+fn filter_effects(audit_file: &mut AuditFile) -> Result<()> {
+    let pattern = Text::new(
+        "Enter a callee path pattern (substring, or glob with `*`, e.g. log::*):",
+    )
+    .with_validator(|x: &str| {
+        if x.is_empty() {
+            Ok(Validation::Invalid("Pattern can't be empty".into()))
+        } else {
+            Ok(Validation::Valid)
+        }
+    })
+    .prompt()?;
+
+    // Caller-checked needs the callers of each effect to be expanded, so it
+    // can't be applied in bulk
+    let ans = Text::new("Mark all matching effects as (s)afe or (u)nsafe:")
+        .with_validator(|x: &str| match x {
+            "s" | "u" => Ok(Validation::Valid),
+            _ => Ok(Validation::Invalid("Invalid input".into())),
+        })
+        .prompt()?;
+    let annotation = match ans.as_str() {
+        "s" => SafetyAnnotation::Safe,
+        "u" => SafetyAnnotation::Unsafe,
+        _ => return Err(anyhow!("Invalid annotation selection")),
+    };
+
+    let annotated = annotate_matching_effects(audit_file, &pattern, annotation);
+    println!("Marked {} effects matching `{}` as {}", annotated, pattern, annotation);
+    Ok(())
+}
+
 fn print_and_update_audit<'a>(
     orig_effect: &'a EffectInstance,
     effect_tree: &mut EffectTree,
@@ -250,6 +351,32 @@ fn audit_effect_tree(
     }
 }
 
+/// The base effects of the audit file, sorted by location so the user doesn't
+/// have to jump between files as much
+pub(crate) fn sorted_audit_locs(audit_file: &AuditFile) -> Vec<EffectInstance> {
+    let mut audit_locs: Vec<EffectInstance> =
+        audit_file.audit_trees.keys().cloned().collect();
+    audit_locs.sort_by(|a, b| {
+        let a_loc = a.call_loc();
+        let b_loc = b.call_loc();
+        let a_path = a_loc.filepath_string();
+        let b_path = b_loc.filepath_string();
+
+        a_path
+            .cmp(&b_path)
+            .then_with(|| a_loc.start_line().cmp(&b_loc.start_line()))
+            .then_with(|| a_loc.start_col().cmp(&b_loc.start_col()))
+    });
+    audit_locs
+}
+
+/// Show how far along the audit is, before auditing the effect at index `i`
+/// of the `total` base effects
+fn print_audit_progress(i: usize, total: usize) {
+    let percent = (i + 1) * 100 / total;
+    println!("[{}/{}] ({}%)", i + 1, total, percent);
+}
+
 // TODO: When we exit early, we have no way of knowing which effects the user
 //       has already gone through in this audit and marked "skipped" and so we
 //       will re-prompt the user once we resume auditing the audit file. We would
@@ -285,78 +412,98 @@ pub fn start_audit(
         println!("WARNING: package has been marked as unsafe");
     }
 
-    // Sort the base audit locs before presenting them to the user so they don't
-    // have to jump between files as much
-    let mut audit_locs: Vec<(&EffectInstance, &mut EffectTree)> =
-        audit_file.audit_trees.iter_mut().collect();
-    audit_locs.sort_by(|(a, _), (b, _)| {
-        let a_loc = a.call_loc();
-        let b_loc = b.call_loc();
-        let a_path = a_loc.filepath_string();
-        let b_path = b_loc.filepath_string();
+    let audit_locs = sorted_audit_locs(audit_file);
 
-        a_path
-            .cmp(&b_path)
-            .then_with(|| a_loc.start_line().cmp(&b_loc.start_line()))
-            .then_with(|| a_loc.start_col().cmp(&b_loc.start_col()))
-    });
+    let mut config = config.clone();
+    config.allow_filter = true;
 
     // Iterate through the effects and prompt the user for if they're safe
-    for (e, t) in audit_locs {
-        match t.get_leaf_annotation() {
-            Some(SafetyAnnotation::Skipped) => {
-                // Check if we have already audited the same function
-                // pointer effect and don't show it to the user again
-                if matches!(e.eff_type(), Effect::FnPtrCreation)
-                    && fn_ptr_effects.contains_key(e.callee_path())
-                {
-                    t.set_annotation(*fn_ptr_effects.get(e.callee_path()).unwrap());
-                    continue;
-                }
-
-                match audit_effect_tree(e, t, &scan_res, config)? {
-                    AuditStatus::EarlyExit => {
-                        break;
-                    }
-                    AuditStatus::AuditChildEffect => {
-                        dependency_audit_effect = Some(e.clone());
-                        break;
-                    }
-                    AuditStatus::AuditParentEffect => {
-                        return Err(anyhow!("We should never return this status here"));
+    let mut history = AuditHistory::default();
+    let mut i = 0;
+    'effects: while i < audit_locs.len() {
+        let e = &audit_locs[i];
+        // Loop so the effect is shown again after filtering, if it's still
+        // unaudited
+        loop {
+            config.allow_undo = !history.is_empty();
+            let t = audit_file
+                .audit_trees
+                .get_mut(e)
+                .ok_or_else(|| anyhow!("Missing effect tree for {:?}", e))?;
+            let prev_tree = t.clone();
+            let status = match t.get_leaf_annotation() {
+                Some(SafetyAnnotation::Skipped | SafetyAnnotation::Truncated) => {
+                    // Check if we have already audited the same function
+                    // pointer effect and don't show it to the user again
+                    if matches!(e.eff_type(), Effect::FnPtrCreation)
+                        && fn_ptr_effects.contains_key(e.callee_path())
+                    {
+                        t.set_annotation(*fn_ptr_effects.get(e.callee_path()).unwrap());
+                        i += 1;
+                        continue 'effects;
                     }
-                    _ => {
-                        // Keep track of the safety annotations for function pointers
-                        if matches!(e.eff_type(), Effect::FnPtrCreation)
-                            && !matches!(
-                                t.get_leaf_annotation(),
-                                Some(SafetyAnnotation::Skipped)
-                            )
-                        {
-                            fn_ptr_effects.insert(
-                                e.callee_path(),
-                                t.get_leaf_annotation().unwrap(),
-                            );
-                        }
+
+                    print_audit_progress(i, audit_locs.len());
+                    let status = audit_effect_tree(e, t, &scan_res, &config)?;
+
+                    // Keep track of the safety annotations for function pointers
+                    if matches!(e.eff_type(), Effect::FnPtrCreation)
+                        && !matches!(
+                            t.get_leaf_annotation(),
+                            Some(SafetyAnnotation::Skipped | SafetyAnnotation::Truncated)
+                        )
+                    {
+                        fn_ptr_effects
+                            .insert(e.callee_path(), t.get_leaf_annotation().unwrap());
                     }
+                    status
+                }
+
+                Some(_) => {
+                    i += 1;
+                    continue 'effects;
                 }
-            }
 
-            Some(_) => (),
+                None => {
+                    print_audit_progress(i, audit_locs.len());
+                    audit_effect_tree(e, t, &scan_res, &config)?
+                }
+            };
 
-            None => match audit_effect_tree(e, t, &scan_res, config)? {
+            match status {
                 AuditStatus::EarlyExit => {
-                    break;
+                    break 'effects;
                 }
                 AuditStatus::AuditChildEffect => {
                     dependency_audit_effect = Some(e.clone());
-                    break;
+                    break 'effects;
                 }
                 AuditStatus::AuditParentEffect => {
                     return Err(anyhow!("We should never return this status here"));
                 }
-                _ => (),
-            },
+                AuditStatus::FilterEffects => {
+                    if let Err(err) = filter_effects(audit_file) {
+                        println!(
+                            "Error filtering effects ({}). Trying to continue...",
+                            err
+                        );
+                    }
+                }
+                AuditStatus::UndoAnnotation => {
+                    if let Some(prev_e) = history.undo(audit_file) {
+                        if matches!(prev_e.eff_type(), Effect::FnPtrCreation) {
+                            fn_ptr_effects.remove(prev_e.callee_path());
+                        }
+                        i = audit_locs.iter().position(|x| x == &prev_e).unwrap_or(i);
+                        continue 'effects;
+                    }
+                }
+                _ => {
+                    history.record(audit_file, e, prev_tree);
+                    i += 1;
+                    continue 'effects;
+                }
+            }
         }
     }
 
@@ -370,7 +517,75 @@ pub fn start_audit(
     Ok(dependency_audit_effect)
 }
 
-fn update_audit_annotation(
+/// Build an audit file containing only the base effects reachable from
+/// `scope_fn` through the call graph. Annotations are copied over from
+/// `audit_file`.
+pub fn scoped_audit_file(
+    audit_file: &AuditFile,
+    scan_res: &ScanResults,
+    scope_fn: &CanonicalPath,
+) -> Result<AuditFile> {
+    let start = scan_res
+        .node_idxs
+        .get(scope_fn)
+        .ok_or_else(|| anyhow!("Couldn't find function in call graph: {}", scope_fn))?;
+
+    let graph = &scan_res.call_graph;
+    let mut reachable_fns = HashSet::new();
+    let mut bfs = Bfs::new(graph, *start);
+    while let Some(node) = bfs.next(graph) {
+        reachable_fns.insert(&graph[node]);
+    }
+
+    let mut scoped = audit_file.clone();
+    scoped.audit_trees.retain(|e, _| reachable_fns.contains(e.caller()));
+    scoped.recalc_pub_caller_checked(&scan_res.pub_fns);
+
+    Ok(scoped)
+}
+
+/// Perform the auditing process only on the effects reachable from
+/// `scope_fn`, and update `audit_file` with the new annotations. Returns the
+/// same result as `start_audit`.
+pub fn start_scoped_audit(
+    audit_file: &mut AuditFile,
+    scan_res: ScanResults,
+    scope_fn: &CanonicalPath,
+    config: &Config,
+) -> Result<Option<EffectInstance>> {
+    let mut scoped = scoped_audit_file(audit_file, &scan_res, scope_fn)?;
+    println!("Auditing effects reachable from: {}", scope_fn);
+
+    let pub_fns = scan_res.pub_fns.clone();
+    let res = start_audit(&mut scoped, scan_res, config)?;
+
+    audit_file.audit_trees.extend(scoped.audit_trees);
+    audit_file.recalc_pub_caller_checked(&pub_fns);
+
+    Ok(res)
+}
+
+/// Run `audit` on a copy of `audit_file` without the effects in test-only
+/// code, and update `audit_file` with the new annotations. The test effects
+/// are only hidden during the audit, so they and their annotations are kept.
+pub fn audit_without_test_effects<T>(
+    audit_file: &mut AuditFile,
+    pub_fns: &HashSet<CanonicalPath>,
+    audit: impl FnOnce(&mut AuditFile) -> Result<T>,
+) -> Result<T> {
+    let mut without_tests = audit_file.clone();
+    without_tests.audit_trees.retain(|e, _| !e.in_test());
+    without_tests.recalc_pub_caller_checked(pub_fns);
+
+    let res = audit(&mut without_tests)?;
+
+    audit_file.audit_trees.extend(without_tests.audit_trees);
+    audit_file.recalc_pub_caller_checked(pub_fns);
+
+    Ok(res)
+}
+
+pub(crate) fn update_audit_annotation(
     annotation: SafetyAnnotation,
     scan_res: &ScanResults,
     effect_tree: &mut EffectTree,
@@ -429,7 +644,11 @@ fn update_audit_from_input(
     curr_effect: EffectInfo,
     config: &Config,
 ) -> Result<AuditStatus> {
-    match get_user_annotation(config.allow_effect_origin) {
+    // Filtering and undoing apply to base effects, so only offer them at the
+    // top level
+    let allow_filter = config.allow_filter && effect_history.is_empty();
+    let allow_undo = config.allow_undo && effect_history.is_empty();
+    match get_user_annotation(config.allow_effect_origin, allow_filter, allow_undo) {
         Ok((Some(a), AuditStatus::ContinueAudit)) => {
             let update_status =
                 update_audit_annotation(a, scan_res, effect_tree, curr_effect)?;
@@ -444,7 +663,9 @@ fn update_audit_from_input(
         )),
         Ok((_, s @ AuditStatus::AuditChildEffect))
         | Ok((_, s @ AuditStatus::EarlyExit))
-        | Ok((_, s @ AuditStatus::ExpandContext)) => Ok(s),
+        | Ok((_, s @ AuditStatus::ExpandContext))
+        | Ok((_, s @ AuditStatus::FilterEffects))
+        | Ok((_, s @ AuditStatus::UndoAnnotation)) => Ok(s),
         Ok((_, AuditStatus::AuditParentEffect)) => {
             // TODO: This is for the case where we are walking down the effect
             //       stack for auditing child effects and the user decides they
@@ -479,12 +700,23 @@ pub fn audit_pub_fn(
         })?;
     let mut new_audit_file = prev_audit_file.clone();
 
-    // Find the public function associated with the sink
-    let scan_res = scan_crate(
-        &new_audit_file.base_dir,
-        &prev_audit_file.scanned_effects,
-        quick_mode,
-    )?;
+    // Find the public function associated with the sink. Auditing the
+    // children of an effect comes back here for the same crates, so the scan
+    // is cached next to the audit file.
+    let options = ScanOptions { quick_mode, ..ScanOptions::default() };
+    let scan_res = match chain.scan_cache_path(&sink_crate_id) {
+        Some(cache) => scan_crate_cached(
+            &new_audit_file.base_dir,
+            &prev_audit_file.scanned_effects,
+            &options,
+            &cache,
+        )?,
+        None => scan_crate(
+            &new_audit_file.base_dir,
+            &prev_audit_file.scanned_effects,
+            quick_mode,
+        )?,
+    };
     let sink_fn = CanonicalPath::new(sink_ident.as_str());
     loop {
         // Keep looping until we are done with auditing children
@@ -540,6 +772,9 @@ pub fn audit_pub_fn(
             (AuditStatus::ExpandContext, _) => {
                 return Err(anyhow!("Shouldn't return ExpandContext when auditing public function effects"));
             }
+            (AuditStatus::FilterEffects | AuditStatus::UndoAnnotation, _) => {
+                return Err(anyhow!("Shouldn't return FilterEffects or UndoAnnotation when auditing public function effects"));
+            }
         }
     }
 
@@ -587,6 +822,9 @@ fn audit_pub_fn_effect(
                 AuditStatus::ExpandContext => {
                     config.expand_context();
                 }
+                AuditStatus::FilterEffects | AuditStatus::UndoAnnotation => {
+                    return Err(anyhow!("Cannot filter or undo effects in this context"));
+                }
             }
         }
     }
@@ -3,6 +3,7 @@ use crate::audit_file::AuditFile;
 use crate::auditing::audit::{audit_pub_fn, start_audit};
 use crate::auditing::info::Config as AuditConfig;
 use crate::auditing::review::review_audit;
+use crate::capabilities::chain_capabilities;
 use crate::effect::Effect;
 use crate::{download_crate, scanner};
 
@@ -34,6 +35,7 @@ pub enum Command {
     Create(Create),
     Review(Review),
     Audit(Audit),
+    Capabilities(Capabilities),
 }
 
 pub trait CommandRunner {
@@ -46,6 +48,7 @@ impl CommandRunner for Command {
             Self::Create(create) => create.run_command(args),
             Self::Review(review) => review.run_command(args),
             Self::Audit(audit) => audit.run_command(args),
+            Self::Capabilities(capabilities) => capabilities.run_command(args),
         }
     }
 }
@@ -68,6 +71,7 @@ impl CommandRunner for Create {
                 crate_name,
                 crate_version,
                 &self.crate_path,
+                self.registry.as_ref(),
             )?;
 
             // We have now downloaded the crate into a subfolder of the
@@ -259,6 +263,32 @@ impl CommandRunner for Audit {
     }
 }
 
+/// Print the effect types observed in each crate of the chain as CycloneDX
+/// components, to attach to an SBOM
+#[derive(Clone, ClapArgs, Debug)]
+pub struct Capabilities {
+    /// Path to manifest
+    manifest_path: String,
+}
+
+impl CommandRunner for Capabilities {
+    fn run_command(self, _args: OuterArgs) -> Result<()> {
+        let mut chain = AuditChain::read_audit_chain(PathBuf::from(&self.manifest_path))?
+            .ok_or_else(|| {
+                anyhow!("Couldn't find audit chain manifest at {}", &self.manifest_path)
+            })?;
+
+        let components = chain_capabilities(&mut chain)?
+            .iter()
+            .map(|c| c.to_cyclonedx())
+            .collect::<Vec<_>>();
+        let json = serde_json::json!({ "components": components });
+        println!("{}", serde_json::to_string_pretty(&json)?);
+
+        Ok(())
+    }
+}
+
 fn review_crate_audit_file(
     audit_file: &AuditFile,
     crate_path: PathBuf,
@@ -7,6 +7,7 @@ use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term;
 use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use serde::{Deserialize, Serialize};
 
 use crate::ident::CanonicalPath;
 use crate::{
@@ -27,6 +28,13 @@ pub struct Config {
     //       can now that chains are our primary auditing mechanism?)
     #[clap(default_value_t = false)]
     pub allow_effect_origin: bool,
+    /// Whether the user can annotate all effects matching a pattern at once.
+    /// Only set while auditing the base effects of an audit file.
+    #[clap(skip)]
+    pub allow_filter: bool,
+    /// Whether the user can go back to the last annotated effect
+    #[clap(skip)]
+    pub allow_undo: bool,
 }
 
 impl Default for Config {
@@ -35,6 +43,8 @@ impl Default for Config {
             lines_before_effect: 4,
             lines_after_effect: 1,
             allow_effect_origin: false,
+            allow_filter: false,
+            allow_undo: false,
         }
     }
 }
@@ -45,6 +55,8 @@ impl Config {
             lines_before_effect: lines_before,
             lines_after_effect: lines_after,
             allow_effect_origin,
+            allow_filter: false,
+            allow_undo: false,
         }
     }
 
@@ -54,6 +66,57 @@ impl Config {
     }
 }
 
+/// The message shown on the primary label of an effect: a description of the
+/// effect in its original function, or a note that it was marked
+/// caller-checked further down the call stack
+pub fn effect_label(effect_origin: &EffectInstance, effect: &EffectInfo) -> String {
+    if effect_origin.caller() == &effect.caller_path {
+        // We are in the original function, so print all the effects in the
+        // EffectInstance
+        match effect_origin.eff_type() {
+            Effect::SinkCall(sink) => format!("sink call: {}", sink),
+            Effect::FFICall(call) => format!("ffi call: {}", call),
+            Effect::UnsafeCall(call) => format!("unsafe call: {}", call),
+            Effect::RawPointer(ptr) => format!("raw pointer access: {}", ptr),
+            Effect::UnionField(union) => format!("union access: {}", union),
+            Effect::PackedFieldAccess(field) => {
+                format!("packed field reference: {}", field)
+            }
+            Effect::StaticMut(var) => format!("static mut access: {}", var),
+            Effect::StaticExt(var) => format!("static ffi variable access: {}", var),
+            Effect::FnPtrCreation =>
+                format!("function pointer creation: {} - (verify the function is always safe to call)", &effect_origin.callee()),
+            Effect::ClosureCreation if effect_origin.closure_escapes() == Some(true) => {
+                "escaping closure creation (verify the closure is always safe to call)"
+                    .to_string()
+            }
+            Effect::ClosureCreation => {
+                "closure creation (verify the closure is always safe to call)".to_string()
+            }
+            Effect::RawPtrCast => {
+                "Cast to a raw pointer (can't cause unsafe behavior on its own)"
+                    .to_string()
+            }
+            Effect::FFIDecl(decl) => format!("ffi declaration: {}", decl),
+            Effect::Transmute(call) => format!("transmute: {}", call),
+            Effect::RawAlloc(call) => format!("manual memory allocation: {}", call),
+            Effect::RawThread(call) => format!("raw thread management: {}", call),
+            Effect::UntrustedInput(call) => format!("untrusted input: {}", call),
+            Effect::PtrOffset(call) => format!("raw pointer arithmetic: {}", call),
+            Effect::FFIExport(f) => format!("function exported to foreign code: {}", f),
+            Effect::CStrPtr { callee, on_temporary: false } => {
+                format!("pointer to C string: {}", callee)
+            }
+            Effect::CStrPtr { callee, on_temporary: true } => format!(
+                "pointer to temporary C string: {} (the pointer dangles once the string is dropped)",
+                callee
+            ),
+        }
+    } else {
+        "call safety marked as caller-checked".to_string()
+    }
+}
+
 pub fn print_effect_src(
     effect_origin: &EffectInstance,
     effect: &EffectInfo,
@@ -134,31 +197,7 @@ pub fn print_effect_src(
         }
     };
 
-    let label_msg = if effect_origin.caller() == &effect.caller_path {
-        // We are in the original function, so print all the effects in the
-        // EffectInstance
-        match effect_origin.eff_type() {
-            Effect::SinkCall(sink) => format!("sink call: {}", sink),
-            Effect::FFICall(call) => format!("ffi call: {}", call),
-            Effect::UnsafeCall(call) => format!("unsafe call: {}", call),
-            Effect::RawPointer(ptr) => format!("raw pointer access: {}", ptr),
-            Effect::UnionField(union) => format!("union access: {}", union),
-            Effect::StaticMut(var) => format!("static mut access: {}", var),
-            Effect::StaticExt(var) => format!("static ffi variable access: {}", var),
-            Effect::FnPtrCreation =>
-                format!("function pointer creation: {} - (verify the function is always safe to call)", &effect_origin.callee()),
-            Effect::ClosureCreation => {
-                "closure creation (verify the closure is always safe to call)".to_string()
-            }
-            Effect::RawPtrCast => {
-                "Cast to a raw pointer (can't cause unsafe behavior on its own)"
-                    .to_string()
-            }
-            Effect::FFIDecl(decl) => format!("ffi declaration: {}", decl),
-        }
-    } else {
-        "call safety marked as caller-checked".to_string()
-    };
+    let label_msg = effect_label(effect_origin, effect);
     let l = labels.remove(0);
     labels.insert(0, l.with_message(label_msg));
 
@@ -178,10 +217,13 @@ pub fn print_effect_src(
     Ok(())
 }
 
-struct CallStackInfo {
-    fn_string: Option<String>,
-    filename: String,
-    lineno: usize,
+/// A function on the call stack of an effect, with the line that declares it
+/// (None if the declaration couldn't be found)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallStackInfo {
+    pub fn_string: Option<String>,
+    pub filename: String,
+    pub lineno: usize,
 }
 
 impl CallStackInfo {
@@ -231,30 +273,34 @@ fn print_call_stack_infos(stack: Vec<CallStackInfo>) {
     }
 }
 
-fn print_call_stack(
+/// The functions on the call stack of `curr_effect`, from the innermost
+/// caller out to the base effect, which is the first in `effect_history`
+pub fn call_stack(
     curr_effect: &EffectInfo,
     effect_history: &[&EffectInfo],
     fn_locs: &HashMap<CanonicalPath, SrcLoc>,
-) -> Result<()> {
-    if !effect_history.is_empty() {
-        let mut call_stack_infos = vec![];
-        // TODO: Colorize
-        println!("EffectInstance call stack:");
-        let call_info = match fn_locs.get(&curr_effect.caller_path) {
+) -> Result<Vec<CallStackInfo>> {
+    let mut call_stack_infos = vec![];
+    for e in std::iter::once(&curr_effect).chain(effect_history.iter().rev()) {
+        let call_info = match fn_locs.get(&e.caller_path) {
             Some(fn_loc) => fn_decl_info(fn_loc)?,
-            None => missing_fn_decl_info(&curr_effect.callee_loc),
+            None => missing_fn_decl_info(&e.callee_loc),
         };
         call_stack_infos.push(call_info);
+    }
 
-        for e in effect_history.iter().rev() {
-            let call_info = match fn_locs.get(&e.caller_path) {
-                Some(fn_loc) => fn_decl_info(fn_loc)?,
-                None => missing_fn_decl_info(&e.callee_loc),
-            };
-            call_stack_infos.push(call_info);
-        }
+    Ok(call_stack_infos)
+}
 
-        print_call_stack_infos(call_stack_infos);
+fn print_call_stack(
+    curr_effect: &EffectInfo,
+    effect_history: &[&EffectInfo],
+    fn_locs: &HashMap<CanonicalPath, SrcLoc>,
+) -> Result<()> {
+    if !effect_history.is_empty() {
+        // TODO: Colorize
+        println!("EffectInstance call stack:");
+        print_call_stack_infos(call_stack(curr_effect, effect_history, fn_locs)?);
     }
 
     Ok(())
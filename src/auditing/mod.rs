@@ -3,4 +3,5 @@ pub mod chain;
 pub mod info;
 pub mod reset;
 pub mod review;
+pub mod tui;
 pub mod util;
@@ -1,8 +1,10 @@
 use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
-use super::info::Config;
+use super::audit::sorted_audit_locs;
+use super::info::{call_stack, CallStackInfo, Config};
 use super::util::is_audit_scan_valid;
 use crate::audit_file::{AuditFile, EffectInfo, EffectTree, SafetyAnnotation};
 use crate::auditing::info::print_effect_info;
@@ -77,3 +79,69 @@ pub fn review_audit(
 
     Ok(())
 }
+
+/// A location in the effect tree of a base effect, as printed by
+/// `review_audit`, for machine-readable reviews
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewEntry {
+    pub effect: EffectInstance,
+    pub caller: CanonicalPath,
+    pub location: SrcLoc,
+    pub annotation: SafetyAnnotation,
+    pub call_stack: Vec<CallStackInfo>,
+}
+
+fn review_entries(
+    orig_effect: &EffectInstance,
+    effect_tree: &EffectTree,
+    effect_history: &[&EffectInfo],
+    fn_locs: &HashMap<CanonicalPath, SrcLoc>,
+    entries: &mut Vec<ReviewEntry>,
+) -> Result<()> {
+    let (new_e, annotation) = match effect_tree {
+        EffectTree::Leaf(new_e, a) => (new_e, *a),
+        EffectTree::Branch(new_e, _) => (new_e, SafetyAnnotation::CallerChecked),
+    };
+    entries.push(ReviewEntry {
+        effect: orig_effect.clone(),
+        caller: new_e.caller_path.clone(),
+        location: new_e.callee_loc.clone(),
+        annotation,
+        call_stack: call_stack(new_e, effect_history, fn_locs)?,
+    });
+
+    if let EffectTree::Branch(_, es) = effect_tree {
+        let mut new_history = effect_history.to_owned();
+        new_history.push(new_e);
+        for new_tree in es {
+            review_entries(orig_effect, new_tree, &new_history, fn_locs, entries)?;
+        }
+    }
+    Ok(())
+}
+
+/// Like `review_audit`, but print each location in the audit file with its
+/// annotation and call stack as JSON, for other tools to consume
+pub fn review_audit_json(
+    audit_file: &AuditFile,
+    crate_path: &Path,
+    quick_mode: bool,
+    ignore_hash: bool,
+) -> Result<()> {
+    let scan_res =
+        scanner::scan_crate(crate_path, &audit_file.scanned_effects, quick_mode)?;
+    if !ignore_hash && !is_audit_scan_valid(audit_file, crate_path)? {
+        return Err(anyhow!(
+            "Invalid audit file during review: crate has changed since last audit file scan"
+        ));
+    }
+
+    let mut entries = Vec::new();
+    for e in sorted_audit_locs(audit_file) {
+        let t = &audit_file.audit_trees[&e];
+        review_entries(&e, t, &[], &scan_res.fn_locs, &mut entries)?;
+    }
+    println!("{}", serde_json::to_string_pretty(&entries)?);
+
+    Ok(())
+}
@@ -0,0 +1,419 @@
+//! A full-screen terminal frontend for auditing, as an alternative to the
+//! line-based prompts in `audit`. It shows the unaudited effects, the source
+//! around the selected one, and its call stack side-by-side, and updates the
+//! effect trees the same way the line-based audit does.
+
+use std::io::{self, Stdout};
+use std::panic;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+
+use crate::audit_file::{AuditFile, EffectInfo, EffectTree, SafetyAnnotation};
+use crate::auditing::audit::{sorted_audit_locs, update_audit_annotation, AuditHistory};
+use crate::auditing::info::effect_label;
+use crate::effect::EffectInstance;
+use crate::scanner::ScanResults;
+
+/// An unaudited leaf in one of the audit file's effect trees
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEffect {
+    /// The base effect whose tree contains the leaf
+    pub base: EffectInstance,
+    /// The child indices to follow from the root of the tree to the leaf
+    pub path: Vec<usize>,
+}
+
+fn tree_at_path<'a>(tree: &'a EffectTree, path: &[usize]) -> Option<&'a EffectTree> {
+    match (path.split_first(), tree) {
+        (None, _) => Some(tree),
+        (Some((i, rest)), EffectTree::Branch(_, next)) => {
+            tree_at_path(next.get(*i)?, rest)
+        }
+        (Some(_), EffectTree::Leaf(..)) => None,
+    }
+}
+
+fn tree_at_path_mut<'a>(
+    tree: &'a mut EffectTree,
+    path: &[usize],
+) -> Option<&'a mut EffectTree> {
+    match (path.split_first(), tree) {
+        (None, tree) => Some(tree),
+        (Some((i, rest)), EffectTree::Branch(_, next)) => {
+            tree_at_path_mut(next.get_mut(*i)?, rest)
+        }
+        (Some(_), EffectTree::Leaf(..)) => None,
+    }
+}
+
+fn tree_info(tree: &EffectTree) -> &EffectInfo {
+    match tree {
+        EffectTree::Leaf(e, _) | EffectTree::Branch(e, _) => e,
+    }
+}
+
+fn collect_skipped_leaves(
+    base: &EffectInstance,
+    tree: &EffectTree,
+    path: &mut Vec<usize>,
+    pending: &mut Vec<PendingEffect>,
+) {
+    match tree {
+        EffectTree::Leaf(_, SafetyAnnotation::Skipped)
+        | EffectTree::Leaf(_, SafetyAnnotation::Truncated) => {
+            pending.push(PendingEffect { base: base.clone(), path: path.clone() })
+        }
+        EffectTree::Leaf(..) => (),
+        EffectTree::Branch(_, next) => {
+            for (i, t) in next.iter().enumerate() {
+                path.push(i);
+                collect_skipped_leaves(base, t, path, pending);
+                path.pop();
+            }
+        }
+    }
+}
+
+/// The state of a TUI audit, independent of how it is drawn
+#[derive(Debug)]
+pub struct TuiAudit {
+    audit_locs: Vec<EffectInstance>,
+    pending: Vec<PendingEffect>,
+    selected: usize,
+    history: AuditHistory,
+}
+
+impl TuiAudit {
+    pub fn new(audit_file: &AuditFile) -> Self {
+        let mut tui = TuiAudit {
+            audit_locs: sorted_audit_locs(audit_file),
+            pending: Vec::new(),
+            selected: 0,
+            history: AuditHistory::default(),
+        };
+        tui.refresh(audit_file);
+        tui
+    }
+
+    /// Recompute the unaudited leaves after the effect trees have changed
+    fn refresh(&mut self, audit_file: &AuditFile) {
+        self.pending.clear();
+        for e in &self.audit_locs {
+            if let Some(t) = audit_file.audit_trees.get(e) {
+                collect_skipped_leaves(e, t, &mut Vec::new(), &mut self.pending);
+            }
+        }
+        self.selected = self.selected.min(self.pending.len().saturating_sub(1));
+    }
+
+    pub fn pending(&self) -> &[PendingEffect] {
+        &self.pending
+    }
+
+    pub fn selected(&self) -> Option<&PendingEffect> {
+        self.pending.get(self.selected)
+    }
+
+    pub fn select(&mut self, i: usize) {
+        self.selected = i.min(self.pending.len().saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        self.select(self.selected + 1);
+    }
+
+    pub fn select_prev(&mut self) {
+        self.select(self.selected.saturating_sub(1));
+    }
+
+    /// The effects from the base effect down to the selected leaf, i.e. the
+    /// callers that were marked caller-checked to reach it
+    pub fn call_stack<'a>(&self, audit_file: &'a AuditFile) -> Vec<&'a EffectInfo> {
+        let Some(p) = self.selected() else {
+            return Vec::new();
+        };
+        let Some(mut tree) = audit_file.audit_trees.get(&p.base) else {
+            return Vec::new();
+        };
+
+        let mut stack = vec![tree_info(tree)];
+        for i in &p.path {
+            match tree {
+                EffectTree::Branch(_, next) if *i < next.len() => {
+                    tree = &next[*i];
+                    stack.push(tree_info(tree));
+                }
+                _ => break,
+            }
+        }
+        stack
+    }
+
+    /// Annotate the selected leaf. Marking it caller-checked adds its callers
+    /// as new unaudited leaves right after it, the same as the line-based
+    /// audit does. Marking it skipped moves on to the next effect.
+    pub fn annotate(
+        &mut self,
+        audit_file: &mut AuditFile,
+        scan_res: &ScanResults,
+        annotation: SafetyAnnotation,
+    ) -> Result<()> {
+        let p = self.selected().cloned().ok_or_else(|| anyhow!("No effect selected"))?;
+        if annotation == SafetyAnnotation::Skipped {
+            self.select_next();
+            return Ok(());
+        }
+
+        let tree = audit_file
+            .audit_trees
+            .get_mut(&p.base)
+            .ok_or_else(|| anyhow!("Missing effect tree for {:?}", p.base))?;
+        let prev_tree = tree.clone();
+        let leaf = tree_at_path_mut(tree, &p.path)
+            .ok_or_else(|| anyhow!("Missing effect tree leaf for {:?}", p.base))?;
+        let curr_effect = tree_info(leaf).clone();
+        update_audit_annotation(annotation, scan_res, leaf, curr_effect)?;
+
+        self.history.record(audit_file, &p.base, prev_tree);
+        self.refresh(audit_file);
+        Ok(())
+    }
+
+    /// Undo the last annotation and select the effect it was made on. Returns
+    /// the base effect whose tree was restored.
+    pub fn undo(&mut self, audit_file: &mut AuditFile) -> Option<EffectInstance> {
+        let base = self.history.undo(audit_file)?;
+        self.refresh(audit_file);
+        if let Some(i) = self.pending.iter().position(|p| p.base == base) {
+            self.selected = i;
+        }
+        Some(base)
+    }
+}
+
+const HELP: &str = "(s)afe  (u)nsafe  (c)aller checked  ask me (l)ater  go (b)ack  \
+                    ↑/↓ move  e(x)it";
+
+fn pending_item(audit_file: &AuditFile, p: &PendingEffect) -> ListItem<'static> {
+    let loc = audit_file
+        .audit_trees
+        .get(&p.base)
+        .and_then(|t| tree_at_path(t, &p.path))
+        .map(|t| tree_info(t).callee_loc.to_string())
+        .unwrap_or_default();
+    let indent = "  ".repeat(p.path.len());
+    ListItem::new(vec![
+        Line::from(format!("{}{}", indent, p.base.callee_path())),
+        Line::from(Span::styled(
+            format!("{}  {}", indent, loc),
+            Style::default().fg(Color::DarkGray),
+        )),
+    ])
+}
+
+/// The source lines around `effect`, centered in a pane of `height` lines
+fn source_lines(effect: &EffectInfo, height: usize) -> Vec<Line<'static>> {
+    let loc = &effect.callee_loc;
+    let mut full_path = loc.dir().clone();
+    full_path.push(loc.file());
+    let src = match std::fs::read_to_string(&full_path) {
+        Ok(src) => src,
+        Err(e) => {
+            return vec![Line::from(format!(
+                "Couldn't read {}: {}",
+                full_path.to_string_lossy(),
+                e
+            ))]
+        }
+    };
+
+    // NOTE: SrcLocs are 1-indexed
+    let (start, end) = (loc.start_line(), loc.end_line());
+    let first = start.saturating_sub(height.saturating_sub(end + 1 - start) / 2).max(1);
+    src.lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l))
+        .skip(first - 1)
+        .take(height)
+        .map(|(n, l)| {
+            let style = if (start..=end).contains(&n) {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Line::from(Span::styled(format!("{:>5} │ {}", n, l), style))
+        })
+        .collect()
+}
+
+fn draw(frame: &mut Frame, tui: &TuiAudit, audit_file: &AuditFile, status: &str) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(2)])
+        .split(frame.size());
+    let cols = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
+        .split(rows[0]);
+
+    let items =
+        tui.pending().iter().map(|p| pending_item(audit_file, p)).collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Unaudited effects ({})", tui.pending().len())),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state =
+        ListState::default().with_selected(tui.selected().map(|_| tui.selected));
+    frame.render_stateful_widget(list, cols[0], &mut list_state);
+
+    let stack = tui.call_stack(audit_file);
+    let stack_height = (stack.len() as u16 + 2).min(cols[1].height / 2);
+    let panes = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(stack_height)])
+        .split(cols[1]);
+
+    if let (Some(p), Some(curr)) = (tui.selected(), stack.last()) {
+        let src = Paragraph::new(source_lines(
+            curr,
+            panes[0].height.saturating_sub(2) as usize,
+        ))
+        .block(Block::default().borders(Borders::ALL).title(effect_label(&p.base, curr)));
+        frame.render_widget(src, panes[0]);
+
+        let stack_lines = stack
+            .iter()
+            .enumerate()
+            .map(|(i, e)| {
+                Line::from(format!(
+                    "{}{} ({})",
+                    "  ".repeat(i),
+                    e.caller_path,
+                    e.callee_loc
+                ))
+            })
+            .collect::<Vec<_>>();
+        let stack_pane = Paragraph::new(stack_lines)
+            .block(Block::default().borders(Borders::ALL).title("Call stack"));
+        frame.render_widget(stack_pane, panes[1]);
+    } else {
+        let done = Paragraph::new("No more effects to audit")
+            .block(Block::default().borders(Borders::ALL));
+        frame.render_widget(done, cols[1]);
+    }
+
+    let footer = Paragraph::new(vec![Line::from(status.to_string()), Line::from(HELP)]);
+    frame.render_widget(footer, Rect { height: 2, ..rows[1] });
+}
+
+fn run(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    audit_file: &mut AuditFile,
+    scan_res: &ScanResults,
+) -> Result<()> {
+    let mut tui = TuiAudit::new(audit_file);
+    let mut status = String::new();
+
+    loop {
+        terminal.draw(|f| draw(f, &tui, audit_file, &status))?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        let annotation = match key.code {
+            KeyCode::Char('s') => Some(SafetyAnnotation::Safe),
+            KeyCode::Char('u') => Some(SafetyAnnotation::Unsafe),
+            KeyCode::Char('c') => Some(SafetyAnnotation::CallerChecked),
+            KeyCode::Char('l') => Some(SafetyAnnotation::Skipped),
+            KeyCode::Char('b') => {
+                status = match tui.undo(audit_file) {
+                    Some(e) => format!("Undid the annotation of {}", e.callee_path()),
+                    None => "Nothing to undo".to_string(),
+                };
+                None
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                tui.select_prev();
+                None
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                tui.select_next();
+                None
+            }
+            KeyCode::Char('x') | KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            _ => None,
+        };
+
+        if let Some(a) = annotation {
+            if tui.selected().is_some() {
+                status = match tui.annotate(audit_file, scan_res, a) {
+                    Ok(()) => format!("Marked effect as {}", a),
+                    Err(e) => format!("Error annotating effect: {}", e),
+                };
+            }
+        }
+    }
+}
+
+/// Perform the auditing process on the unaudited effects in the audit file
+/// using the full-screen terminal interface. Auditing the effects of
+/// dependencies isn't supported in this mode.
+pub fn start_tui_audit(audit_file: &mut AuditFile, scan_res: ScanResults) -> Result<()> {
+    // Give the terminal back before a panic message is printed, so it isn't
+    // lost in the alternate screen with raw mode still on
+    let prev_hook = Arc::new(panic::take_hook());
+    let hook = Arc::clone(&prev_hook);
+    panic::set_hook(Box::new(move |info| {
+        let _ = restore_terminal();
+        hook(info);
+    }));
+    let res = run_in_terminal(audit_file, &scan_res);
+    panic::set_hook(Box::new(move |info| prev_hook(info)));
+    res?;
+
+    // The audit may have been left early
+    if TuiAudit::new(audit_file).pending().is_empty() {
+        println!("No more effects to audit");
+    }
+    audit_file.recalc_pub_caller_checked(&scan_res.pub_fns);
+
+    Ok(())
+}
+
+fn run_in_terminal(audit_file: &mut AuditFile, scan_res: &ScanResults) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let res = run(&mut terminal, audit_file, scan_res);
+
+    // Always give the terminal back, even if the audit failed
+    restore_terminal()?;
+    terminal.show_cursor()?;
+    res
+}
+
+fn restore_terminal() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)
+}
@@ -1,4 +1,5 @@
 use crate::audit_file::AuditFile;
+use crate::ident::glob_match;
 
 use std::fs::File;
 use std::io::Read;
@@ -28,6 +29,17 @@ where
     Ok(hasher.finalize().into())
 }
 
+/// Returns true if the string (e.g. a callee path) matches the pattern.
+/// Patterns containing `*` are globs over the whole string; otherwise any
+/// substring matches.
+pub fn pattern_matches(pattern: &str, s: &str) -> bool {
+    if pattern.contains('*') {
+        glob_match(pattern, s)
+    } else {
+        s.contains(pattern)
+    }
+}
+
 pub fn is_audit_scan_valid<P>(audit_file: &AuditFile, crate_path: P) -> Result<bool>
 where
     P: AsRef<Path>,
@@ -3,14 +3,18 @@
 //! See README for current usage information.
 
 use cargo_scan::audit_file::*;
-use cargo_scan::auditing::audit::start_audit;
+use cargo_scan::auditing::audit::{
+    audit_without_test_effects, start_audit, start_scoped_audit,
+};
 use cargo_scan::auditing::info::Config;
 use cargo_scan::auditing::reset::reset_annotation;
-use cargo_scan::auditing::review::review_audit;
+use cargo_scan::auditing::review::{review_audit, review_audit_json};
+use cargo_scan::auditing::tui::start_tui_audit;
 use cargo_scan::auditing::util::{hash_dir, is_audit_scan_valid};
 use cargo_scan::effect::{EffectInstance, EffectType, DEFAULT_EFFECT_TYPES};
-use cargo_scan::ident::IdentPath;
+use cargo_scan::ident::{CanonicalPath, IdentPath};
 use cargo_scan::scanner::{self, scan_crate};
+use cargo_scan::sink::Sink;
 use cargo_scan::util::load_cargo_toml;
 
 use std::collections::{HashMap, HashSet};
@@ -19,10 +23,10 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, ValueEnum};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
 use home::home_dir;
 use inquire::{validator::Validation, Text};
-use petgraph::dot::Dot;
 
 /// Interactively vet a package audit
 #[derive(Parser, Debug)]
@@ -64,11 +68,25 @@ struct Args {
     #[clap(long, default_value_t = ReviewInfo::PubFuns)]
     review_info: ReviewInfo,
 
+    /// With `--review-info all`, print the review as JSON
+    #[clap(long, default_value_t = false, requires = "review")]
+    json: bool,
+
     /// Preview the effects in a package without performing an audit or saving
     /// an audit file
     #[clap(short, long, default_value_t = false)]
     preview: bool,
 
+    /// Print the public functions that the default caller-checked audit
+    /// exports as sinks to dependents, without saving an audit file
+    #[clap(long, default_value_t = false)]
+    exported_sinks: bool,
+
+    /// Write a Markdown report of the existing audit file to the given path,
+    /// without performing an audit
+    #[clap(long)]
+    markdown_report: Option<PathBuf>,
+
     /// Reset an annotation to "skipped" for a base effect
     #[clap(long)]
     reset_annotation: bool,
@@ -83,6 +101,15 @@ struct Args {
     #[clap(short, long, default_value_t = false)]
     ignore_hash: bool,
 
+    /// Audit in a full-screen terminal interface instead of with line prompts
+    #[clap(long, default_value_t = false)]
+    tui: bool,
+
+    /// Only audit the effects reachable from the given function (full
+    /// canonical path, e.g. my_crate::my_mod::my_fn)
+    #[clap(long)]
+    scope_fn: Option<String>,
+
     /// Dump the callgraph to the specified file. Uses the DOT format.
     #[clap(long)]
     dump_callgraph: Option<String>,
@@ -92,6 +119,11 @@ struct Args {
     #[clap(long, value_parser, num_args = 1.., default_values_t = DEFAULT_EFFECT_TYPES)]
     effect_types: Vec<EffectType>,
 
+    /// File with additional sink patterns, one per line, like
+    /// data/of_interest.txt. Idents may contain `*` wildcards.
+    #[clap(long)]
+    sinks_file: Option<PathBuf>,
+
     /// TESTING ONLY: Import all caller-checked public functions from audits in
     /// a folder as additional sinks for an audit. This functionality should
     /// eventuallly be replaced by the chain binary, but is included here for
@@ -102,6 +134,21 @@ struct Args {
     /// TESTING ONLY: Use the quick-mode scan option
     #[clap(long, default_value_t = false)]
     quick_mode: bool,
+
+    /// Save the scan results to the given file, and reuse them instead of
+    /// re-scanning when neither the crate nor the scan options have changed
+    #[clap(long)]
+    scan_cache: Option<PathBuf>,
+
+    /// Leave out the effects in test-only code, e.g. in `#[test]` functions
+    #[clap(long, default_value_t = false)]
+    exclude_tests: bool,
+
+    /// Treat a method call through a generic type bounded by a trait as a
+    /// call to every impl of the method in the crate, like calls on `dyn
+    /// Trait` objects. Over-approximates the callers of each impl.
+    #[clap(long, default_value_t = false)]
+    virtual_dispatch: bool,
 }
 
 #[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
@@ -233,7 +280,7 @@ fn get_sinks(sinks_folder: Option<&String>) -> Result<HashSet<IdentPath>> {
 }
 
 fn audit_crate(args: Args, audit_file: Option<AuditFile>) -> Result<()> {
-    let scan_res = {
+    let mut scan_res = {
         let relevant_effects = if let Some(p) = &audit_file {
             &p.scanned_effects
         } else {
@@ -241,24 +288,43 @@ fn audit_crate(args: Args, audit_file: Option<AuditFile>) -> Result<()> {
         };
 
         // Load extra sinks if we have any
-        let sinks =
+        let mut sinks =
             get_sinks(args.sinks_folder.as_ref()).unwrap_or_else(|_| HashSet::new());
+        if let Some(sinks_file) = &args.sinks_file {
+            sinks.extend(Sink::load_from_file(sinks_file)?);
+        }
 
         println!("Scanning crate...");
-        scanner::scan_crate_with_sinks(
-            &args.crate_path,
+        let options = scanner::ScanOptions {
             sinks,
-            relevant_effects,
-            args.quick_mode,
-        )?
+            quick_mode: args.quick_mode,
+            virtual_dispatch: args.virtual_dispatch,
+            ..scanner::ScanOptions::default()
+        };
+        match &args.scan_cache {
+            Some(cache) => scanner::scan_crate_cached(
+                &args.crate_path,
+                relevant_effects,
+                &options,
+                cache,
+            )?,
+            None => scanner::scan_crate_with_options(
+                &args.crate_path,
+                relevant_effects,
+                &options,
+            )?,
+        }
     };
+    if args.exclude_tests {
+        scan_res.remove_test_effects();
+    }
     let scan_effects = scan_res.effects_set();
 
     if let Some(callgraph_file) = &args.dump_callgraph {
         let path = Path::new(callgraph_file);
         if !path.exists() {
             let mut file = File::create(callgraph_file)?;
-            file.write_all(&format!("{}", Dot::new(&scan_res.call_graph)).into_bytes())?;
+            file.write_all(scan_res.call_graph_dot().as_bytes())?;
         } else {
             println!("Callgraph filepath already exists");
         }
@@ -307,7 +373,33 @@ fn audit_crate(args: Args, audit_file: Option<AuditFile>) -> Result<()> {
         }
     };
 
-    if start_audit(&mut audit_file, scan_res, &args.config)?.is_some() {
+    let pub_fns = scan_res.pub_fns.clone();
+    let run_audit = |audit_file: &mut AuditFile| -> Result<Option<EffectInstance>> {
+        match &args.scope_fn {
+            Some(_) if args.tui => {
+                Err(anyhow!("--tui can't be combined with --scope-fn"))
+            }
+            None if args.tui => {
+                start_tui_audit(audit_file, scan_res)?;
+                Ok(None)
+            }
+            Some(scope_fn) => start_scoped_audit(
+                audit_file,
+                scan_res,
+                &CanonicalPath::new(scope_fn),
+                &args.config,
+            ),
+            None => start_audit(audit_file, scan_res, &args.config),
+        }
+    };
+    // An audit started without --exclude-tests keeps its test effects; they
+    // are only hidden for this run
+    let dependency_effect = if args.exclude_tests {
+        audit_without_test_effects(&mut audit_file, &pub_fns, run_audit)?
+    } else {
+        run_audit(&mut audit_file)?
+    };
+    if dependency_effect.is_some() {
         // The user marked that they want to audit a child effect, but we aren't
         // able to do so in this mode.
         return Err(anyhow!("Can't audit dependency crate effects in this binary"));
@@ -338,6 +430,33 @@ fn runner(args: Args) -> Result<()> {
             println!("{}", effect.to_csv());
         }
         Ok(())
+    } else if args.exported_sinks {
+        println!("Scanning crate...");
+        let audit_file = AuditFile::new_caller_checked_default_with_sinks(
+            &args.crate_path,
+            HashSet::new(),
+            &args.effect_types,
+            args.quick_mode,
+            None,
+        )?;
+
+        println!("Public functions exported as caller-checked sinks:");
+        for (effect_type, pub_fns) in audit_file.exported_sinks() {
+            println!("  {}:", effect_type);
+            for pub_fn in pub_fns {
+                println!("    {}", pub_fn);
+            }
+        }
+        Ok(())
+    } else if let Some(report_path) = &args.markdown_report {
+        match audit_file {
+            None => Err(anyhow!("Audit file to report on doesn't exist")),
+            Some(af) => {
+                fs::write(report_path, af.to_markdown_report())?;
+                println!("Wrote audit report to {}", report_path.display());
+                Ok(())
+            }
+        }
     } else if args.reset_annotation {
         match audit_file {
             None => Err(anyhow!("Audit file doesn't exist")),
@@ -348,6 +467,12 @@ fn runner(args: Args) -> Result<()> {
             None => Err(anyhow!("Audit file to review doesn't exist")),
             Some(af) => {
                 match args.review_info {
+                    ReviewInfo::All if args.json => review_audit_json(
+                        &af,
+                        &args.crate_path,
+                        args.quick_mode,
+                        args.ignore_hash,
+                    ),
                     ReviewInfo::All => review_audit(
                         &af,
                         &args.crate_path,
@@ -374,6 +499,14 @@ fn runner(args: Args) -> Result<()> {
 fn main() {
     cargo_scan::util::init_logging();
     let mut args = Args::parse();
+    if args.json && args.review_info != ReviewInfo::All {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--json requires --review-info all",
+            )
+            .exit();
+    }
 
     if let Some(audit_file_path) = &mut args.audit_file_path {
         // If the user-chosen audit file path is a directory, make the audit path
@@ -0,0 +1,45 @@
+//! The audit_diff binary: Compare two audit files, e.g. for two versions of a
+//! crate, and print the effects and annotations that changed.
+//!
+//! See README for current usage information.
+
+use cargo_scan::audit_file::AuditFile;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Compare two audit files.",
+    long_about = "Compare two audit files and print the base effects that were \
+                  added or removed, and the locations whose safety annotation \
+                  changed. Useful for re-reviewing only what changed between \
+                  two versions of a crate."
+)]
+struct Args {
+    /// Path to the old audit file
+    old: PathBuf,
+
+    /// Path to the new audit file
+    new: PathBuf,
+}
+
+fn read_audit(path: PathBuf) -> Result<AuditFile> {
+    AuditFile::read_audit_file(path.clone())?
+        .ok_or_else(|| anyhow!("Audit file doesn't exist: {}", path.display()))
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+
+    let old = read_audit(args.old)?;
+    let new = read_audit(args.new)?;
+    print!("{}", AuditFile::diff(&old, &new));
+
+    Ok(())
+}
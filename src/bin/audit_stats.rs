@@ -217,6 +217,7 @@ fn download_crate(crate_path: &Path, crate_id: &CrateId) -> Result<bool> {
             &crate_id.crate_name,
             &crate_id.version.to_string(),
             download_dir.to_str().unwrap(),
+            None,
         )?;
         return Ok(true);
     }
@@ -34,6 +34,11 @@ struct Args {
     /// Run in quick mode (turns off RustAnalyzer)
     #[clap(long, default_value_t = false)]
     quick_mode: bool,
+
+    /// Maximum depth of caller-checked trees; deeper callers are marked
+    /// truncated, to review manually
+    #[clap(long)]
+    max_depth: Option<usize>,
 }
 
 // TODO: Combine this with DefaultAuditType once we implement every version
@@ -52,11 +57,17 @@ fn runner(args: Args) -> Result<()> {
     }
 
     let audit_file = match args.audit_type {
-        AuditType::CallerChecked => AuditFile::new_caller_checked_default(
-            &args.crate_path,
-            &EffectType::unsafe_effects(),
-            args.quick_mode,
-        )?,
+        AuditType::CallerChecked => {
+            AuditFile::new_caller_checked_default_with_sinks_and_results(
+                &args.crate_path,
+                HashSet::new(),
+                &EffectType::unsafe_effects(),
+                args.quick_mode,
+                None,
+                args.max_depth,
+            )?
+            .0
+        }
         AuditType::Safe => AuditFile::new_safe_default_with_sinks(
             &args.crate_path,
             HashSet::new(),
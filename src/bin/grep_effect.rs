@@ -0,0 +1,83 @@
+//! The grep_effect binary: Print the effects in an existing audit file that
+//! match a query, e.g. all unaudited FFI calls.
+//!
+//! See README for current usage information.
+
+use cargo_scan::audit_file::{AuditFile, EffectQuery, EffectTree, SafetyAnnotation};
+use cargo_scan::effect::EffectType;
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Query the effects in an audit file.",
+    long_about = "Print the base effects in an audit file that match all of \
+                  the given filters, one per line, without re-running the \
+                  audit. Useful for re-checking one category of effects in an \
+                  existing audit."
+)]
+struct Args {
+    /// Path to the audit file
+    audit_file: PathBuf,
+
+    /// Only print effects of the given types
+    #[clap(long, value_parser, num_args = 1..)]
+    effect_types: Vec<EffectType>,
+
+    /// Only print effects with the given annotation anywhere in their effect
+    /// tree (`skipped` for effects that haven't been fully audited)
+    #[clap(long, value_enum)]
+    annotation: Option<SafetyAnnotation>,
+
+    /// Only print effects in files whose path matches the pattern (a
+    /// substring, or a glob such as `*/src/net/*`)
+    #[clap(long)]
+    file: Option<String>,
+
+    /// Only print effects whose callee path matches the pattern (a
+    /// substring, or a glob such as `libc::*`)
+    #[clap(long)]
+    callee: Option<String>,
+}
+
+/// Annotation of the base effect: its own if it is a leaf, or caller-checked
+fn base_annotation(tree: &EffectTree) -> SafetyAnnotation {
+    tree.get_leaf_annotation().unwrap_or(SafetyAnnotation::CallerChecked)
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+
+    let audit_file =
+        AuditFile::read_audit_file(args.audit_file.clone())?.ok_or_else(|| {
+            anyhow!("Audit file doesn't exist: {}", args.audit_file.display())
+        })?;
+    let query = EffectQuery {
+        effect_types: args.effect_types,
+        annotation: args.annotation,
+        file: args.file,
+        callee: args.callee,
+    };
+
+    for (effect, tree) in audit_file.query_effects(&query) {
+        let loc = effect.call_loc();
+        println!(
+            "{}:{}:{}: {} {} -> {} [{}]",
+            loc.filepath_string(),
+            loc.start_line(),
+            loc.start_col(),
+            EffectType::from(effect.eff_type()),
+            effect.caller_path(),
+            effect.callee_path(),
+            base_annotation(tree)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! The regression binary: Report effects in a local crate that are not in its
+//! published version on crates.io, e.g. before publishing a new version.
+//!
+//! Exits with an error if there are any new effects.
+//!
+//! See README for current usage information.
+
+use cargo_scan::effect::{EffectType, DEFAULT_EFFECT_TYPES};
+use cargo_scan::regression;
+
+use std::path::PathBuf;
+use std::process::exit;
+
+use anyhow::Result;
+use clap::Parser;
+use url::Url;
+
+#[derive(Parser, Debug)]
+#[command(
+    author,
+    version,
+    about = "Report effects that are new since the published version of a crate.",
+    long_about = "Download the published version of a crate from crates.io, scan \
+                  it along with the local crate, and report the effects in the \
+                  local crate that are not in the published version."
+)]
+struct Args {
+    /// Path to crate directory; should contain a 'src' directory and a Cargo.toml file
+    crate_path: PathBuf,
+
+    /// Published version to compare against (defaults to the latest version)
+    #[clap(long)]
+    published_version: Option<String>,
+
+    /// Path to download the published crate to
+    #[clap(short = 'd', long = "crate-download-path", default_value = ".regression_tmp")]
+    crate_download_path: String,
+
+    /// Registry mirror to download the published crate from instead of
+    /// crates.io: a base URL serving `<name>/<version>/download`, or a
+    /// `file://` URL of a directory of `<name>-<version>.crate` files.
+    /// Requires --published-version.
+    #[clap(long, requires = "published_version")]
+    registry: Option<Url>,
+
+    /// Run in quick mode (turns off RustAnalyzer)
+    #[clap(short, long, default_value_t = false)]
+    quick_mode: bool,
+
+    /// The types of Effects to report. Defaults to all unsafe behavior.
+    #[clap(long, value_parser, num_args = 1.., default_values_t = DEFAULT_EFFECT_TYPES)]
+    effect_types: Vec<EffectType>,
+}
+
+fn main() -> Result<()> {
+    cargo_scan::util::init_logging();
+    let args = Args::parse();
+
+    let (version, effects) = regression::new_effects_since_published(
+        &args.crate_path,
+        args.published_version.as_deref(),
+        &args.crate_download_path,
+        args.registry.as_ref(),
+        &args.effect_types,
+        args.quick_mode,
+    )?;
+
+    if effects.is_empty() {
+        println!("No new effects since published version {}", version);
+        return Ok(());
+    }
+    println!("New effects since published version {} ({}):", version, effects.len());
+    for e in &effects {
+        println!("  + {} -> {} ({})", e.caller(), e.callee(), e.call_loc());
+    }
+    exit(1);
+}
@@ -5,17 +5,36 @@
 //!
 //! See README for current usage information.
 
-use cargo_scan::effect::EffectInstance;
+use cargo_scan::effect::{
+    EffectFilter, EffectInstance, EffectProvenance, EffectType, Severity,
+    DEFAULT_EFFECT_TYPES,
+};
+use cargo_scan::ident::CanonicalPath;
+use cargo_scan::output::manifest::ScanManifest;
+use cargo_scan::output::ndjson;
+use cargo_scan::output::sarif;
+use cargo_scan::scan_config::ScanConfig;
 use cargo_scan::scan_stats::{self, CrateStats};
+use cargo_scan::scanner;
+use cargo_scan::sink::{Sink, SinkCategory};
+use cargo_scan::util;
 
-use clap::Parser;
-use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use comfy_table::{presets, ContentArrangement, Table};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::exit;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to crate directory; should contain a 'src' directory and a Cargo.toml file
-    crate_path: PathBuf,
+    #[clap(required_unless_present = "explain")]
+    crate_path: Option<PathBuf>,
 
     // Turned off for now -- chain binary not being used
     // /// Include transitive effects in dependency crates
@@ -32,21 +51,609 @@ struct Args {
     /// Suppress "total" lines at the bottom of the output
     #[clap(short, long, default_value_t = false)]
     suppress_total: bool,
+
+    /// File with additional sink patterns, one per line, like
+    /// data/of_interest.txt. Idents may contain `*` wildcards.
+    #[clap(long)]
+    sinks_file: Option<PathBuf>,
+
+    /// Only output sink calls in the given capability category
+    #[clap(long, value_enum)]
+    only_category: Option<SinkCategory>,
+
+    /// Only output effects of at least the given severity
+    #[clap(long, value_enum)]
+    min_severity: Option<Severity>,
+
+    /// Only output effects found directly in the source, only effects in the
+    /// arguments of macros like `println!`, or only effects in macro
+    /// definitions (with --macro-definitions)
+    #[clap(long, value_enum)]
+    only: Option<EffectProvenance>,
+
+    /// Also output the potential effects in the code of `macro_rules!`
+    /// definitions, which happen wherever the macro is invoked
+    #[clap(long, conflicts_with = "stream")]
+    macro_definitions: bool,
+
+    /// Also scan the code under `#[cfg(feature = ...)]` for the given
+    /// comma-separated features
+    #[clap(long, value_delimiter = ',', value_name = "FEATURES")]
+    features: Vec<String>,
+
+    /// The types of effects to report, e.g. `--effect-type ffi-call` (may be
+    /// repeated). Defaults to the effect types in the crate's
+    /// .cargo-scan.toml, or else all unsafe behavior.
+    #[clap(
+        long = "effect-type",
+        visible_alias = "effect-types",
+        value_name = "EFFECT_TYPE",
+        value_parser,
+        num_args = 1..
+    )]
+    effect_types: Vec<EffectType>,
+
+    /// Output format for the effects. The ndjson and sarif formats start
+    /// with a manifest of the scan options, output filters and the crate
+    /// source hash.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+
+    /// Write the effects in each source file to a separate file in the given
+    /// directory, named after the source path (e.g. `src/lib.rs.csv`), instead
+    /// of printing them. Supports the csv and ndjson formats.
+    #[clap(long, value_name = "DIR")]
+    output_per_file: Option<PathBuf>,
+
+    /// Print each effect as soon as its file is scanned, instead of collecting
+    /// all of them first, to bound memory use on very large crates. Requires
+    /// quick mode (from -q or the config file) and the csv or ndjson format.
+    /// Scan metadata and options that need the call graph are unavailable.
+    #[clap(long, conflicts_with_all = ["output_per_file", "dump_callgraph"])]
+    stream: bool,
+
+    /// Skip source files larger than the given number of bytes, e.g. huge
+    /// generated files. Skipped files are reported on stderr.
+    #[clap(long, value_name = "BYTES", conflicts_with = "stream")]
+    max_file_size: Option<u64>,
+
+    /// Print the location of each macro, conditional code and other construct
+    /// the scanner skipped to stderr, for manual review
+    #[clap(long, conflicts_with = "stream")]
+    record_skipped: bool,
+
+    /// Dump the call graph to the specified file in the DOT format
+    #[clap(long)]
+    dump_callgraph: Option<PathBuf>,
+
+    /// Print the public functions with a safe signature that contain or
+    /// transitively call unsafe code, one per line, and exit
+    #[clap(long, conflicts_with_all = ["stream", "output_per_file", "fail_on"])]
+    encapsulated_unsafe: bool,
+
+    /// Report on stderr if the crate is `no_std` but still uses an allocator
+    /// through the `alloc` crate (informational)
+    #[clap(long, conflicts_with = "stream")]
+    no_std_alloc: bool,
+
+    /// Report on stderr the unsafe blocks without any unsafe operations,
+    /// which could be removed (informational)
+    #[clap(long, conflicts_with = "stream")]
+    empty_unsafe: bool,
+
+    /// Print the effective configuration, merging the crate's .cargo-scan.toml
+    /// with the command-line options, and exit without scanning
+    #[clap(long)]
+    config_check: bool,
+
+    /// Print an explanation of the given effect type and exit
+    #[clap(long, value_name = "EFFECT_TYPE")]
+    explain: Option<EffectType>,
+
+    /// Exit with code 1 if any effects of the given types are found, e.g.
+    /// `--fail-on ffi-call,unsafe-call`, after printing them on stderr. For
+    /// gating changes in CI. Also exits with code 1 if the scan fails.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        value_name = "EFFECT_TYPE",
+        conflicts_with = "stream"
+    )]
+    fail_on: Vec<EffectType>,
+}
+
+impl Args {
+    /// The filters on the effects to output
+    fn filter(&self) -> EffectFilter {
+        EffectFilter {
+            only_category: self.only_category,
+            only: self.only,
+            min_severity: self.min_severity,
+        }
+    }
+}
+
+/// The settings for a scan after merging the config file and the
+/// command-line options, as printed by `--config-check`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct EffectiveConfig {
+    config_file: Option<PathBuf>,
+    quick_mode: bool,
+    stream: bool,
+    macro_definitions: bool,
+    encapsulated_unsafe: bool,
+    effect_types: Vec<EffectType>,
+    /// Sink patterns in addition to the default ones
+    sinks: BTreeSet<String>,
+    features: Vec<String>,
+    only_category: Option<SinkCategory>,
+    only: Option<EffectProvenance>,
+    min_severity: Option<Severity>,
+    max_file_size: Option<u64>,
+    fail_on: Vec<EffectType>,
+}
+
+impl EffectiveConfig {
+    /// Merge the crate's config file with the command-line options, which
+    /// take precedence. Fails on options that conflict with each other.
+    fn resolve(args: &Args, crate_path: &Path) -> Result<Self> {
+        let config = ScanConfig::load(crate_path)?;
+        let config_file = Some(ScanConfig::path(crate_path)).filter(|p| p.is_file());
+
+        let mut sinks: BTreeSet<String> =
+            config.sink_paths()?.iter().map(|s| s.to_string()).collect();
+        if let Some(sinks_file) = &args.sinks_file {
+            let file_sinks =
+                Sink::load_from_file(sinks_file).context("Failed to load sinks")?;
+            sinks.extend(file_sinks.iter().map(|s| s.to_string()));
+        }
+
+        let effect_types = if !args.effect_types.is_empty() {
+            args.effect_types.clone()
+        } else {
+            config.effect_types.unwrap_or_else(|| DEFAULT_EFFECT_TYPES.to_vec())
+        };
+        let quick_mode = args.quick_mode || config.quick_mode;
+
+        if effect_types.is_empty() {
+            bail!("No effect types to report");
+        }
+        if args.stream && !quick_mode {
+            bail!("--stream requires quick mode");
+        }
+        if args.only_category.is_some() && !effect_types.contains(&EffectType::SinkCall) {
+            bail!("--only-category only applies to sink calls, which aren't reported");
+        }
+        if let Some(t) = args.fail_on.iter().find(|t| !effect_types.contains(t)) {
+            bail!("--fail-on {} can't fail, since {} effects aren't reported", t, t);
+        }
+
+        Ok(Self {
+            config_file,
+            quick_mode,
+            stream: args.stream,
+            macro_definitions: args.macro_definitions,
+            encapsulated_unsafe: args.encapsulated_unsafe,
+            effect_types,
+            sinks,
+            features: args.features.clone(),
+            only_category: args.only_category,
+            only: args.only,
+            min_severity: args.min_severity,
+            max_file_size: args.max_file_size,
+            fail_on: args.fail_on.clone(),
+        })
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// CSV, followed by scan metadata
+    Csv,
+    /// One JSON object per line for each effect
+    Ndjson,
+    /// Aligned terminal table, for interactive inspection
+    Table,
+    /// SARIF log, for code scanning in CI
+    Sarif,
+}
+
+/// Maximum width of a path in the table output before it gets truncated
+const TABLE_MAX_PATH_LEN: usize = 48;
+
+/// Shorten a path for the table output, keeping the end of the path since
+/// that's usually the most informative part
+fn truncate_path(path: &str) -> String {
+    let len = path.chars().count();
+    if len <= TABLE_MAX_PATH_LEN {
+        path.to_string()
+    } else {
+        let tail: String = path.chars().skip(len - (TABLE_MAX_PATH_LEN - 3)).collect();
+        format!("...{}", tail)
+    }
+}
+
+fn severity(effect: &EffectInstance) -> Severity {
+    EffectType::from(effect.eff_type()).severity()
+}
+
+fn print_table(effects: &[EffectInstance]) {
+    let mut table = Table::new();
+    table
+        .load_preset(presets::UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["location", "type", "caller", "callee", "severity"]);
+
+    for effect in effects {
+        let loc = effect.call_loc();
+        let location = format!(
+            "{}:{}:{}",
+            loc.dir().join(loc.file()).to_string_lossy(),
+            loc.start_line(),
+            loc.start_col()
+        );
+        table.add_row(vec![
+            truncate_path(&location),
+            EffectType::from(effect.eff_type()).to_string(),
+            truncate_path(effect.caller_path()),
+            truncate_path(effect.callee_path()),
+            severity(effect).to_string(),
+        ]);
+    }
+
+    println!("{table}");
+}
+
+fn stream_effects(
+    crate_path: &Path,
+    sinks: &HashSet<CanonicalPath>,
+    config: &EffectiveConfig,
+    filter: &EffectFilter,
+    format: OutputFormat,
+    manifest: Option<&ScanManifest>,
+) -> Result<()> {
+    if !matches!(format, OutputFormat::Csv | OutputFormat::Ndjson) {
+        bail!("Streaming output only supports the csv and ndjson formats");
+    }
+    let sinks = sinks.iter().map(|s| s.clone().to_path()).collect();
+    let mut stdout = io::stdout().lock();
+    match (format, manifest) {
+        (OutputFormat::Csv, _) => writeln!(stdout, "{}", EffectInstance::csv_header())?,
+        (_, Some(manifest)) => ndjson::write_manifest(&mut stdout, manifest)?,
+        _ => (),
+    }
+    scanner::scan_crate_streaming(
+        crate_path,
+        sinks,
+        &config.effect_types,
+        &config.features,
+        |e| {
+            if !filter.matches(e) {
+                return Ok(());
+            }
+            match format {
+                OutputFormat::Csv => writeln!(stdout, "{}", e.to_csv())?,
+                _ => ndjson::write_effect(&mut stdout, e)?,
+            }
+            Ok(())
+        },
+    )?;
+    Ok(())
+}
+
+fn print_sarif(
+    crate_path: &Path,
+    effects: &[EffectInstance],
+    manifest: &ScanManifest,
+) -> Result<()> {
+    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+    let log = sarif::effects_to_sarif(effects, &crate_name, Some(manifest));
+    serde_json::to_writer_pretty(io::stdout().lock(), &log)?;
+    println!();
+    Ok(())
+}
+
+/// Write the effects of each source file to `<out_dir>/<source path>.<ext>`,
+/// where the source path is relative to the crate
+fn write_per_file(
+    out_dir: &Path,
+    crate_path: &Path,
+    effects: &[EffectInstance],
+    format: OutputFormat,
+    manifest: Option<&ScanManifest>,
+) -> Result<()> {
+    let ext = match format {
+        OutputFormat::Csv => "csv",
+        OutputFormat::Ndjson => "ndjson",
+        _ => bail!("--output-per-file only supports the csv and ndjson formats"),
+    };
+
+    let mut by_file: BTreeMap<PathBuf, Vec<&EffectInstance>> = BTreeMap::new();
+    for effect in effects {
+        let loc = effect.call_loc();
+        let src_path = loc.dir().join(loc.file());
+        let rel_path = match src_path.strip_prefix(crate_path) {
+            Ok(p) => p.to_path_buf(),
+            Err(_) => PathBuf::from(loc.file()),
+        };
+        by_file.entry(rel_path).or_default().push(effect);
+    }
+
+    for (rel_path, effects) in by_file {
+        let mut out_path = out_dir.join(&rel_path).into_os_string();
+        out_path.push(format!(".{}", ext));
+        let out_path = PathBuf::from(out_path);
+        if let Some(parent) = out_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut out = io::BufWriter::new(fs::File::create(&out_path)?);
+        match (format, manifest) {
+            (OutputFormat::Csv, _) => writeln!(out, "{}", EffectInstance::csv_header())?,
+            (_, Some(manifest)) => ndjson::write_manifest(&mut out, manifest)?,
+            _ => (),
+        }
+        for effect in effects {
+            match format {
+                OutputFormat::Csv => writeln!(out, "{}", effect.to_csv())?,
+                _ => ndjson::write_effect(&mut out, effect)?,
+            }
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Scan the crate, write the reports that need the full scan results (the
+/// call graph, skipped code, and encapsulated unsafe functions), and collect
+/// the stats of the crate. Returns None if only the encapsulated unsafe
+/// functions were asked for.
+fn scan_and_report(
+    args: &Args,
+    crate_path: &Path,
+    config: &EffectiveConfig,
+    sinks: &HashSet<CanonicalPath>,
+) -> Result<Option<CrateStats>> {
+    let options = scanner::ScanOptions {
+        sinks: sinks.iter().map(|s| s.clone().to_path()).collect(),
+        quick_mode: config.quick_mode,
+        enabled_features: config.features.clone(),
+        max_file_size: args.max_file_size,
+        record_skipped: args.record_skipped,
+        ..scanner::ScanOptions::default()
+    };
+    let results =
+        scanner::scan_crate_with_options(crate_path, &config.effect_types, &options)?;
+
+    if let Some(dot_path) = &args.dump_callgraph {
+        if let Err(e) = fs::write(dot_path, results.call_graph_dot()) {
+            eprintln!("Failed to dump the call graph: {:?}", e);
+        }
+    }
+    for (kind, loc) in results.skipped_report() {
+        eprintln!("Skipped {}: {}", kind, loc);
+    }
+    if args.encapsulated_unsafe {
+        for f in results.encapsulated_unsafe_fns() {
+            println!("{}", f);
+        }
+        return Ok(None);
+    }
+
+    let stats = scan_stats::get_crate_stats_from_results(
+        crate_path.to_path_buf(),
+        &config.effect_types,
+        results,
+    )?;
+    Ok(Some(stats))
 }
 
 fn main() {
     cargo_scan::util::init_logging();
     let args = Args::parse();
 
+    if let Some(effect_type) = args.explain {
+        print!("{}", effect_type.doc());
+        return;
+    }
+    let crate_path = args.crate_path.clone().expect("crate path required by clap");
+
+    let config = match EffectiveConfig::resolve(&args, &crate_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {:?}", e);
+            exit(1);
+        }
+    };
+    if args.config_check {
+        match toml::to_string(&config) {
+            Ok(s) => print!("{}", s),
+            Err(e) => eprintln!("Failed to print the configuration: {:?}", e),
+        }
+        return;
+    }
+
+    // Only the structured formats record a manifest, which hashes the crate
+    let manifest = match args.format {
+        OutputFormat::Ndjson | OutputFormat::Sarif => match ScanManifest::new(
+            &crate_path,
+            &config.effect_types,
+            config.sinks.iter().cloned(),
+            config.quick_mode,
+            &config.features,
+            args.filter(),
+        ) {
+            Ok(manifest) => Some(manifest),
+            Err(e) => {
+                eprintln!("Failed to hash the crate: {:?}", e);
+                exit(1);
+            }
+        },
+        _ => None,
+    };
+
     // Note: old version without default_audit:
     // scanner::scan_crate(&args.crate_path, &args.effect_types)?
-    let stats = scan_stats::get_crate_stats_default(args.crate_path, args.quick_mode);
+    let sinks: HashSet<CanonicalPath> =
+        config.sinks.iter().map(|s| CanonicalPath::new(s)).collect();
+
+    if args.stream {
+        if let Err(e) = stream_effects(
+            &crate_path,
+            &sinks,
+            &config,
+            &args.filter(),
+            args.format,
+            manifest.as_ref(),
+        ) {
+            eprintln!("Failed to write effects: {:?}", e);
+        }
+        return;
+    }
+
+    let stats = match scan_and_report(&args, &crate_path, &config, &sinks) {
+        Ok(Some(stats)) => stats,
+        Ok(None) => return,
+        Err(e) if args.encapsulated_unsafe => {
+            eprintln!("Failed to scan for encapsulated unsafe: {:?}", e);
+            return;
+        }
+        Err(e) if !config.fail_on.is_empty() => {
+            eprintln!("Scan crashed, can't check --fail-on: {:?}", e);
+            exit(1);
+        }
+        Err(e) => {
+            eprintln!("Scan crashed, skipping crate: {:?}", e);
+            CrateStats { crate_path, ..Default::default() }
+        }
+    };
+
+    for (file, size) in &stats.skipped_large_files {
+        eprintln!("Skipped {} ({} bytes)", file.to_string_lossy(), size);
+    }
+    for imp in &stats.unsafe_impl_decls {
+        eprintln!("{}: {}", imp, imp.src_loc);
+    }
+    if args.no_std_alloc {
+        for loc in &stats.no_std_alloc_uses {
+            eprintln!("no_std crate uses the alloc crate: {}", loc);
+        }
+    }
+    if args.empty_unsafe {
+        for loc in &stats.empty_unsafe_blocks {
+            eprintln!("Unsafe block without unsafe operations: {}", loc);
+        }
+    }
+
+    let macro_def_effects: &[EffectInstance] =
+        if args.macro_definitions { &stats.macro_def_effects } else { &[] };
+    let filter = args.filter();
+    let effects: Vec<EffectInstance> = stats
+        .effects
+        .iter()
+        .chain(macro_def_effects)
+        .filter(|e| filter.matches(e))
+        .cloned()
+        .collect();
+
+    let failing: Vec<&EffectInstance> = effects
+        .iter()
+        .filter(|e| config.fail_on.contains(&EffectType::from(e.eff_type())))
+        .collect();
+    if !failing.is_empty() {
+        eprintln!("Found {} effects of types in --fail-on:", failing.len());
+        for effect in &failing {
+            eprintln!("{}", effect.to_csv());
+        }
+    }
+    let failed = !failing.is_empty();
+
+    write_effects(&args, &stats, effects, manifest.as_ref());
+    if failed {
+        exit(1);
+    }
+}
+
+/// Write the effects and scan metadata in the output format
+fn write_effects(
+    args: &Args,
+    stats: &CrateStats,
+    effects: Vec<EffectInstance>,
+    manifest: Option<&ScanManifest>,
+) {
+    if let Some(out_dir) = &args.output_per_file {
+        if let Err(e) =
+            write_per_file(out_dir, &stats.crate_path, &effects, args.format, manifest)
+        {
+            eprintln!("Failed to write effects: {:?}", e);
+        }
+        return;
+    }
+
+    if let (OutputFormat::Ndjson, Some(manifest)) = (args.format, manifest) {
+        if let Err(e) =
+            ndjson::write_effects(io::stdout().lock(), &effects, Some(manifest))
+        {
+            eprintln!("Failed to write effects: {:?}", e);
+        }
+        return;
+    }
+
+    if let (OutputFormat::Sarif, Some(manifest)) = (args.format, manifest) {
+        if let Err(e) = print_sarif(&stats.crate_path, &effects, manifest) {
+            eprintln!("Failed to write effects: {:?}", e);
+        }
+        return;
+    }
+
+    // Build script effects are reported in their own section, since they
+    // run at compile time
+    let (build_effects, effects): (Vec<_>, Vec<_>) =
+        effects.into_iter().partition(|e| e.from_build_script());
+    // Likewise for macro definitions, since their effects are only potential
+    let (macro_def_effects, effects): (Vec<_>, Vec<_>) = effects
+        .into_iter()
+        .partition(|e| e.found_via() == EffectProvenance::MacroDefinition);
+
+    if args.format == OutputFormat::Table {
+        print_table(&effects);
+        if !build_effects.is_empty() {
+            println!();
+            println!("Build script effects:");
+            print_table(&build_effects);
+        }
+        if !macro_def_effects.is_empty() {
+            println!();
+            println!("Potential effects from macro definitions:");
+            print_table(&macro_def_effects);
+        }
+        return;
+    }
 
     println!("{}", EffectInstance::csv_header());
-    for effect in &stats.effects {
+    for effect in &effects {
         println!("{}", effect.to_csv());
     }
 
+    if !build_effects.is_empty() {
+        println!();
+        println!("Build script effects:");
+        println!("{}", EffectInstance::csv_header());
+        for effect in &build_effects {
+            println!("{}", effect.to_csv());
+        }
+    }
+
+    if !macro_def_effects.is_empty() {
+        println!();
+        println!("Potential effects from macro definitions:");
+        println!("{}", EffectInstance::csv_header());
+        for effect in &macro_def_effects {
+            println!("{}", effect.to_csv());
+        }
+    }
+
     if !args.suppress_total {
         println!();
         println!("{}", CrateStats::metadata_csv_header());
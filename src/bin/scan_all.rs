@@ -12,7 +12,7 @@ use cargo_scan::util;
 
 use clap::Parser;
 use log::{debug, error, info, warn};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
@@ -68,6 +68,10 @@ struct Args {
     // Don't collect raw list of effects
     #[clap(long, default_value_t = false)]
     skip_raw: bool,
+
+    /// Skip source files larger than the given number of bytes
+    #[clap(long, value_name = "BYTES")]
+    max_file_size: Option<u64>,
 }
 
 /*
@@ -78,6 +82,7 @@ fn crate_stats(
     download_loc: PathBuf,
     test_run: bool,
     quick_mode: bool,
+    max_file_size: Option<u64>,
 ) -> CrateStats {
     info!("Getting stats for: {}", crt);
     let output_dir = download_loc.join(Path::new(crt));
@@ -97,7 +102,12 @@ fn crate_stats(
 
     debug!("Downloaded");
 
-    let stats = scan_stats::get_crate_stats_default(output_dir, quick_mode);
+    let stats = scan_stats::get_crate_stats_default_with_sinks(
+        output_dir,
+        HashSet::new(),
+        quick_mode,
+        max_file_size,
+    );
 
     // dbg!(&stats);
     info!("Done scanning: {}", crt);
@@ -261,7 +271,13 @@ fn main() {
             let crt = crt.clone();
             let download_loc = download_loc.to_owned();
             pool.execute(move || {
-                let res = crate_stats(&crt, download_loc, args.test_run, args.quick_mode);
+                let res = crate_stats(
+                    &crt,
+                    download_loc,
+                    args.test_run,
+                    args.quick_mode,
+                    args.max_file_size,
+                );
                 if let Err(e) = tx_inner.send((crt, res)) {
                     error!("Error sending result: {:?}", e);
                 }
@@ -17,6 +17,7 @@ use cargo_scan::{
     auditing::review::review_audit,
 };
 use clap::{Parser, ValueEnum};
+use url::Url;
 
 #[derive(Parser, Debug, Clone)]
 struct Args {
@@ -41,6 +42,12 @@ struct Args {
     #[clap(short = 'v', long)]
     pub download_version: Option<String>,
 
+    /// Registry mirror to download the crate from instead of crates.io: a
+    /// base URL serving `<name>/<version>/download`, or a `file://` URL of a
+    /// directory of `<name>-<version>.crate` files
+    #[clap(long, requires = "download_root_crate")]
+    pub registry: Option<Url>,
+
     /// The types of Effects the audit should track. Defaults to all unsafe
     /// behavior.
     #[clap(long, value_parser, num_args = 1.., default_values_t = [
@@ -49,10 +56,18 @@ struct Args {
         EffectType::UnsafeCall,
         EffectType::RawPointer,
         EffectType::UnionField,
+        EffectType::PackedFieldAccess,
         EffectType::StaticMut,
         EffectType::StaticExt,
         EffectType::FnPtrCreation,
         EffectType::ClosureCreation,
+        EffectType::Transmute,
+        EffectType::RawAlloc,
+        EffectType::CStrPtr,
+        EffectType::RawThread,
+        EffectType::UntrustedInput,
+        EffectType::PtrOffset,
+        EffectType::FFIExport,
     ])]
     effect_types: Vec<EffectType>,
 }
@@ -97,6 +112,7 @@ fn main() -> Result<()> {
             crate_name,
             crate_version,
             &args.crate_path,
+            args.registry.as_ref(),
         )?;
 
         // We have now downloaded the crate into a subfolder of the
@@ -0,0 +1,98 @@
+//! Per-crate capability summaries.
+//!
+//! Summarizes which effect types are observed in each crate, and exports the
+//! summary in a shape that can be attached to a CycloneDX SBOM component as a
+//! list of properties.
+
+use crate::audit_chain::AuditChain;
+use crate::effect::{EffectInstance, EffectType};
+use crate::util::CrateId;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Property name used for each observed effect type
+pub const CAPABILITY_PROPERTY: &str = "cargo-scan:effect-type";
+
+/// The set of effect types observed in a crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateCapabilities {
+    pub crate_id: CrateId,
+    pub effect_types: BTreeSet<EffectType>,
+}
+
+impl CrateCapabilities {
+    pub fn from_effects<'a, I>(crate_id: CrateId, effects: I) -> Self
+    where
+        I: IntoIterator<Item = &'a EffectInstance>,
+    {
+        let effect_types = effects.into_iter().map(|e| e.eff_type().into()).collect();
+        Self { crate_id, effect_types }
+    }
+
+    pub fn to_cyclonedx(&self) -> CycloneDxComponent {
+        let name = self.crate_id.crate_name.clone();
+        let version = self.crate_id.version.to_string();
+        let purl = format!("pkg:cargo/{}@{}", name, version);
+        let properties = self
+            .effect_types
+            .iter()
+            .map(|t| CycloneDxProperty {
+                name: CAPABILITY_PROPERTY.to_string(),
+                value: t.to_string(),
+            })
+            .collect();
+
+        CycloneDxComponent {
+            component_type: "library".to_string(),
+            bom_ref: purl.clone(),
+            name,
+            version,
+            purl,
+            properties,
+        }
+    }
+}
+
+/// A CycloneDX component, restricted to the fields needed to identify the
+/// crate and attach its capabilities
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycloneDxComponent {
+    #[serde(rename = "type")]
+    pub component_type: String,
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    pub name: String,
+    pub version: String,
+    pub purl: String,
+    pub properties: Vec<CycloneDxProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CycloneDxProperty {
+    pub name: String,
+    pub value: String,
+}
+
+/// Collect the capabilities of every crate in the audit chain, i.e. every
+/// package in the root crate's lockfile.
+pub fn chain_capabilities(chain: &mut AuditChain) -> Result<Vec<CrateCapabilities>> {
+    let mut crate_ids = chain.all_crates().into_iter().cloned().collect::<Vec<_>>();
+    crate_ids.sort_by(|a, b| {
+        a.crate_name.cmp(&b.crate_name).then_with(|| a.version.cmp(&b.version))
+    });
+
+    let mut res = Vec::new();
+    for crate_id in crate_ids {
+        let audit_file = chain.read_audit_file(&crate_id)?.ok_or_else(|| {
+            anyhow!("Couldn't find audit for crate {} in chain", crate_id)
+        })?;
+        res.push(CrateCapabilities::from_effects(
+            crate_id,
+            audit_file.audit_trees.keys(),
+        ));
+    }
+
+    Ok(res)
+}
@@ -1,4 +1,4 @@
-use std::fs::{create_dir_all, remove_file, write, File};
+use std::fs::{create_dir_all, read, remove_file, write, File};
 use std::path::PathBuf;
 use std::process::Command;
 
@@ -9,6 +9,7 @@ use flate2::read::GzDecoder;
 use log::info;
 use regex::Regex;
 use tar::Archive;
+use url::Url;
 
 // Regexes to match crate names and versions
 const CRATE_NAME_REGEX: &str = r"[a-zA-Z0-9_-]+";
@@ -21,12 +22,38 @@ fn get_crates_io_url(package_name: &str, package_version: &str) -> String {
     )
 }
 
-fn download_crate(
-    url: &str,
+/// Get the URL to download the crate from: crates.io, or the given registry
+/// mirror. A mirror serves crates at `<registry>/<name>/<version>/download`,
+/// like the crates.io API. A `file://` registry is a local directory of
+/// `<name>-<version>.crate` files instead, e.g. a vendored crate cache.
+fn get_registry_url(
+    registry: Option<&Url>,
     package_name: &str,
     package_version: &str,
-    download_dir: &str,
-) -> Result<PathBuf> {
+) -> String {
+    match registry {
+        None => get_crates_io_url(package_name, package_version),
+        Some(registry) => {
+            let base = registry.as_str().trim_end_matches('/');
+            if registry.scheme() == "file" {
+                format!("{}/{}-{}.crate", base, package_name, package_version)
+            } else {
+                format!("{}/{}/{}/download", base, package_name, package_version)
+            }
+        }
+    }
+}
+
+/// Get the contents of the crate tarball at the URL, reading it from disk
+/// for a `file://` URL
+fn fetch_crate(url: &str) -> Result<Vec<u8>> {
+    let parsed = Url::parse(url)?;
+    if parsed.scheme() == "file" {
+        let path =
+            parsed.to_file_path().map_err(|_| anyhow!("Invalid file URL: {}", url))?;
+        return Ok(read(path)?);
+    }
+
     let mut dst = Vec::new();
     let mut easy = Easy::new();
     easy.follow_location(true)?;
@@ -41,6 +68,17 @@ fn download_crate(
         transfer.perform()?;
     }
 
+    Ok(dst)
+}
+
+fn download_crate(
+    url: &str,
+    package_name: &str,
+    package_version: &str,
+    download_dir: &str,
+) -> Result<PathBuf> {
+    let dst = fetch_crate(url)?;
+
     let package_dir_name = format!("{}-{}", package_name, package_version);
     let tarball_name = format!("{}.tar.gz", package_dir_name);
     let mut download_dir = PathBuf::from(download_dir);
@@ -82,13 +120,15 @@ fn download_crate(
     Ok(download_dir)
 }
 
-/// Downloads the crate from the package name and version
+/// Downloads the crate from the package name and version, from crates.io or
+/// the given registry mirror
 pub fn download_crate_from_info(
     package_name: &str,
     package_version: &str,
     download_dir: &str,
+    registry: Option<&Url>,
 ) -> Result<PathBuf> {
-    let url = get_crates_io_url(package_name, package_version);
+    let url = get_registry_url(registry, package_name, package_version);
     download_crate(&url, package_name, package_version, download_dir)
 }
 
@@ -132,7 +172,8 @@ pub fn download_latest_crate_version(
     download_dir: &str,
 ) -> Result<PathBuf> {
     let latest_version = get_latest_version(package_name)?;
-    let result = download_crate_from_info(package_name, &latest_version, download_dir)?;
+    let result =
+        download_crate_from_info(package_name, &latest_version, download_dir, None)?;
     let _output = Command::new("mv")
         .arg(format!("{}/{}-{}", download_dir, package_name, latest_version))
         .arg(format!("{}/{}", download_dir, package_name))
@@ -140,23 +181,28 @@ pub fn download_latest_crate_version(
     Ok(result)
 }
 
-/// Downloads the crate from the `cargo_lock::Package`
+/// Downloads the crate from the `cargo_lock::Package`. Crates from crates.io
+/// are downloaded from the given registry mirror instead, if any.
 pub fn download_crate_from_package(
     package: &Package,
     download_dir: &str,
+    registry: Option<&Url>,
 ) -> Result<PathBuf> {
+    let crates_io_url = || {
+        get_registry_url(registry, package.name.as_str(), &package.version.to_string())
+    };
     let url = match &package.source {
         // TODO: This is a bit of a hack to handle crates.io urls. We should
         //       handle non crates.io urls as well.
         Some(source) => {
             let source_str = source.url().as_str().to_string();
             if source_str == "https://github.com/rust-lang/crates.io-index" {
-                get_crates_io_url(package.name.as_str(), &package.version.to_string())
+                crates_io_url()
             } else {
                 source_str
             }
         }
-        None => get_crates_io_url(package.name.as_str(), &package.version.to_string()),
+        None => crates_io_url(),
     };
 
     download_crate(
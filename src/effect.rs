@@ -7,13 +7,15 @@
 //!     zero or more effects (such as an unsafe block).
 
 use super::ident::{CanonicalPath, IdentPath};
-use super::sink::Sink;
+use super::sink::{Sink, SinkCategory};
 use super::util::csv;
 
+use clap::ValueEnum;
 use log::debug;
 use parse_display::{Display, FromStr};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::path::{Path as FilePath, PathBuf as FilePathBuf};
 use syn;
@@ -115,6 +117,15 @@ impl SrcLoc {
     pub fn filepath_string(&self) -> String {
         self.dir.join(&self.file).to_string_lossy().to_string()
     }
+
+    /// Move the location from under `old_base` to under `new_base`, e.g.
+    /// when the crate was scanned at a different path. Locations outside of
+    /// `old_base` are left as-is.
+    pub fn rebase(&mut self, old_base: &FilePath, new_base: &FilePath) {
+        if let Ok(rel) = self.dir.strip_prefix(old_base) {
+            self.dir = new_base.join(rel);
+        }
+    }
 }
 
 impl fmt::Display for SrcLoc {
@@ -152,6 +163,9 @@ pub enum Effect {
     RawPointer(CanonicalPath),
     /// Reading a union field
     UnionField(CanonicalPath),
+    /// Taking a reference to a field of a `#[repr(packed)]` struct that needs
+    /// more alignment than the struct's packing, so it may be unaligned
+    PackedFieldAccess(CanonicalPath),
     /// Accessing a global mutable variable
     StaticMut(CanonicalPath),
     /// Accessing an external mutable variable
@@ -166,6 +180,26 @@ pub enum Effect {
     RawPtrCast,
     /// Declaration of a foreign function
     FFIDecl(CanonicalPath),
+    /// Call to `mem::transmute`
+    Transmute(CanonicalPath),
+    /// Manual memory allocation, e.g. `libc::malloc` or `std::alloc::alloc`
+    RawAlloc(CanonicalPath),
+    /// Getting a raw pointer from a C string with `as_ptr`.
+    /// `on_temporary` is set if the C string is a temporary value, in which
+    /// case it is dropped at the end of the statement and the pointer dangles.
+    CStrPtr { callee: CanonicalPath, on_temporary: bool },
+    /// Low-level thread management through libc, e.g. `libc::pthread_create`
+    RawThread(CanonicalPath),
+    /// Reading input from outside the program, e.g. command-line arguments,
+    /// environment variables, or stdin. Reported in addition to the sink call
+    /// if the callee also matches a sink.
+    UntrustedInput(CanonicalPath),
+    /// Raw pointer arithmetic, e.g. `p.add(1)` or `p.offset(-1)`. Also
+    /// reported for the safe `wrapping_offset`, which isn't Rust unsafe.
+    PtrOffset(CanonicalPath),
+    /// Definition of a function callable from foreign code, e.g.
+    /// `#[no_mangle] pub extern "C" fn`
+    FFIExport(CanonicalPath),
 }
 impl Effect {
     fn sink_pattern(&self) -> Option<&Sink> {
@@ -177,7 +211,18 @@ impl Effect {
 
     /// Return true if the type of unsafety is something that Rust considers unsafe.
     fn is_rust_unsafe(&self) -> bool {
-        !matches!(self, Self::SinkCall(_) | Self::FnPtrCreation | Self::ClosureCreation)
+        match self {
+            Self::SinkCall(_)
+            | Self::FnPtrCreation
+            | Self::ClosureCreation
+            | Self::CStrPtr { .. }
+            | Self::UntrustedInput(_)
+            | Self::PackedFieldAccess(_)
+            | Self::FFIExport(_) => false,
+            // Only `offset`, `add`, and `sub` are unsafe to call
+            Self::PtrOffset(callee) => !callee.as_str().ends_with("wrapping_offset"),
+            _ => true,
+        }
     }
 
     fn simple_str(&self) -> &str {
@@ -187,12 +232,20 @@ impl Effect {
             Self::UnsafeCall(_) => "[UnsafeCall]",
             Self::RawPointer(_) => "[PtrDeref]",
             Self::UnionField(_) => "[UnionField]",
+            Self::PackedFieldAccess(_) => "[PackedFieldAccess]",
             Self::StaticMut(_) => "[StaticMutVar]",
             Self::StaticExt(_) => "[StaticExtVar]",
             Self::FnPtrCreation => "[FnPtrCreation]",
             Self::ClosureCreation => "[ClosureCreation]",
             Self::RawPtrCast => "[RawPtrCast]",
             Self::FFIDecl(_) => "[FFI Declaration]",
+            Self::Transmute(_) => "[Transmute]",
+            Self::RawAlloc(_) => "[RawAlloc]",
+            Self::CStrPtr { .. } => "[CStrPtr]",
+            Self::RawThread(_) => "[RawThread]",
+            Self::UntrustedInput(_) => "[UntrustedInput]",
+            Self::PtrOffset(_) => "[PtrOffset]",
+            Self::FFIExport(_) => "[FFI Export]",
         }
     }
 
@@ -206,38 +259,102 @@ impl Effect {
 }
 
 /// This is a field-less copy of Effect for easy pattern matching and passing
-/// command-line arguments.
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Display, FromStr)]
+/// command-line arguments. On the command line, effect types can be given
+/// in kebab case (e.g. `ffi-call`) or as displayed (e.g. `FFICall`).
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Display,
+    FromStr,
+    ValueEnum,
+)]
 pub enum EffectType {
+    #[value(name = "sink-call", alias = "SinkCall")]
     SinkCall,
+    #[value(name = "ffi-call", alias = "FFICall")]
     FFICall,
+    #[value(name = "unsafe-call", alias = "UnsafeCall")]
     UnsafeCall,
+    #[value(name = "raw-pointer", alias = "RawPointer")]
     RawPointer,
+    #[value(name = "union-field", alias = "UnionField")]
     UnionField,
+    #[value(name = "packed-field-access", alias = "PackedFieldAccess")]
+    PackedFieldAccess,
+    #[value(name = "static-mut", alias = "StaticMut")]
     StaticMut,
+    #[value(name = "static-ext", alias = "StaticExt")]
     StaticExt,
+    #[value(name = "fn-ptr-creation", alias = "FnPtrCreation")]
     FnPtrCreation,
+    #[value(name = "closure-creation", alias = "ClosureCreation")]
     ClosureCreation,
+    #[value(name = "raw-ptr-cast", alias = "RawPtrCast")]
     RawPtrCast,
+    #[value(name = "ffi-decl", alias = "FFIDecl")]
     FFIDecl,
+    #[value(name = "transmute", alias = "Transmute")]
+    Transmute,
+    #[value(name = "raw-alloc", alias = "RawAlloc")]
+    RawAlloc,
+    #[value(name = "c-str-ptr", alias = "CStrPtr")]
+    CStrPtr,
+    #[value(name = "raw-thread", alias = "RawThread")]
+    RawThread,
+    #[value(name = "untrusted-input", alias = "UntrustedInput")]
+    UntrustedInput,
+    #[value(name = "ptr-offset", alias = "PtrOffset")]
+    PtrOffset,
+    #[value(name = "ffi-export", alias = "FFIExport")]
+    FFIExport,
 }
 
-impl EffectType {
-    pub fn matches_effect(types: &[EffectType], e: &Effect) -> bool {
+impl From<&Effect> for EffectType {
+    fn from(e: &Effect) -> Self {
         match e {
-            Effect::SinkCall(_) => types.contains(&EffectType::SinkCall),
-            Effect::FFICall(_) => types.contains(&EffectType::FFICall),
-            Effect::UnsafeCall(_) => types.contains(&EffectType::UnsafeCall),
-            Effect::RawPointer(_) => types.contains(&EffectType::RawPointer),
-            Effect::UnionField(_) => types.contains(&EffectType::UnionField),
-            Effect::StaticMut(_) => types.contains(&EffectType::StaticMut),
-            Effect::StaticExt(_) => types.contains(&EffectType::StaticExt),
-            Effect::FnPtrCreation => types.contains(&EffectType::FnPtrCreation),
-            Effect::ClosureCreation => types.contains(&EffectType::ClosureCreation),
-            Effect::RawPtrCast => types.contains(&EffectType::RawPtrCast),
-            Effect::FFIDecl(_) => types.contains(&EffectType::FFIDecl),
+            Effect::SinkCall(_) => EffectType::SinkCall,
+            Effect::FFICall(_) => EffectType::FFICall,
+            Effect::UnsafeCall(_) => EffectType::UnsafeCall,
+            Effect::RawPointer(_) => EffectType::RawPointer,
+            Effect::UnionField(_) => EffectType::UnionField,
+            Effect::PackedFieldAccess(_) => EffectType::PackedFieldAccess,
+            Effect::StaticMut(_) => EffectType::StaticMut,
+            Effect::StaticExt(_) => EffectType::StaticExt,
+            Effect::FnPtrCreation => EffectType::FnPtrCreation,
+            Effect::ClosureCreation => EffectType::ClosureCreation,
+            Effect::RawPtrCast => EffectType::RawPtrCast,
+            Effect::FFIDecl(_) => EffectType::FFIDecl,
+            Effect::Transmute(_) => EffectType::Transmute,
+            Effect::RawAlloc(_) => EffectType::RawAlloc,
+            Effect::CStrPtr { .. } => EffectType::CStrPtr,
+            Effect::RawThread(_) => EffectType::RawThread,
+            Effect::UntrustedInput(_) => EffectType::UntrustedInput,
+            Effect::PtrOffset(_) => EffectType::PtrOffset,
+            Effect::FFIExport(_) => EffectType::FFIExport,
         }
     }
+}
+
+impl EffectType {
+    pub fn matches_effect(types: &[EffectType], e: &Effect) -> bool {
+        types.contains(&e.into())
+    }
+
+    /// Get the documentation entry for this effect type
+    pub fn doc(&self) -> &'static EffectTypeDoc {
+        EFFECT_TYPE_DOCS
+            .iter()
+            .find(|d| d.effect_type == *self)
+            .expect("every effect type should be documented")
+    }
 
     pub fn unsafe_effects() -> Vec<EffectType> {
         vec![
@@ -246,15 +363,280 @@ impl EffectType {
             EffectType::UnsafeCall,
             EffectType::RawPointer,
             EffectType::UnionField,
+            EffectType::PackedFieldAccess,
             EffectType::StaticMut,
             EffectType::StaticExt,
             EffectType::FnPtrCreation,
             EffectType::ClosureCreation,
             EffectType::FFIDecl,
+            EffectType::Transmute,
+            EffectType::RawAlloc,
+            EffectType::CStrPtr,
+            EffectType::RawThread,
+            EffectType::UntrustedInput,
+            EffectType::PtrOffset,
+            EffectType::FFIExport,
         ]
     }
+
+    /// How risky effects of this type are: operations Rust considers unsafe
+    /// are high, capabilities like sink calls and mutable globals are medium,
+    /// and pointer/closure creation (which only matters if called) is low.
+    pub fn severity(&self) -> Severity {
+        match self {
+            EffectType::SinkCall | EffectType::StaticMut | EffectType::UntrustedInput => {
+                Severity::Medium
+            }
+            EffectType::FnPtrCreation
+            | EffectType::ClosureCreation
+            | EffectType::RawPtrCast => Severity::Low,
+            EffectType::FFICall
+            | EffectType::UnsafeCall
+            | EffectType::RawPointer
+            | EffectType::UnionField
+            | EffectType::PackedFieldAccess
+            | EffectType::StaticExt
+            | EffectType::FFIDecl
+            | EffectType::Transmute
+            | EffectType::RawAlloc
+            | EffectType::CStrPtr
+            | EffectType::RawThread
+            | EffectType::PtrOffset
+            | EffectType::FFIExport => Severity::High,
+        }
+    }
+}
+
+/// Severity of an effect type, for triaging the effects of large crates.
+/// Ordered from least to most severe.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    ValueEnum,
+)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
+/// Documentation for an effect type, used for `--explain` and for rule
+/// descriptions in structured output formats.
+#[derive(Debug, Clone, Copy)]
+pub struct EffectTypeDoc {
+    pub effect_type: EffectType,
+    /// One-line summary of the effect
+    pub summary: &'static str,
+    /// Why the effect is security-relevant
+    pub description: &'static str,
+    /// Example Rust code producing the effect
+    pub example: &'static str,
+}
+
+impl fmt::Display for EffectTypeDoc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}: {}", self.effect_type, self.summary)?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.description)?;
+        writeln!(f)?;
+        writeln!(f, "Example:")?;
+        for line in self.example.lines() {
+            writeln!(f, "    {}", line)?;
+        }
+        Ok(())
+    }
 }
 
+pub const EFFECT_TYPE_DOCS: &[EffectTypeDoc] = &[
+    EffectTypeDoc {
+        effect_type: EffectType::SinkCall,
+        summary: "call to a dangerous standard library or system function",
+        description: "The callee matches a sink pattern, e.g. a function that \
+            accesses the file system, the network, the environment, or spawns \
+            processes. These calls give a crate capabilities beyond pure \
+            computation, so they should be checked against what the crate is \
+            expected to do.",
+        example: "std::fs::remove_file(\"data.txt\")?;",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::FFICall,
+        summary: "call to a foreign (FFI) function",
+        description: "The callee is declared in an `extern` block. Foreign code \
+            is not checked by the Rust compiler and can perform arbitrary \
+            operations, so the call must uphold whatever contract the foreign \
+            function expects.",
+        example: "unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::UnsafeCall,
+        summary: "call to an unsafe function or method",
+        description: "The callee is marked `unsafe`, meaning it has safety \
+            preconditions the compiler cannot check. The caller is responsible \
+            for upholding them, otherwise the call may cause undefined behavior.",
+        example: "unsafe { v.set_len(100) };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::RawPointer,
+        summary: "dereference of a raw pointer",
+        description: "Reading or writing through a raw pointer is only sound if \
+            the pointer is non-null, aligned, and points to valid memory. \
+            Invalid dereferences lead to memory corruption.",
+        example: "let x = unsafe { *ptr };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::UnionField,
+        summary: "read of a union field",
+        description: "Reading a union field reinterprets the bytes of the union \
+            as the field's type. If the union was last written through a \
+            different field, the read may produce an invalid value and cause \
+            undefined behavior.",
+        example: "let f = unsafe { u.float_field };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::PackedFieldAccess,
+        summary: "reference to a field of a packed struct",
+        description: "Fields of a `#[repr(packed)]` struct may be unaligned, \
+            and creating a reference to an unaligned field is undefined \
+            behavior. Only fields whose type needs more alignment than the \
+            packing are reported. Newer compilers reject these references, \
+            but no unsafe block was needed for them. Use `ptr::addr_of!` or \
+            `&raw const` and unaligned reads instead.",
+        example: "let r = &packed.len;",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::StaticMut,
+        summary: "access to a mutable static variable",
+        description: "Mutable statics are global state shared across threads \
+            without synchronization. Any access can race with another access \
+            and cause undefined behavior.",
+        example: "unsafe { COUNTER += 1 };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::StaticExt,
+        summary: "access to an external (FFI) static variable",
+        description: "Static variables declared in an `extern` block are owned \
+            by foreign code. The Rust compiler cannot check their type or how \
+            foreign code mutates them.",
+        example: "let e = unsafe { errno };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::FnPtrCreation,
+        summary: "creation of a function pointer",
+        description: "A function pointer may be called later from anywhere, \
+            which hides the effects of the function at the call site. The \
+            function that is pointed to should always be safe to call.",
+        example: "let f: fn() = do_io;",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::ClosureCreation,
+        summary: "creation of a closure that has effects",
+        description: "A closure whose body contains effects may be passed \
+            around and called later, which hides the effects from the code \
+            that eventually calls it. The closure should always be safe to call.",
+        example: "let f = || std::env::var(\"HOME\");",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::RawPtrCast,
+        summary: "cast to a raw pointer",
+        description: "Casting to a raw pointer is safe on its own, but is often \
+            the first step towards an unsafe dereference. This effect is not \
+            tracked by default.",
+        example: "let p = &x as *const i32;",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::FFIDecl,
+        summary: "declaration of a foreign function",
+        description: "An `extern` block declares the signature of a foreign \
+            function. If the declared signature does not match the foreign \
+            definition, every call to it is unsound.",
+        example: "extern \"C\" { fn abs(x: i32) -> i32; }",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::Transmute,
+        summary: "call to mem::transmute",
+        description: "Transmute reinterprets the bits of a value as another \
+            type. It can create invalid values, extend lifetimes, and break \
+            invariants of the target type, which caller-checking alone does \
+            not capture.",
+        example: "let f: f32 = unsafe { std::mem::transmute(1u32) };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::RawAlloc,
+        summary: "manual memory allocation or deallocation",
+        description: "Calls like `libc::malloc`/`free` or `std::alloc::alloc` \
+            bypass Rust's ownership model. Memory must be freed exactly once, \
+            with the matching allocator and layout, or the program may leak, \
+            double free, or use freed memory.",
+        example: "unsafe { libc::free(libc::malloc(16)) };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::CStrPtr,
+        summary: "raw pointer taken from a C string",
+        description: "`CString::as_ptr` and `CStr::as_ptr` return a pointer \
+            that is only valid while the owning string is alive. If the string \
+            is a temporary, it is dropped at the end of the statement and the \
+            pointer passed to foreign code dangles.",
+        example: "let p = CString::new(\"hello\").unwrap().as_ptr();",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::RawThread,
+        summary: "low-level thread management through libc",
+        description: "Creating or joining threads with pthreads bypasses \
+            `std::thread`, so nothing checks that the thread's closure and \
+            arguments are `Send` or outlive the thread, and the stack size and \
+            other attributes are set by hand.",
+        example: "unsafe { libc::pthread_create(&mut t, ptr::null(), run, arg) };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::UntrustedInput,
+        summary: "read of input from outside the program",
+        description: "Command-line arguments, environment variables, and stdin \
+            are controlled by whoever runs the program. They are where untrusted \
+            input enters a crate, so code that uses them should validate it \
+            before passing it on to other effects.",
+        example: "let path = std::env::args().nth(1).unwrap();",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::PtrOffset,
+        summary: "raw pointer arithmetic",
+        description: "Offsetting a raw pointer with `offset`, `add`, or `sub` \
+            is undefined behavior if the result is out of bounds of the \
+            allocation it points into, even if it is never dereferenced, so \
+            these need an unsafe block. `wrapping_offset` is safe to call and \
+            isn't counted as Rust unsafe, but its result is usually \
+            dereferenced later.",
+        example: "let second = unsafe { *p.add(1) };",
+    },
+    EffectTypeDoc {
+        effect_type: EffectType::FFIExport,
+        summary: "function exported to foreign code",
+        description: "An `extern` function that is public or has a fixed \
+            symbol name (`#[no_mangle]` or `#[export_name]`) can be called \
+            from C or other languages, which don't check Rust's types or \
+            borrowing rules. Its arguments can be anything the caller passes, \
+            and a `#[no_mangle]` name can clash with another symbol.",
+        example: "#[no_mangle] pub extern \"C\" fn add(a: i32, b: i32) -> i32 { a + b }",
+    },
+];
+
 // Default effect types that we care about
 // Excludes: RawPtrCast as it is not unsafe
 pub const DEFAULT_EFFECT_TYPES: &[EffectType] = &[
@@ -263,13 +645,86 @@ pub const DEFAULT_EFFECT_TYPES: &[EffectType] = &[
     EffectType::UnsafeCall,
     EffectType::RawPointer,
     EffectType::UnionField,
+    EffectType::PackedFieldAccess,
     EffectType::StaticMut,
     EffectType::StaticExt,
     EffectType::FnPtrCreation,
     EffectType::ClosureCreation,
     EffectType::FFIDecl,
+    EffectType::Transmute,
+    EffectType::RawAlloc,
+    EffectType::CStrPtr,
+    EffectType::RawThread,
+    EffectType::UntrustedInput,
+    EffectType::PtrOffset,
+    EffectType::FFIExport,
+];
+
+/// Manual allocation functions in libc
+const LIBC_ALLOC_FNS: &[&str] = &["malloc", "calloc", "realloc", "free"];
+
+/// Manual allocation functions in the standard library
+const STD_ALLOC_FNS: &[&str] = &["alloc", "alloc_zeroed", "realloc", "dealloc"];
+
+/// Thread creation and management functions in libc
+const LIBC_THREAD_FNS: &[&str] =
+    &["pthread_create", "pthread_join", "pthread_detach", "pthread_attr_setstacksize"];
+
+/// Functions that read input from outside the program. `std::io::stdin` is
+/// defined in `std::io::stdio`, so both paths are listed.
+const UNTRUSTED_INPUT_FNS: &[&str] = &[
+    "std::env::args",
+    "std::env::args_os",
+    "std::env::var",
+    "std::env::var_os",
+    "std::env::vars",
+    "std::env::vars_os",
+    "std::io::stdin",
+    "std::io::stdio::stdin",
 ];
 
+/// Functions that run the program given as their first argument
+const COMMAND_FNS: &[&str] = &["std::process::Command::new"];
+
+/// Return true if the callee is a manual allocation function. libc functions
+/// resolve to platform-specific modules, so only the crate and function name
+/// are checked.
+fn is_raw_alloc(callee: &CanonicalPath) -> bool {
+    let path = callee.as_path();
+    let (Some(krate), Some(name)) = (path.first_ident(), path.last_ident()) else {
+        return false;
+    };
+    match krate.as_str() {
+        "libc" => LIBC_ALLOC_FNS.contains(&name.as_str()),
+        "std" | "alloc" => {
+            let mut module = path.clone();
+            module.pop_ident();
+            module.as_str() == format!("{}::alloc", krate)
+                && STD_ALLOC_FNS.contains(&name.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// Return true if the callee is a libc thread management function
+fn is_raw_thread(callee: &CanonicalPath) -> bool {
+    let path = callee.as_path();
+    let (Some(krate), Some(name)) = (path.first_ident(), path.last_ident()) else {
+        return false;
+    };
+    krate.as_str() == "libc" && LIBC_THREAD_FNS.contains(&name.as_str())
+}
+
+/// Return true if the callee runs the program given as its first argument
+pub fn runs_program(callee: &CanonicalPath) -> bool {
+    COMMAND_FNS.contains(&callee.as_str())
+}
+
+/// Return true if the callee reads input from outside the program
+fn is_untrusted_input(callee: &CanonicalPath) -> bool {
+    UNTRUSTED_INPUT_FNS.contains(&callee.as_str())
+}
+
 /// Type representing an Effect instance, with complete context.
 /// This includes a field for which Effect it is an instance of.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -286,12 +741,53 @@ pub struct EffectInstance {
     /// EffectInstance type
     /// If Sink, this includes the effect pattern -- prefix of callee (effect), e.g. libc.
     eff_type: Effect,
+
+    /// Whether the effect is in test-only code, e.g. under `#[test]` or
+    /// `#[cfg(test)]`
+    #[serde(default)]
+    in_test: bool,
+
+    /// Whether the effect is in the crate's build script (`build.rs`)
+    #[serde(default)]
+    from_build_script: bool,
+
+    /// Signature of the callee function, if it has been resolved
+    /// (see `scanner::resolve_callee_signatures`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    callee_sig: Option<FnSignature>,
+
+    /// Stability of the callee function, if it has been resolved
+    /// (see `scanner::resolve_callee_stability`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    callee_stability: Option<ApiStability>,
+
+    /// For calls that run a program (e.g. `Command::new`), whether the
+    /// program is given as a string literal or a dynamic expression
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    program_arg: Option<ProgramArg>,
+
+    /// For closure creations, whether the closure (heuristically) escapes
+    /// the function it's created in, e.g. by being returned or stored in a
+    /// field, rather than being called locally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    closure_escapes: Option<bool>,
+
+    /// Whether the effect was found directly in the source or in the
+    /// expansion of a macro
+    #[serde(default)]
+    found_via: EffectProvenance,
+
+    /// The kind of function the effect is in
+    #[serde(default)]
+    fn_context: FnContext,
 }
 
 impl EffectInstance {
-    /// Returns a new EffectInstance if the call matches a Sink, is an ffi call,
-    /// or is an unsafe call. Regular calls are handled by the explicit call
-    /// graph structure.
+    /// Returns the EffectInstances of the call if it matches a Sink, is an ffi
+    /// call, or is an unsafe call, and none otherwise. Regular calls are
+    /// handled by the explicit call graph structure. A sink call that reads
+    /// untrusted input is an UntrustedInput effect as well, and a call to an
+    /// unsafe function that matches a sink is an UnsafeCall as well.
     pub fn new_call<S>(
         filepath: &FilePath,
         caller: CanonicalPath,
@@ -300,13 +796,17 @@ impl EffectInstance {
         is_unsafe: bool,
         ffi: Option<CanonicalPath>,
         sinks: &HashSet<IdentPath>,
-    ) -> Option<Self>
+    ) -> Vec<Self>
     where
         S: Spanned,
     {
         // Code to classify an effect based on call site information
         let call_loc = SrcLoc::from_span(filepath, callsite);
-        let eff_type = if let Some(ffi) = ffi {
+        let eff_types = if is_raw_alloc(&callee) {
+            vec![Effect::RawAlloc(callee.clone())]
+        } else if is_raw_thread(&callee) {
+            vec![Effect::RawThread(callee.clone())]
+        } else if let Some(ffi) = ffi {
             if !is_unsafe {
                 // This case can occur in certain contexts, e.g. with
                 // the wasm_bindgen attribute
@@ -326,16 +826,41 @@ impl EffectInstance {
                     callee, call_loc, ffi
                 );
             }
-            Some(Effect::FFICall(ffi))
+            vec![Effect::FFICall(ffi)]
         } else if let Some(pat) = Sink::new_match(&callee, sinks) {
             // callee.remove_src_loc();
-            Some(Effect::SinkCall(pat))
+            let mut eff_types = vec![Effect::SinkCall(pat)];
+            if is_untrusted_input(&callee) {
+                eff_types.push(Effect::UntrustedInput(callee.clone()));
+            }
+            if is_unsafe {
+                eff_types.push(Effect::UnsafeCall(callee.clone()));
+            }
+            eff_types
+        } else if is_untrusted_input(&callee) {
+            vec![Effect::UntrustedInput(callee.clone())]
         } else if is_unsafe {
-            Some(Effect::UnsafeCall(callee.clone()))
+            vec![Effect::UnsafeCall(callee.clone())]
         } else {
-            None
+            vec![]
         };
-        Some(Self { caller, call_loc, callee, eff_type: eff_type? })
+        eff_types
+            .into_iter()
+            .map(|eff_type| Self {
+                caller: caller.clone(),
+                call_loc: call_loc.clone(),
+                callee: callee.clone(),
+                eff_type,
+                in_test: false,
+                from_build_script: false,
+                callee_sig: None,
+                callee_stability: None,
+                program_arg: None,
+                closure_escapes: None,
+                found_via: EffectProvenance::Direct,
+                fn_context: FnContext::Free,
+            })
+            .collect()
     }
 
     pub fn new_effect<S>(
@@ -349,7 +874,20 @@ impl EffectInstance {
         S: Spanned,
     {
         let call_loc = SrcLoc::from_span(filepath, eff_site);
-        Self { caller, call_loc, callee, eff_type }
+        Self {
+            caller,
+            call_loc,
+            callee,
+            eff_type,
+            in_test: false,
+            from_build_script: false,
+            callee_sig: None,
+            callee_stability: None,
+            program_arg: None,
+            closure_escapes: None,
+            found_via: EffectProvenance::Direct,
+            fn_context: FnContext::Free,
+        }
     }
 
     pub fn caller(&self) -> &CanonicalPath {
@@ -391,10 +929,60 @@ impl EffectInstance {
         &self.eff_type
     }
 
+    /// A stable identifier for the effect, as a hex SHA-256 digest of its
+    /// caller, callee, effect type and `nth`, its position among the effects
+    /// with the same caller, callee and type in source order. Unlike the
+    /// location, it doesn't change when code around the effect moves.
+    /// See `EffectInstance::fingerprints` to number a set of effects.
+    pub fn fingerprint(&self, nth: usize) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            self.caller_path(),
+            self.callee_path(),
+            EffectType::from(&self.eff_type).to_string().as_str(),
+            nth.to_string().as_str(),
+        ] {
+            hasher.update(part);
+            hasher.update([0]);
+        }
+        hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Get the fingerprint of each of the effects, see
+    /// `EffectInstance::fingerprint`
+    pub fn fingerprints<'a, I>(effects: I) -> HashMap<String, &'a EffectInstance>
+    where
+        I: IntoIterator<Item = &'a EffectInstance>,
+    {
+        let mut effects = effects.into_iter().collect::<Vec<_>>();
+        effects.sort_by_key(|e| {
+            let loc = e.call_loc();
+            (loc.filepath_string(), loc.start_line(), loc.start_col())
+        });
+
+        let mut counts: HashMap<_, usize> = HashMap::new();
+        effects
+            .into_iter()
+            .map(|e| {
+                let key =
+                    (e.caller_path(), e.callee_path(), EffectType::from(e.eff_type()));
+                let nth = counts.entry(key).or_default();
+                let fingerprint = e.fingerprint(*nth);
+                *nth += 1;
+                (fingerprint, e)
+            })
+            .collect()
+    }
+
     pub fn pattern(&self) -> Option<&Sink> {
         self.eff_type.sink_pattern()
     }
 
+    /// Get the capability category of the sink, for SinkCall effects
+    pub fn sink_category(&self) -> Option<SinkCategory> {
+        self.pattern().map(Sink::category)
+    }
+
     /// Return true if the type of unsafety is something that Rust considers unsafe.
     pub fn is_rust_unsafe(&self) -> bool {
         self.eff_type.is_rust_unsafe()
@@ -403,6 +991,279 @@ impl EffectInstance {
     pub fn call_loc(&self) -> &SrcLoc {
         &self.call_loc
     }
+
+    /// Return true if the effect is in test-only code
+    pub fn in_test(&self) -> bool {
+        self.in_test
+    }
+
+    pub fn set_in_test(&mut self, in_test: bool) {
+        self.in_test = in_test;
+    }
+
+    /// Return true if the effect is in the crate's build script
+    pub fn from_build_script(&self) -> bool {
+        self.from_build_script
+    }
+
+    pub fn set_from_build_script(&mut self, from_build_script: bool) {
+        self.from_build_script = from_build_script;
+    }
+
+    /// Get the signature of the callee function, if it has been resolved
+    pub fn callee_sig(&self) -> Option<&FnSignature> {
+        self.callee_sig.as_ref()
+    }
+
+    /// See `SrcLoc::rebase`
+    pub fn rebase(&mut self, old_base: &FilePath, new_base: &FilePath) {
+        self.call_loc.rebase(old_base, new_base);
+    }
+
+    pub fn set_callee_sig(&mut self, sig: FnSignature) {
+        self.callee_sig = Some(sig);
+    }
+
+    /// Get the stability of the callee function, if it has been resolved
+    pub fn callee_stability(&self) -> Option<&ApiStability> {
+        self.callee_stability.as_ref()
+    }
+
+    pub fn set_callee_stability(&mut self, stability: ApiStability) {
+        self.callee_stability = Some(stability);
+    }
+
+    /// Get whether the program run by the callee is a literal or dynamic,
+    /// if the callee runs a program
+    pub fn program_arg(&self) -> Option<ProgramArg> {
+        self.program_arg
+    }
+
+    pub fn set_program_arg(&mut self, program_arg: ProgramArg) {
+        self.program_arg = Some(program_arg);
+    }
+
+    /// Get whether the created closure escapes its function, if the effect
+    /// is a closure creation
+    pub fn closure_escapes(&self) -> Option<bool> {
+        self.closure_escapes
+    }
+
+    pub fn set_closure_escapes(&mut self, escapes: bool) {
+        self.closure_escapes = Some(escapes);
+    }
+
+    /// Get whether the effect was found directly or via macro expansion
+    pub fn found_via(&self) -> EffectProvenance {
+        self.found_via
+    }
+
+    pub fn set_found_via(&mut self, found_via: EffectProvenance) {
+        self.found_via = found_via;
+    }
+
+    /// Get the kind of function the effect is in, e.g. a trait impl
+    pub fn fn_context(&self) -> &FnContext {
+        &self.fn_context
+    }
+
+    pub fn set_fn_context(&mut self, fn_context: FnContext) {
+        self.fn_context = fn_context;
+    }
+}
+
+/// Signature of a function, with the parameter and return types as written
+/// in its source
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct FnSignature {
+    pub params: Vec<String>,
+    pub ret: Option<String>,
+}
+
+impl fmt::Display for FnSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({})", self.params.join(", "))?;
+        if let Some(ret) = &self.ret {
+            write!(f, " -> {}", ret)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a function is a stable API, or an unstable one that can only be
+/// used on nightly behind a feature gate, as marked by the `#[stable]` and
+/// `#[unstable]` attributes in the standard library
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ApiStability {
+    Stable,
+    Unstable { feature: String },
+}
+
+impl fmt::Display for ApiStability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiStability::Stable => write!(f, "stable"),
+            ApiStability::Unstable { feature } => write!(f, "unstable ({})", feature),
+        }
+    }
+}
+
+/// How the program run by e.g. `Command::new` is given: a string literal like
+/// `"ls"`, or any other expression, which could come from untrusted input
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ProgramArg {
+    Literal,
+    Dynamic,
+}
+
+impl fmt::Display for ProgramArg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgramArg::Literal => write!(f, "literal"),
+            ProgramArg::Dynamic => write!(f, "dynamic"),
+        }
+    }
+}
+
+/// Where an effect was found: directly in the crate's source, in the
+/// arguments of a macro like `println!` that are expanded in place, or in the
+/// code of a `macro_rules!` definition, where it's only a potential effect of
+/// each invocation
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default, ValueEnum,
+)]
+pub enum EffectProvenance {
+    #[default]
+    Direct,
+    #[value(name = "macro-expanded")]
+    MacroExpansion,
+    #[value(name = "macro-definition")]
+    MacroDefinition,
+}
+
+impl fmt::Display for EffectProvenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EffectProvenance::Direct => write!(f, "direct"),
+            EffectProvenance::MacroExpansion => write!(f, "macro-expanded"),
+            EffectProvenance::MacroDefinition => write!(f, "macro-definition"),
+        }
+    }
+}
+
+/// Filters on which effects to output: only sink calls in a capability
+/// category, only effects found in a certain way, and only effects of at
+/// least a severity. Filters that are None keep every effect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EffectFilter {
+    pub only_category: Option<SinkCategory>,
+    pub only: Option<EffectProvenance>,
+    pub min_severity: Option<Severity>,
+}
+
+impl EffectFilter {
+    pub fn matches(&self, effect: &EffectInstance) -> bool {
+        self.only_category.map_or(true, |c| effect.sink_category() == Some(c))
+            && self.only.map_or(true, |p| effect.found_via() == p)
+            && self
+                .min_severity
+                .map_or(true, |s| EffectType::from(effect.eff_type()).severity() >= s)
+    }
+}
+
+/// The kind of function an effect is in: a free function, a method in an
+/// inherent or trait impl, or a default method in a trait definition
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum FnContext {
+    #[default]
+    Free,
+    /// Method of an inherent impl, with the implementing type
+    InherentImpl(CanonicalPath),
+    /// Method of a trait impl, with the trait and the implementing type
+    TraitImpl { trait_: CanonicalPath, ty: CanonicalPath },
+    /// Default method in a trait definition
+    TraitDefault,
+}
+
+impl fmt::Display for FnContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FnContext::Free => write!(f, "free function"),
+            FnContext::InherentImpl(ty) => write!(f, "impl {}", ty),
+            FnContext::TraitImpl { trait_, ty } => {
+                write!(f, "impl {} for {}", trait_, ty)
+            }
+            FnContext::TraitDefault => write!(f, "trait default method"),
+        }
+    }
+}
+
+/// Flat JSON representation of an EffectInstance, used for machine-readable
+/// output. Field names are kept stable so the output can be consumed by other
+/// tools.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EffectRecord {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub caller: String,
+    pub callee: String,
+    pub effect_type: EffectType,
+    /// Sink pattern, only present for SinkCall effects
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pattern: Option<String>,
+    /// Sink category, only present for SinkCall effects
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub category: Option<SinkCategory>,
+    pub in_test: bool,
+    pub from_build_script: bool,
+    /// Callee signature, only present if it has been resolved
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub callee_sig: Option<String>,
+    /// Callee stability, only present if it has been resolved
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub callee_stability: Option<String>,
+    /// Whether the program run is a literal or dynamic, only present for
+    /// calls that run a program
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub program_arg: Option<String>,
+    /// Whether the closure escapes its function, only present for closure
+    /// creations
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub closure_escapes: Option<bool>,
+    #[serde(default)]
+    pub found_via: EffectProvenance,
+    pub file: FilePathBuf,
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
+}
+
+impl From<&EffectInstance> for EffectRecord {
+    fn from(e: &EffectInstance) -> Self {
+        let loc = e.call_loc();
+        Self {
+            crate_name: e.caller().crate_name().to_string(),
+            caller: e.caller_path().to_string(),
+            callee: e.callee_path().to_string(),
+            effect_type: e.eff_type().into(),
+            pattern: e.pattern().map(|p| p.to_string()),
+            category: e.sink_category(),
+            in_test: e.in_test(),
+            from_build_script: e.from_build_script(),
+            callee_sig: e.callee_sig().map(|s| s.to_string()),
+            callee_stability: e.callee_stability().map(|s| s.to_string()),
+            program_arg: e.program_arg().map(|p| p.to_string()),
+            closure_escapes: e.closure_escapes(),
+            found_via: e.found_via(),
+            file: loc.dir().join(loc.file()),
+            start_line: loc.start_line(),
+            start_col: loc.start_col(),
+            end_line: loc.end_line(),
+            end_col: loc.end_col(),
+        }
+    }
 }
 
 /*
@@ -449,6 +1310,36 @@ impl FnDec {
     }
 }
 
+/// An `unsafe impl` of a trait, e.g. `unsafe impl Send for MyType`, which
+/// asserts properties of the implementing type that the compiler can't check
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct UnsafeImpl {
+    pub trait_path: CanonicalPath,
+    pub self_ty: CanonicalPath,
+    pub src_loc: SrcLoc,
+}
+
+impl UnsafeImpl {
+    pub fn new<S>(
+        filepath: &FilePath,
+        impl_span: &S,
+        trait_path: CanonicalPath,
+        self_ty: CanonicalPath,
+    ) -> Self
+    where
+        S: Spanned,
+    {
+        let src_loc = SrcLoc::from_span(filepath, impl_span);
+        Self { trait_path, self_ty, src_loc }
+    }
+}
+
+impl fmt::Display for UnsafeImpl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unsafe impl {} for {}", self.trait_path, self.self_ty)
+    }
+}
+
 /*
     Unit tests
 */
@@ -457,3 +1348,12 @@ impl FnDec {
 fn test_csv_header() {
     assert!(EffectInstance::csv_header().ends_with(SrcLoc::csv_header()));
 }
+
+#[test]
+fn test_effect_type_docs() {
+    let mut all_types = EffectType::unsafe_effects();
+    all_types.push(EffectType::RawPtrCast);
+    for t in all_types {
+        assert_eq!(t.doc().effect_type, t);
+    }
+}
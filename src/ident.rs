@@ -207,7 +207,18 @@ impl IdentPath {
     }
 
     pub fn matches(&self, pattern: &Pattern) -> bool {
-        self.0.starts_with(pattern.as_str())
+        self.matches_glob(pattern.as_str())
+    }
+
+    /// Return true if the path matches a glob pattern such as `libc::*` or
+    /// `std::process::*::output`. The pattern is matched ident by ident
+    /// against the start of the path, where `*` matches any part of a single
+    /// ident. So a trailing `::*` matches anything inside a module, and a
+    /// pattern without wildcards matches the path itself and anything inside
+    /// it (`libc` matches `libc::sysconf`, but not `libc_ex::main`).
+    pub fn matches_glob(&self, pattern: &str) -> bool {
+        let mut idents = self.0.split("::");
+        pattern.split("::").all(|p| idents.next().is_some_and(|id| glob_match(p, id)))
     }
 
     pub fn as_str(&self) -> &str {
@@ -286,6 +297,24 @@ impl CanonicalPath {
         self.0.idents().next().unwrap()
     }
 
+    /// The path without its leading crate name, e.g. `fs::File` for
+    /// `std::fs::File`. Empty if the path is just the crate name.
+    pub fn strip_crate_prefix(&self) -> IdentPath {
+        match self.0 .0.split_once("::") {
+            Some((_, rest)) => IdentPath::new(rest),
+            None => IdentPath::new_empty(),
+        }
+    }
+
+    /// The same path in the crate `name` instead, e.g. to compare paths
+    /// across versions of a crate. Hyphens in `name` are replaced as for the
+    /// crate's own paths.
+    pub fn with_crate(&self, name: &str) -> CanonicalPath {
+        let mut result = CanonicalPath::new(name);
+        result.append_path(&self.strip_crate_prefix());
+        result
+    }
+
     pub fn to_path(self) -> IdentPath {
         self.0
     }
@@ -303,12 +332,17 @@ impl CanonicalPath {
     pub fn matches(&self, pattern: &Pattern) -> bool {
         self.0.matches(pattern)
     }
+
+    pub fn matches_glob(&self, pattern: &str) -> bool {
+        self.0.matches_glob(pattern)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum TypeKind {
     RawPointer,
     UnionFld,
+    PackedFld,
     StaticMut,
     Function,
     #[default]
@@ -322,6 +356,7 @@ impl Display for TypeKind {
         let s = match self {
             TypeKind::RawPointer => "raw pointer",
             TypeKind::UnionFld => "union field",
+            TypeKind::PackedFld => "packed struct field",
             TypeKind::StaticMut => "mutable static",
             TypeKind::Function => "function",
             TypeKind::Plain => "plain",
@@ -353,6 +388,10 @@ impl CanonicalType {
         matches!(self.0, TypeKind::UnionFld)
     }
 
+    pub fn is_packed_field(&self) -> bool {
+        matches!(self.0, TypeKind::PackedFld)
+    }
+
     pub fn is_mut_static(&self) -> bool {
         matches!(self.0, TypeKind::StaticMut)
     }
@@ -362,11 +401,34 @@ impl CanonicalType {
     }
 }
 
+/// Return true if the string matches the glob, where `*` matches any
+/// (possibly empty) substring
+pub(crate) fn glob_match(glob: &str, s: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = s.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect::<Vec<_>>();
+    let Some(last) = parts.pop() else {
+        // No wildcard
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
 /// Type representing a pattern over paths
 ///
-/// Currently supported: only patterns of the form
+/// Currently supported: patterns of the form
 /// <path>::* (includes <path> itself)
-/// The ::* is left implicit and should not be provided
+/// The ::* is left implicit and should not be provided.
+/// Idents in the path may also contain `*` wildcards, e.g. std::process::*::output
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Pattern(IdentPath);
 impl Display for Pattern {
@@ -450,6 +512,43 @@ mod tests {
         assert!(!p.matches(&pat4));
     }
 
+    #[test]
+    fn test_path_matches_wildcard() {
+        let p = IdentPath::new("std::process::Command::output");
+        assert!(p.matches(&Pattern::new("std::process::*::output")));
+        assert!(p.matches(&Pattern::new("std::*::Command")));
+        assert!(p.matches(&Pattern::new("std::process::Com*::out*")));
+        assert!(p.matches(&Pattern::new("*::process")));
+        assert!(!p.matches(&Pattern::new("std::process::*::spawn")));
+        assert!(!p.matches(&Pattern::new("std::*::Command::output::extra")));
+        assert!(!p.matches(&Pattern::new("std::fs::*")));
+    }
+
+    #[test]
+    fn test_path_matches_glob() {
+        let p = IdentPath::new("libc::unix::sysconf");
+        assert!(p.matches_glob("libc::*"));
+        assert!(p.matches_glob("libc::unix::*"));
+        assert!(p.matches_glob("libc"));
+        assert!(!p.matches_glob("libc::unix::sysconf::*"));
+        assert!(!p.matches_glob("std::fs::*"));
+
+        // Idents are matched in full, not by string prefix
+        assert!(!IdentPath::new("libc_ex::main").matches_glob("libc"));
+        assert!(!IdentPath::new("libc_ex::main").matches_glob("libc::*"));
+        assert!(!IdentPath::new("std::fsx::read").matches_glob("std::fs"));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("libc::*", "libc::unix::sysconf"));
+        assert!(glob_match("*sysconf", "libc::unix::sysconf"));
+        assert!(glob_match("src/*/*.rs", "src/unix/mod.rs"));
+        assert!(glob_match("a*b*b", "abb"));
+        assert!(!glob_match("a*b*b", "ab"));
+        assert!(!glob_match("libc", "libc::sysconf"));
+    }
+
     #[test]
     fn test_pattern_subset_superset() {
         let pat1 = Pattern::new("std");
@@ -473,4 +572,16 @@ mod tests {
         assert!(!pat2.subset(&pat4));
         assert!(!pat4.subset(&pat2));
     }
+
+    #[test]
+    fn test_crate_prefix() {
+        let p = CanonicalPath::new("foo_bar::sub::effect");
+        assert_eq!(p.strip_crate_prefix(), IdentPath::new("sub::effect"));
+        assert_eq!(p.with_crate("baz-qux"), CanonicalPath::new("baz_qux::sub::effect"));
+        assert_eq!(p.with_crate("foo-bar"), p);
+
+        let krate = CanonicalPath::new("foo_bar");
+        assert!(krate.strip_crate_prefix().is_empty());
+        assert_eq!(krate.with_crate("baz"), CanonicalPath::new("baz"));
+    }
 }
@@ -1,10 +1,14 @@
 pub mod audit_chain;
 pub mod audit_file;
 pub mod auditing;
+pub mod capabilities;
 pub mod download_crate;
 pub mod effect;
 pub mod ident;
 pub mod loc_tracker;
+pub mod output;
+pub mod regression;
+pub mod scan_config;
 pub mod scan_stats;
 pub mod scanner;
 pub mod sink;
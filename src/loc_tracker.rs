@@ -7,14 +7,20 @@
 //! - The "length" of each block is defined to be the end line, minus the start line,
 //!   plus one if the excerpt starts and ends on the same line.
 
+use super::effect::SrcLoc;
+
+use serde::{Deserialize, Serialize};
 use syn::spanned::Spanned;
 
 /// Lines of Code tracker
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct LoCTracker {
     instances: usize,
     lines: usize,
     zero_size_lines: usize,
+    /// Locations of the spans added with `add_at`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    locs: Vec<SrcLoc>,
 }
 impl LoCTracker {
     /// Create an empty tracker
@@ -36,6 +42,20 @@ impl LoCTracker {
         }
     }
 
+    /// Add a syn Spanned object, and keep its location
+    pub fn add_at<S: Spanned>(&mut self, s: S, loc: SrcLoc) {
+        self.add(s);
+        self.locs.push(loc);
+    }
+
+    /// Add all spans tracked by another tracker
+    pub fn combine(&mut self, other: &LoCTracker) {
+        self.instances += other.instances;
+        self.lines += other.lines;
+        self.zero_size_lines += other.zero_size_lines;
+        self.locs.extend(other.locs.iter().cloned());
+    }
+
     /// Return true if no spans were added
     pub fn is_empty(&self) -> bool {
         self.instances == 0
@@ -51,6 +71,11 @@ impl LoCTracker {
         self.lines + self.zero_size_lines
     }
 
+    /// Get the locations of the spans added with `add_at`
+    pub fn get_locs(&self) -> &[SrcLoc] {
+        &self.locs
+    }
+
     /// Summary as a CSV
     pub fn as_csv(&self) -> String {
         format!("{}, {}", self.get_instances(), self.get_loc())
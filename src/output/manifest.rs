@@ -0,0 +1,61 @@
+//! Reproducibility manifest for structured scan output.
+//!
+//! Records how a set of results was produced (the cargo-scan version, scan
+//! options, and a hash of the crate sources), so that a reviewer can confirm
+//! the results by re-running the scan on the same sources.
+
+use crate::auditing::util::hash_dir;
+use crate::effect::{EffectFilter, EffectType};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ScanManifest {
+    pub cargo_scan_version: String,
+    pub effect_types: Vec<EffectType>,
+    /// Sink patterns used in addition to the default ones, sorted
+    pub sinks: Vec<String>,
+    pub quick_mode: bool,
+    /// Cargo features whose `#[cfg(feature = ...)]` code was scanned, in
+    /// addition to any enabled by rust-analyzer
+    pub features: Vec<String>,
+    /// Filters on the effects that were output
+    #[serde(default)]
+    pub filter: EffectFilter,
+    /// Hex-encoded SHA-256 of the crate sources, as computed by `hash_dir`
+    pub crate_hash: String,
+}
+
+impl ScanManifest {
+    pub fn new<I>(
+        crate_path: &Path,
+        effect_types: &[EffectType],
+        sinks: I,
+        quick_mode: bool,
+        features: &[String],
+        filter: EffectFilter,
+    ) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut sinks = sinks.into_iter().collect::<Vec<_>>();
+        sinks.sort();
+        Ok(Self {
+            cargo_scan_version: env!("CARGO_PKG_VERSION").to_string(),
+            effect_types: effect_types.to_vec(),
+            sinks,
+            quick_mode,
+            features: features.to_vec(),
+            filter,
+            crate_hash: hex_hash(&hash_dir(crate_path)?),
+        })
+    }
+}
+
+/// Hex encoding of a hash from `hash_dir`
+pub fn hex_hash(hash: &[u8; 32]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,3 @@
+pub mod manifest;
+pub mod ndjson;
+pub mod sarif;
@@ -0,0 +1,42 @@
+//! Newline-delimited JSON output for scan results.
+//!
+//! Each line is a JSON object: the scan manifest first, if any, as
+//! `{"manifest": ...}`, followed by an `EffectRecord` for each effect.
+
+use super::manifest::ScanManifest;
+use crate::effect::{EffectInstance, EffectRecord};
+
+use anyhow::Result;
+use std::io::Write;
+
+/// Write the manifest line
+pub fn write_manifest(mut writer: impl Write, manifest: &ScanManifest) -> Result<()> {
+    serde_json::to_writer(&mut writer, &serde_json::json!({ "manifest": manifest }))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Write the line for a single effect
+pub fn write_effect(mut writer: impl Write, effect: &EffectInstance) -> Result<()> {
+    serde_json::to_writer(&mut writer, &EffectRecord::from(effect))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Write the given effects, after the manifest if given
+pub fn write_effects<'a, I>(
+    mut writer: impl Write,
+    effects: I,
+    manifest: Option<&ScanManifest>,
+) -> Result<()>
+where
+    I: IntoIterator<Item = &'a EffectInstance>,
+{
+    if let Some(manifest) = manifest {
+        write_manifest(&mut writer, manifest)?;
+    }
+    for effect in effects {
+        write_effect(&mut writer, effect)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! SARIF output for scan results.
+//!
+//! SARIF (Static Analysis Results Interchange Format) is ingested by GitHub
+//! code scanning and other CI systems. Each effect becomes a SARIF result,
+//! with one rule per effect type. The scan manifest, if any, is recorded in
+//! the properties of the run.
+
+use super::manifest::ScanManifest;
+use crate::effect::{EffectInstance, EffectType, EFFECT_TYPE_DOCS};
+use crate::scanner::ScanResults;
+
+use serde_json::{json, Value};
+
+pub const SARIF_VERSION: &str = "2.1.0";
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Rule ID for effects of the given type
+pub fn rule_id(effect_type: EffectType) -> String {
+    format!("cargo-scan/{}", effect_type)
+}
+
+fn rules() -> Vec<Value> {
+    EFFECT_TYPE_DOCS
+        .iter()
+        .map(|doc| {
+            json!({
+                "id": rule_id(doc.effect_type),
+                "name": doc.effect_type.to_string(),
+                "shortDescription": { "text": doc.summary },
+                "fullDescription": { "text": doc.description },
+                "help": { "text": format!("Example:\n    {}", doc.example) },
+            })
+        })
+        .collect()
+}
+
+fn to_sarif_result(effect: &EffectInstance) -> Value {
+    let effect_type = EffectType::from(effect.eff_type());
+    let rule_index = EFFECT_TYPE_DOCS.iter().position(|d| d.effect_type == effect_type);
+    let loc = effect.call_loc();
+    let message = format!(
+        "{} in {}: {}",
+        effect_type.doc().summary,
+        effect.caller_path(),
+        effect.callee_path()
+    );
+
+    json!({
+        "ruleId": rule_id(effect_type),
+        "ruleIndex": rule_index,
+        "level": "warning",
+        "message": { "text": message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": loc.filepath_string() },
+                // SARIF columns are 1-based
+                "region": {
+                    "startLine": loc.start_line(),
+                    "startColumn": loc.start_col() + 1,
+                    "endLine": loc.end_line(),
+                    "endColumn": loc.end_col() + 1,
+                },
+            },
+            "logicalLocations": [{
+                "fullyQualifiedName": effect.caller_path(),
+                "kind": "function",
+            }],
+        }],
+    })
+}
+
+/// Convert the given effects to a SARIF log with a single run
+pub fn effects_to_sarif<'a, I>(
+    effects: I,
+    crate_name: &str,
+    manifest: Option<&ScanManifest>,
+) -> Value
+where
+    I: IntoIterator<Item = &'a EffectInstance>,
+{
+    let results: Vec<Value> = effects.into_iter().map(to_sarif_result).collect();
+    let mut properties = json!({ "crate": crate_name });
+    if let Some(manifest) = manifest {
+        properties["manifest"] = json!(manifest);
+    }
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cargo-scan",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/PLSysSec/cargo-scan",
+                    "rules": rules(),
+                },
+            },
+            "properties": properties,
+            "results": results,
+        }],
+    })
+}
+
+/// Convert the effects in the scan results to a SARIF log, recording the
+/// manifest of the scan if given
+pub fn to_sarif(
+    results: &ScanResults,
+    crate_name: &str,
+    manifest: Option<&ScanManifest>,
+) -> Value {
+    effects_to_sarif(&results.effects, crate_name, manifest)
+}
@@ -0,0 +1,75 @@
+//! Detect effect regressions: effects in a local copy of a crate that are not
+//! in its published version on crates.io, e.g. to check a crate before
+//! publishing a new version.
+
+use crate::audit_file::AuditFile;
+use crate::download_crate;
+use crate::effect::{EffectInstance, EffectType};
+use crate::util;
+
+use anyhow::{bail, Result};
+use std::collections::HashSet;
+use std::fs::create_dir_all;
+use std::path::Path;
+use url::Url;
+
+/// Scan both versions of a crate and get the effects in `local_path` that
+/// aren't in `published_path`. Effects are matched as in `AuditFile::diff`,
+/// so effects that only moved within a file aren't reported.
+pub fn new_effects(
+    published_path: &Path,
+    local_path: &Path,
+    relevant_effects: &[EffectType],
+    quick: bool,
+) -> Result<Vec<EffectInstance>> {
+    let published = AuditFile::new_empty_default_with_sinks(
+        published_path,
+        HashSet::new(),
+        relevant_effects,
+        quick,
+    )?;
+    let local = AuditFile::new_empty_default_with_sinks(
+        local_path,
+        HashSet::new(),
+        relevant_effects,
+        quick,
+    )?;
+
+    Ok(AuditFile::diff(&published, &local).added)
+}
+
+/// Download the given published version of the crate at `crate_path`
+/// to `download_dir`, from crates.io or the given registry mirror, and get the
+/// effects in the crate that aren't in the published version. Returns the
+/// published version compared against along with the new effects.
+///
+/// The version defaults to the latest one on crates.io, so it's required with
+/// a registry mirror, which can't be asked for its latest version.
+pub fn new_effects_since_published(
+    crate_path: &Path,
+    version: Option<&str>,
+    download_dir: &str,
+    registry: Option<&Url>,
+    relevant_effects: &[EffectType],
+    quick: bool,
+) -> Result<(String, Vec<EffectInstance>)> {
+    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+    let version = match version {
+        Some(v) => v.to_string(),
+        None if registry.is_some() => {
+            bail!("A published version is required with a registry mirror")
+        }
+        None => download_crate::get_latest_version(&crate_name)?,
+    };
+
+    create_dir_all(download_dir)?;
+    let published_path = download_crate::download_crate_from_info(
+        &crate_name,
+        &version,
+        download_dir,
+        registry,
+    )?;
+    let effects = new_effects(&published_path, crate_path, relevant_effects, quick)?;
+
+    Ok((version, effects))
+}
@@ -237,6 +237,14 @@ impl<'a> Resolve<'a> for HackyResolver<'a> {
     fn resolve_all_impl_methods(&self, _: &'a syn::Ident) -> Vec<CanonicalPath> {
         Vec::new()
     }
+
+    fn resolve_dyn_impl_methods(&self, _: &'a syn::Ident) -> Vec<CanonicalPath> {
+        Vec::new()
+    }
+
+    fn resolve_virtual_impl_methods(&self, _: &'a syn::Ident) -> Vec<CanonicalPath> {
+        Vec::new()
+    }
 }
 
 impl<'a> HackyResolver<'a> {
@@ -2,14 +2,24 @@ use anyhow::{anyhow, Result};
 use itertools::Itertools;
 use log::debug;
 use ra_ap_cfg::CfgDiff;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
+use std::env;
 use std::fs::canonicalize;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use crate::effect::SrcLoc;
+use crate::effect::{ApiStability, FnSignature, SrcLoc};
 use crate::ident::{CanonicalPath, CanonicalType, Ident};
 
-use ra_ap_hir::{AssocItem, CfgAtom, Crate, Impl, Semantics};
+use ra_ap_hir::{
+    AsAssocItem, AssocItem, CfgAtom, Crate, HasAttrs, HasSource, Impl, Semantics,
+};
+use ra_ap_hir_def::attr::Attrs;
 use ra_ap_hir_def::db::DefDatabase;
 use ra_ap_hir_def::{FunctionId, Lookup};
 use ra_ap_ide::{AnalysisHost, Diagnostic, FileId, LineCol, RootDatabase, TextSize};
@@ -21,19 +31,31 @@ use ra_ap_project_model::{
     CargoConfig, CargoFeatures, CfgOverrides, InvocationLocation, InvocationStrategy,
     RustLibSource,
 };
-use ra_ap_syntax::{SourceFile, SyntaxToken};
+use ra_ap_syntax::ast;
+use ra_ap_syntax::{AstNode, SourceFile, SyntaxToken};
 use ra_ap_vfs::{Vfs, VfsPath};
 
 use super::util::{canonical_path, get_canonical_type, get_token, syntax_node_from_def};
 
+/// Environment variable with the maximum number of seconds to spend loading
+/// a workspace with rust-analyzer (no limit if unset)
+pub const RESOLVER_TIMEOUT_VAR: &str = "CARGO_SCAN_RESOLVER_TIMEOUT";
+
 #[derive(Debug)]
 pub struct Resolver {
     host: AnalysisHost,
     vfs: Vfs,
+    /// Canonical paths of the identifiers resolved so far, by file and
+    /// offset. The `ResolverImpl`s for each file share the cache, but since
+    /// entries are per location, it only hits when a file is scanned again
+    /// with the same resolver, e.g. by `ScanResults::rescan_file`.
+    ident_cache: RefCell<HashMap<(FileId, TextSize, Ident), CanonicalPath>>,
+    /// Number of identifiers resolved from `ident_cache`
+    ident_cache_hits: Cell<usize>,
 }
 
 impl Resolver {
-    fn cargo_config() -> CargoConfig {
+    fn cargo_config(include_tests: bool) -> CargoConfig {
         // List of features to activate (or deactivate).
         let features = CargoFeatures::All;
 
@@ -60,9 +82,13 @@ impl Resolver {
         let sysroot_src = None;
         let extra_args = Vec::new();
 
-        // Disable '#[cfg(test)]' in all crates of the workspace
-        let disabled_cfgs =
-            CfgDiff::new(vec![], vec![CfgAtom::Flag("test".into())]).unwrap_or_default();
+        // Disable '#[cfg(test)]' in all crates of the workspace, unless we
+        // want to scan test code too
+        let disabled_cfgs = if include_tests {
+            CfgDiff::default()
+        } else {
+            CfgDiff::new(vec![], vec![CfgAtom::Flag("test".into())]).unwrap_or_default()
+        };
         let cfg_overrides =
             CfgOverrides { global: disabled_cfgs, selective: Default::default() };
 
@@ -83,7 +109,32 @@ impl Resolver {
         }
     }
 
+    /// Read the workspace loading timeout (in seconds) from the
+    /// `CARGO_SCAN_RESOLVER_TIMEOUT` environment variable, if set
+    fn load_timeout() -> Result<Option<Duration>> {
+        match env::var(RESOLVER_TIMEOUT_VAR) {
+            Ok(secs) => {
+                let secs = secs.trim().parse::<u64>().map_err(|e| {
+                    anyhow!(
+                        "Invalid value for {}: {:?} ({})",
+                        RESOLVER_TIMEOUT_VAR,
+                        secs,
+                        e
+                    )
+                })?;
+                Ok(Some(Duration::from_secs(secs)))
+            }
+            Err(env::VarError::NotPresent) => Ok(None),
+            Err(e) => Err(anyhow!("Invalid value for {}: {}", RESOLVER_TIMEOUT_VAR, e)),
+        }
+    }
+
     pub fn new(crate_path: &Path) -> Result<Resolver> {
+        Self::new_with_tests(crate_path, false)
+    }
+
+    /// Create a resolver, optionally with `#[cfg(test)]` code enabled
+    pub fn new_with_tests(crate_path: &Path, include_tests: bool) -> Result<Resolver> {
         debug!("Creating resolver with path {:?}", crate_path);
 
         // Make sure the path is a crate
@@ -94,8 +145,53 @@ impl Resolver {
             ));
         }
 
+        let timeout = match Self::load_timeout()? {
+            Some(timeout) => timeout,
+            None => {
+                return Self::load(crate_path, include_tests, &AtomicBool::new(false))
+            }
+        };
+
+        // Load the workspace on a worker thread so we can give up on crates
+        // that take too long. After a timeout the worker is told to stop, but
+        // rust-analyzer can't be interrupted while it is loading the workspace
+        // or priming its caches, so the stage that is running when we give up
+        // still finishes in the background before the thread exits.
+        let (tx, rx) = mpsc::channel();
+        let path = crate_path.to_path_buf();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let worker_cancelled = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            // The receiver is gone if we already timed out
+            let _ = tx.send(Self::load(&path, include_tests, &worker_cancelled));
+        });
+
+        match rx.recv_timeout(timeout) {
+            Ok(res) => res,
+            Err(RecvTimeoutError::Timeout) => {
+                cancelled.store(true, Ordering::Relaxed);
+                Err(anyhow!(
+                    "Loading the workspace at {:?} timed out after {}s",
+                    crate_path,
+                    timeout.as_secs()
+                ))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(anyhow!(
+                "Loading the workspace at {:?} failed unexpectedly",
+                crate_path
+            )),
+        }
+    }
+
+    /// Load the workspace at `crate_path`, stopping before priming the caches
+    /// if `cancelled` has been set in the meantime
+    fn load(
+        crate_path: &Path,
+        include_tests: bool,
+        cancelled: &AtomicBool,
+    ) -> Result<Resolver> {
         // TODO: Maybe allow to load and analyze multiple workspaces
-        let cargo_config = &Self::cargo_config();
+        let cargo_config = &Self::cargo_config(include_tests);
         let progress = &|p| debug!("Workspace loading progress: {:?}", p);
 
         let with_proc_macro_server = ProcMacroServerChoice::Sysroot;
@@ -103,7 +199,8 @@ impl Resolver {
         let load_config = LoadCargoConfig {
             load_out_dirs_from_check: true,
             with_proc_macro_server,
-            prefill_caches: true,
+            // Primed below, so that a cancelled load can stop first
+            prefill_caches: false,
         };
 
         let (host, vfs, _) = ra_ap_load_cargo::load_workspace_at(
@@ -113,15 +210,39 @@ impl Resolver {
             progress,
         )?;
 
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(anyhow!(
+                "Loading the workspace at {:?} was cancelled",
+                crate_path
+            ));
+        }
+        host.analysis().parallel_prime_caches(1, |_| {})?;
+
         debug!("...created");
 
-        Ok(Resolver { host, vfs })
+        Ok(Resolver {
+            host,
+            vfs,
+            ident_cache: Default::default(),
+            ident_cache_hits: Default::default(),
+        })
     }
 
     fn db(&self) -> &RootDatabase {
         self.host.raw_database()
     }
 
+    /// Number of identifiers whose resolution has been cached
+    pub fn cached_idents(&self) -> usize {
+        self.ident_cache.borrow().len()
+    }
+
+    /// Number of identifiers resolved from the cache rather than by
+    /// rust-analyzer
+    pub fn ident_cache_hits(&self) -> usize {
+        self.ident_cache_hits.get()
+    }
+
     fn find_file_id(&self, filepath: &Path) -> Result<FileId> {
         let abs_path = canonicalize(filepath)?;
         let vfs_path = VfsPath::new_real_path(abs_path.display().to_string());
@@ -222,26 +343,39 @@ impl<'a> ResolverImpl<'a> {
         Some(())
     }
 
-    fn find_def(&self, token: &SyntaxToken) -> Result<Definition> {
+    /// Get the candidate definitions of the token, best first. There may be
+    /// several, e.g. for field shorthand syntax, which uses a single reference
+    /// to point to both a local and a field, or for tokens in macros that
+    /// expand to several places. Local definitions are preferred.
+    /// Errors if the token has no definition.
+    fn find_def(&self, token: &SyntaxToken) -> Result<Vec<Definition>> {
         // For ra_ap_syntax::TextSize, using default, idk if this is correct
         let text_size = Default::default();
-        self.sems
+        let mut defs = self
+            .sems
             .descend_into_macros(token.clone(), text_size)
             .iter()
-            .filter_map(|t| {
-                // 'IdentClass::classify_token' might return two definitions
-                // due to field shorthand syntax, which uses a single
-                // reference to point to two different defs.
-                // We only care about the first one, which corresponds to a
-                // local definition.
-                IdentClass::classify_token(&self.sems, t)?.definitions().pop_at(0)
-            })
-            .exactly_one()
-            .or(Err(anyhow!(
+            .filter_map(|t| IdentClass::classify_token(&self.sems, t))
+            .flat_map(|c| c.definitions())
+            .unique()
+            .collect_vec();
+        if defs.is_empty() {
+            return Err(anyhow!(
                 "Could not classify token {:?}. Diagnostics: {:?}",
                 token.to_string(),
                 self.get_token_diagnostics(token)
-            )))
+            ));
+        }
+        if defs.len() > 1 {
+            debug!("Multiple definitions for token {:?}: {:?}", token.to_string(), defs);
+        }
+        defs.sort_by_key(|d| !matches!(d, Definition::Local(_)));
+        Ok(defs)
+    }
+
+    /// Get the best candidate definition of the token (see `find_def`)
+    fn best_def(&self, token: &SyntaxToken) -> Result<Definition> {
+        Ok(self.find_def(token)?[0])
     }
 
     fn token(&self, i: Ident, s: SrcLoc) -> Result<SyntaxToken> {
@@ -261,24 +395,94 @@ impl<'a> ResolverImpl<'a> {
     }
 
     pub fn resolve_ident(&self, s: SrcLoc, i: Ident) -> Result<CanonicalPath> {
-        let token = self.token(i, s)?;
-        let def = self.find_def(&token)?;
-        self.parse_source_file(&def);
+        let offset = self.resolver.find_offset(self.file_id, s)?;
+        let key = (self.file_id, offset, i.clone());
+        if let Some(cp) = self.resolver.ident_cache.borrow().get(&key) {
+            self.resolver.ident_cache_hits.set(self.resolver.ident_cache_hits.get() + 1);
+            return Ok(cp.clone());
+        }
 
-        canonical_path(&self.sems, self.db, &def)
-            .ok_or_else(|| anyhow!("Could not construct canonical path for '{:?}'", def))
+        let token = get_token(&self.src_file, offset, i)?;
+        let defs = self.find_def(&token)?;
+        // Use the best candidate we can construct a canonical path for
+        let cp = defs
+            .iter()
+            .find_map(|def| {
+                self.parse_source_file(def);
+                canonical_path(&self.sems, self.db, def)
+            })
+            .ok_or_else(|| {
+                anyhow!("Could not construct canonical path for '{:?}'", defs)
+            })?;
+        self.resolver.ident_cache.borrow_mut().insert(key, cp.clone());
+        Ok(cp)
     }
 
     pub fn resolve_type(&self, s: SrcLoc, i: Ident) -> Result<CanonicalType> {
         let token = self.token(i, s)?;
-        let def = self.find_def(&token)?;
+        let def = self.best_def(&token)?;
 
         get_canonical_type(self.db, &def)
     }
 
+    /// Get the signature of the function referred to by the identifier,
+    /// with types as written in the function's source
+    pub fn resolve_fn_signature(&self, s: SrcLoc, i: Ident) -> Result<FnSignature> {
+        let token = self.token(i, s)?;
+        let def = self.best_def(&token)?;
+
+        let Definition::Function(f) = def else {
+            return Err(anyhow!("Definition is not a function: {:?}", def));
+        };
+        let src = f
+            .source(self.db)
+            .ok_or_else(|| anyhow!("Could not find source for function {:?}", f))?
+            .value;
+
+        let type_str = |ty: ast::Type| {
+            ty.syntax().text().to_string().trim_start_matches("::").to_string()
+        };
+
+        let mut params = Vec::new();
+        if let Some(param_list) = src.param_list() {
+            if let Some(self_param) = param_list.self_param() {
+                params.push(self_param.syntax().text().to_string());
+            }
+            params.extend(param_list.params().filter_map(|p| p.ty()).map(type_str));
+        }
+        let ret = src.ret_type().and_then(|r| r.ty()).map(type_str);
+
+        Ok(FnSignature { params, ret })
+    }
+
+    /// Get the stability of the function referred to by the identifier, from
+    /// its `#[stable]`/`#[unstable]` attribute or else from that of the closest
+    /// enclosing module. Returns None if none of them are marked, which is the
+    /// case outside of the standard library.
+    pub fn resolve_stability(&self, s: SrcLoc, i: Ident) -> Result<Option<ApiStability>> {
+        let token = self.token(i, s)?;
+        let def = self.best_def(&token)?;
+
+        let Definition::Function(f) = def else {
+            return Err(anyhow!("Definition is not a function: {:?}", def));
+        };
+        if let Some(stability) = attrs_stability(&f.attrs(self.db)) {
+            return Ok(Some(stability));
+        }
+        let mut module = Some(f.module(self.db));
+        while let Some(m) = module {
+            if let Some(stability) = attrs_stability(&m.attrs(self.db)) {
+                return Ok(Some(stability));
+            }
+            module = m.parent(self.db);
+        }
+
+        Ok(None)
+    }
+
     pub fn is_ffi(&self, s: SrcLoc, i: Ident) -> Result<bool> {
         let token = self.token(i, s)?;
-        let def = self.find_def(&token)?;
+        let def = self.best_def(&token)?;
 
         match def {
             Definition::Function(function) => {
@@ -304,7 +508,7 @@ impl<'a> ResolverImpl<'a> {
 
     pub fn is_unsafe_call(&self, s: SrcLoc, i: Ident) -> Result<bool> {
         let token = self.token(i, s)?;
-        let def = self.find_def(&token)?;
+        let def = self.best_def(&token)?;
 
         if let Definition::Function(f) = def {
             let func_id = FunctionId::from(f);
@@ -321,7 +525,7 @@ impl<'a> ResolverImpl<'a> {
         i: Ident,
     ) -> Result<bool> {
         let token = self.token(i, s)?;
-        let def = self.find_def(&token)?;
+        let def = self.best_def(&token)?;
 
         let is_immutable_static = |def: Definition| -> bool {
             if let Definition::Static(s) = def {
@@ -341,7 +545,7 @@ impl<'a> ResolverImpl<'a> {
         i: Ident,
     ) -> Result<Vec<CanonicalPath>> {
         let token = self.token(i.clone(), s.clone())?;
-        let def = self.find_def(&token)?;
+        let def = self.best_def(&token)?;
 
         let mut impl_methods_for_trait_method: Vec<CanonicalPath> = Vec::new();
         let filter_ = |x: AssocItem| match x {
@@ -365,4 +569,72 @@ impl<'a> ResolverImpl<'a> {
 
         Ok(impl_methods_for_trait_method)
     }
+
+    /// Gathers the implementations in the current crate of the trait method
+    /// called on a `dyn Trait` receiver, any of which may be the one called
+    /// at runtime. Returns no methods if the receiver is not a trait object,
+    /// unless `any_receiver` is set: then any call that resolves to the trait
+    /// method counts, e.g. on a generic type bounded by the trait, which
+    /// over-approximates the impls that can actually be called.
+    pub fn all_impl_methods_for_trait_method(
+        &self,
+        s: SrcLoc,
+        i: Ident,
+        any_receiver: bool,
+    ) -> Result<Vec<CanonicalPath>> {
+        let token = self.token(i, s)?;
+        let def = self.best_def(&token)?;
+
+        let Definition::Function(f) = def else {
+            return Ok(Vec::new());
+        };
+        let Some(tr) = f.as_assoc_item(self.db).and_then(|x| x.containing_trait(self.db))
+        else {
+            return Ok(Vec::new());
+        };
+
+        let is_dyn_receiver = token
+            .parent_ancestors()
+            .find_map(ast::MethodCallExpr::cast)
+            .and_then(|m| m.receiver())
+            .and_then(|r| self.sems.type_of_expr(&r))
+            .is_some_and(|ty| {
+                ty.original.autoderef(self.db).any(|t| t.as_dyn_trait() == Some(tr))
+            });
+        if !any_receiver && !is_dyn_receiver {
+            return Ok(Vec::new());
+        }
+
+        let krate = self.sems.to_module_def(self.file_id).map(|m| m.krate());
+        let name = f.name(self.db);
+        let impl_methods = Impl::all_for_trait(self.db, tr)
+            .into_iter()
+            .filter(|imp| Some(imp.module(self.db).krate()) == krate)
+            .flat_map(|imp| imp.items(self.db))
+            .filter_map(|x| match x {
+                AssocItem::Function(g) if g.name(self.db) == name => {
+                    self.parse_source_file(&g.into())?;
+                    canonical_path(&self.sems, self.db, &g.into())
+                }
+                _ => None,
+            })
+            .collect();
+
+        Ok(impl_methods)
+    }
+}
+
+fn attrs_stability(attrs: &Attrs) -> Option<ApiStability> {
+    let unstable = attrs.by_key("unstable");
+    if unstable.exists() {
+        let feature = unstable
+            .find_string_value_in_tt("feature")
+            .map(|f| f.trim_matches('"').to_string())
+            .unwrap_or_default();
+        Some(ApiStability::Unstable { feature })
+    } else if attrs.by_key("stable").exists() {
+        Some(ApiStability::Stable)
+    } else {
+        None
+    }
 }
@@ -44,6 +44,8 @@ pub trait Resolve<'a>: Sized {
     fn resolve_unsafe_path(&self, p: &'a syn::Path) -> bool;
     fn resolve_unsafe_ident(&self, p: &'a syn::Ident) -> bool;
     fn resolve_all_impl_methods(&self, i: &'a syn::Ident) -> Vec<CanonicalPath>;
+    fn resolve_dyn_impl_methods(&self, i: &'a syn::Ident) -> Vec<CanonicalPath>;
+    fn resolve_virtual_impl_methods(&self, i: &'a syn::Ident) -> Vec<CanonicalPath>;
 
     /*
         Field and expression resolution
@@ -156,6 +158,19 @@ impl<'a> FileResolver<'a> {
         self.resolver.all_impl_methods_for_trait(s, i)
     }
 
+    fn resolve_dyn_impl_methods_core(
+        &self,
+        i: &syn::Ident,
+        any_receiver: bool,
+    ) -> Result<Vec<CanonicalPath>> {
+        let mut s = SrcLoc::from_span(self.filepath, i);
+        debug!("Resolving all impl methods for dyn trait method call: {}", i);
+        // Add 1 to column to avoid weird off-by-one errors
+        s.add1();
+        let i = ident_from_syn(i);
+        self.resolver.all_impl_methods_for_trait_method(s, i, any_receiver)
+    }
+
     fn resolve_or_else<S, R, F, T>(&self, i: &S, try_resolve: R, fallback: F) -> T
     where
         S: Display + Spanned,
@@ -315,4 +330,20 @@ impl<'a> Resolve<'a> for FileResolver<'a> {
             || self.backup.resolve_all_impl_methods(i),
         )
     }
+
+    fn resolve_dyn_impl_methods(&self, i: &'a syn::Ident) -> Vec<CanonicalPath> {
+        self.resolve_or_else(
+            i,
+            || self.resolve_dyn_impl_methods_core(i, false),
+            || self.backup.resolve_dyn_impl_methods(i),
+        )
+    }
+
+    fn resolve_virtual_impl_methods(&self, i: &'a syn::Ident) -> Vec<CanonicalPath> {
+        self.resolve_or_else(
+            i,
+            || self.resolve_dyn_impl_methods_core(i, true),
+            || self.backup.resolve_virtual_impl_methods(i),
+        )
+    }
 }
@@ -126,14 +126,19 @@ fn get_container_name(
         Definition::Local(l) => {
             let parent = l.parent(db);
             let parent_name = parent.name(db);
-            let parent_def = match parent {
-                DefWithBody::Function(f) => f.into(),
-                DefWithBody::Static(s) => s.into(),
-                DefWithBody::Const(c) => c.into(),
-                DefWithBody::Variant(v) => v.into(),
-                DefWithBody::InTypeConst(_) => unimplemented!("TODO"),
+            let parent_def: Option<Definition> = match parent {
+                DefWithBody::Function(f) => Some(f.into()),
+                DefWithBody::Static(s) => Some(s.into()),
+                DefWithBody::Const(c) => Some(c.into()),
+                DefWithBody::Variant(v) => Some(v.into()),
+                // Anonymous consts in types (e.g. array lengths) have no
+                // definition or name of their own, so leave them out of the
+                // path
+                DefWithBody::InTypeConst(_) => None,
             };
-            container_names.append(&mut get_container_name(sems, db, &parent_def));
+            if let Some(parent_def) = parent_def {
+                container_names.append(&mut get_container_name(sems, db, &parent_def));
+            }
             container_names.push(parent_name.map(name_to_string).unwrap_or_default())
         }
         Definition::Function(f) => {
@@ -246,8 +251,18 @@ pub(super) fn get_canonical_type(
             Some(it.ty(db))
         }
         Definition::Field(it) => {
-            if let VariantDef::Union(_) = &it.parent_def(db) {
-                ty_kind = TypeKind::UnionFld;
+            match &it.parent_def(db) {
+                VariantDef::Union(_) => ty_kind = TypeKind::UnionFld,
+                VariantDef::Struct(s) => {
+                    // Only fields that need more alignment than the packing
+                    // can be unaligned
+                    if let Some(pack) = s.repr(db).and_then(|r| r.pack) {
+                        if it.layout(db).map_or(true, |l| l.align() > pack.bytes()) {
+                            ty_kind = TypeKind::PackedFld
+                        }
+                    }
+                }
+                _ => (),
             }
             Some(it.ty(db))
         }
@@ -0,0 +1,69 @@
+//! Per-crate scan settings, loaded from a `.cargo-scan.toml` file in the
+//! crate directory. Command-line flags are merged on top of these.
+//!
+//! Example:
+//! ```toml
+//! sinks = ["my_crate::net::*"]
+//! effect-types = ["SinkCall", "FFICall"]
+//! quick-mode = true
+//! ```
+
+use super::effect::EffectType;
+use super::ident::IdentPath;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the config file, in the crate directory
+pub const CONFIG_FILE_NAME: &str = ".cargo-scan.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ScanConfig {
+    /// Additional sink patterns, as in a sinks file
+    pub sinks: Vec<String>,
+    /// The types of effects to report, if not given on the command line
+    pub effect_types: Option<Vec<EffectType>>,
+    /// Run in quick mode
+    pub quick_mode: bool,
+}
+
+impl ScanConfig {
+    /// Path of the config file for the crate at `crate_path`
+    pub fn path(crate_path: &Path) -> PathBuf {
+        crate_path.join(CONFIG_FILE_NAME)
+    }
+
+    /// Load the config file of the crate, or the default config if the crate
+    /// doesn't have one
+    pub fn load(crate_path: &Path) -> Result<Self> {
+        let path = Self::path(crate_path);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read config file: {}", path.display()))?;
+        let config: Self = toml::from_str(&contents)
+            .with_context(|| format!("Couldn't parse config file: {}", path.display()))?;
+        config.sink_paths()?;
+        Ok(config)
+    }
+
+    /// The additional sink patterns, checked like the ones in a sinks file
+    pub fn sink_paths(&self) -> Result<HashSet<IdentPath>> {
+        self.sinks
+            .iter()
+            .map(|s| {
+                let sink = IdentPath::new(s);
+                if sink.invariant() {
+                    Ok(sink)
+                } else {
+                    Err(anyhow!("Invalid sink pattern in {}: {}", CONFIG_FILE_NAME, s))
+                }
+            })
+            .collect()
+    }
+}
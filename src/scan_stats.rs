@@ -7,21 +7,61 @@
 use crate::ident::CanonicalPath;
 
 use super::audit_file::{AuditFile, EffectTree};
-use super::effect::{EffectInstance, EffectType, DEFAULT_EFFECT_TYPES};
+use super::effect::{
+    EffectInstance, EffectType, SrcLoc, UnsafeImpl, DEFAULT_EFFECT_TYPES,
+};
 use super::loc_tracker::LoCTracker;
 use super::scanner::ScanResults;
 
 use anyhow::Result;
 use log::{debug, warn};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::path::PathBuf;
 
+/// Counts summarizing a scan, see `ScanResults::stats_summary`. Serializable
+/// so that summaries can be aggregated across many crates.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanSummary {
+    /// Number of effects of each type found (types without effects are omitted)
+    pub effect_counts: BTreeMap<EffectType, usize>,
+    pub total_fns: usize,
+    pub pub_fns: usize,
+    /// Number of functions that are the caller of at least one effect
+    pub fns_with_effects: usize,
+    pub total_loc: usize,
+    /// LoC skipped by the scan: macros, conditional code, unsupported calls,
+    /// function pointers, and other unsupported syntax
+    pub skipped_loc: usize,
+    /// The functions with effects, sorted by effects per LoC, highest first
+    #[serde(default)]
+    pub fn_effects: Vec<FnEffectCount>,
+}
+
+/// The number of effects in a function, with the function's LoC
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FnEffectCount {
+    pub fn_name: CanonicalPath,
+    pub effects: usize,
+    pub loc: usize,
+}
+
+impl FnEffectCount {
+    /// Effects per line of code. Functions without LoC (e.g. FFI
+    /// declarations) count as one line.
+    pub fn density(&self) -> f64 {
+        self.effects as f64 / self.loc.max(1) as f64
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct CrateStats {
     pub crate_path: PathBuf,
 
     // List of effects
     pub effects: Vec<EffectInstance>,
+    /// Potential effects in `macro_rules!` definitions
+    pub macro_def_effects: Vec<EffectInstance>,
 
     // Scan metadata
     pub total_loc: LoCTracker,
@@ -32,7 +72,15 @@ pub struct CrateStats {
     pub skipped_other: LoCTracker,
     pub unsafe_traits: LoCTracker,
     pub unsafe_impls: LoCTracker,
+    /// The `unsafe impl` declarations, with their trait and implementing type
+    pub unsafe_impl_decls: Vec<UnsafeImpl>,
+    /// Unsafe blocks without any unsafe operations
+    pub empty_unsafe_blocks: Vec<SrcLoc>,
     pub pub_fns: usize,
+    /// Files skipped for exceeding the maximum file size, with their size
+    pub skipped_large_files: Vec<(PathBuf, u64)>,
+    /// Uses of the `alloc` crate, if the crate is `no_std`
+    pub no_std_alloc_uses: Vec<SrcLoc>,
 
     // AuditFile metadata
     pub pub_fns_with_effects: usize,
@@ -78,12 +126,26 @@ impl CrateStats {
 }
 
 pub fn get_crate_stats_default(crate_path: PathBuf, quick_mode: bool) -> CrateStats {
-    get_crate_stats(crate_path.clone(), DEFAULT_EFFECT_TYPES, quick_mode).unwrap_or_else(
-        |_| {
-            warn!("Scan crashed, skipping crate: {}", crate_path.to_string_lossy());
-            CrateStats { crate_path, ..Default::default() }
-        },
+    get_crate_stats_default_with_sinks(crate_path, HashSet::new(), quick_mode, None)
+}
+
+pub fn get_crate_stats_default_with_sinks(
+    crate_path: PathBuf,
+    sinks: HashSet<CanonicalPath>,
+    quick_mode: bool,
+    max_file_size: Option<u64>,
+) -> CrateStats {
+    get_crate_stats_with_sinks(
+        crate_path.clone(),
+        sinks,
+        DEFAULT_EFFECT_TYPES,
+        quick_mode,
+        max_file_size,
     )
+    .unwrap_or_else(|_| {
+        warn!("Scan crashed, skipping crate: {}", crate_path.to_string_lossy());
+        CrateStats { crate_path, ..Default::default() }
+    })
 }
 
 pub fn get_crate_stats(
@@ -91,12 +153,49 @@ pub fn get_crate_stats(
     effect_types: &[EffectType],
     quick_mode: bool,
 ) -> Result<CrateStats> {
-    let (audit, results) = AuditFile::new_caller_checked_default_with_results(
+    get_crate_stats_with_sinks(crate_path, HashSet::new(), effect_types, quick_mode, None)
+}
+
+/// Scan the crate and collect its stats, skipping source files larger than
+/// `max_file_size` bytes
+pub fn get_crate_stats_with_sinks(
+    crate_path: PathBuf,
+    sinks: HashSet<CanonicalPath>,
+    effect_types: &[EffectType],
+    quick_mode: bool,
+    max_file_size: Option<u64>,
+) -> Result<CrateStats> {
+    let (audit, results) = AuditFile::new_caller_checked_default_with_sinks_and_results(
         &crate_path,
+        sinks,
         effect_types,
         quick_mode,
+        max_file_size,
+        None,
+    )?;
+    Ok(crate_stats(crate_path, &audit, results))
+}
+
+/// Collect the stats of the crate from the results of an existing scan
+pub fn get_crate_stats_from_results(
+    crate_path: PathBuf,
+    effect_types: &[EffectType],
+    results: ScanResults,
+) -> Result<CrateStats> {
+    let audit = AuditFile::new_caller_checked_from_results(
+        &crate_path,
+        effect_types,
+        &results,
+        None,
     )?;
+    Ok(crate_stats(crate_path, &audit, results))
+}
 
+fn crate_stats(
+    crate_path: PathBuf,
+    audit: &AuditFile,
+    results: ScanResults,
+) -> CrateStats {
     let pub_fns = results.pub_fns.len();
     let mut pub_fns_with_effects = 0;
     let mut pub_total_effects = 0;
@@ -108,11 +207,13 @@ pub fn get_crate_stats(
         }
     }
 
-    let (audited_fns, audited_loc) = get_auditing_metrics(&audit, &results);
+    let (audited_fns, audited_loc) = get_auditing_metrics(audit, &results);
 
-    let result = CrateStats {
+    let no_std_alloc_uses = results.no_std_alloc_uses().to_vec();
+    CrateStats {
         crate_path,
         effects: results.effects,
+        macro_def_effects: results.macro_def_effects,
         total_loc: results.total_loc,
         skipped_macros: results.skipped_macros,
         skipped_conditional_code: results.skipped_conditional_code,
@@ -121,14 +222,16 @@ pub fn get_crate_stats(
         skipped_other: results.skipped_other,
         unsafe_traits: results.unsafe_traits,
         unsafe_impls: results.unsafe_impls,
+        unsafe_impl_decls: results.unsafe_impl_decls,
+        empty_unsafe_blocks: results.empty_unsafe_blocks,
         pub_fns,
+        skipped_large_files: results.skipped_large_files,
+        no_std_alloc_uses,
         pub_fns_with_effects,
         pub_total_effects,
         audited_fns,
         audited_loc,
-    };
-
-    Ok(result)
+    }
 }
 
 // Calculates the total number of functions and the total lines of code that will be audited.
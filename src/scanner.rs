@@ -5,48 +5,162 @@
 
 use crate::attr_parser::CfgPred;
 use crate::audit_file::EffectInfo;
+use crate::auditing::util::hash_dir;
+use crate::output::manifest::ScanManifest;
+use crate::output::ndjson;
 use crate::resolution::hacky_resolver::HackyResolver;
-use crate::resolution::name_resolution::Resolver;
+use crate::resolution::name_resolution::{Resolver, ResolverImpl};
 
-use super::effect::{Effect, EffectInstance, EffectType, FnDec, SrcLoc, Visibility};
-use super::ident::{CanonicalPath, IdentPath};
+use super::effect::{
+    runs_program, Effect, EffectInstance, EffectProvenance, EffectType, FnContext, FnDec,
+    ProgramArg, SrcLoc, UnsafeImpl, Visibility,
+};
+use super::ident::{CanonicalPath, Ident, IdentPath};
 use super::loc_tracker::LoCTracker;
+use super::scan_stats::{FnEffectCount, ScanSummary};
 use super::sink::Sink;
-use super::util;
+use super::util::{self, CrateId};
 use crate::resolution::resolve::{FileResolver, Resolve};
 
 use anyhow::{anyhow, Context, Result};
 use log::{debug, info, warn};
+use parse_display::Display;
+use petgraph::dot::Dot;
 use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::visit::{Bfs, EdgeRef};
 use petgraph::Direction;
-use proc_macro2::{TokenStream, TokenTree};
+use proc_macro2::{Span, TokenStream, TokenTree};
 use quote::ToTokens;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::fs::File;
-use std::io::Read;
-use std::path::Path as FilePath;
+use std::io::{Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path as FilePath, PathBuf};
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
 use syn::ForeignItemFn;
 
+/// Paths that `mem::transmute` can resolve to
+const TRANSMUTE_PATHS: &[&str] =
+    &["core::mem::transmute", "std::mem::transmute", "core::intrinsics::transmute"];
+
+/// Paths that `as_ptr` on a C string can resolve to
+const CSTR_AS_PTR_PATHS: &[&str] = &[
+    "core::ffi::c_str::CStr::as_ptr",
+    "std::ffi::CStr::as_ptr",
+    "std::ffi::c_str::CStr::as_ptr",
+    "alloc::ffi::c_str::CString::as_ptr",
+    "std::ffi::CString::as_ptr",
+];
+
+/// Methods that add an element to a collection, for tracking closures that
+/// are stored in a collection and called later
+const COLLECTION_ADD_METHODS: &[&str] = &["push", "push_back", "push_front", "insert"];
+
+/// Macros whose arguments are plain expressions evaluated where the macro is
+/// invoked, so they are scanned instead of skipped (see
+/// `expand_transparent_macros`)
+const TRANSPARENT_MACROS: &[&str] = &[
+    "addr_of",
+    "addr_of_mut",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "dbg",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "eprint",
+    "eprintln",
+    "format",
+    "panic",
+    "print",
+    "println",
+    "vec",
+    "write",
+    "writeln",
+];
+
+/// Raw pointer methods that offset the pointer
+const PTR_OFFSET_METHODS: &[&str] = &["offset", "add", "sub", "wrapping_offset"];
+
+/// Modules with the inherent impls of `*const T` and `*mut T`, for
+/// recognizing resolved raw pointer methods
+const RAW_PTR_IMPL_MODS: &[&str] = &["core::ptr::const_ptr::", "core::ptr::mut_ptr::"];
+
+/// Kinds of code that the scanner skips instead of analyzing, each counted
+/// by one of the `skipped_*` trackers of `ScanResults`
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    Display,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkippedKind {
+    #[display("macro")]
+    Macro,
+    #[display("conditional code")]
+    ConditionalCode,
+    #[display("function call")]
+    FnCall,
+    #[display("function pointer")]
+    FnPtr,
+    #[display("other")]
+    Other,
+}
+
+impl SkippedKind {
+    pub fn all() -> [Self; 5] {
+        [Self::Macro, Self::ConditionalCode, Self::FnCall, Self::FnPtr, Self::Other]
+    }
+}
+
 /// Results of a scan
 ///
 /// Holds the intermediate state between scans which doesn't hold references
 /// to file data
-#[derive(Debug, Default)]
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct ScanResults {
     pub effects: Vec<EffectInstance>,
     fn_ptr_effects: Vec<EffectInstance>,
+    /// Potential effects in the code of `macro_rules!` definitions, which
+    /// happen wherever the macro is invoked. Kept apart from `effects`, since
+    /// they are found in templates rather than code that runs as written.
+    pub macro_def_effects: Vec<EffectInstance>,
 
     // Saved function declarations
     pub pub_fns: HashSet<CanonicalPath>,
+    #[serde_as(as = "Vec<(_, _)>")]
     pub fn_locs: HashMap<CanonicalPath, SrcLoc>,
     pub trait_meths: HashSet<CanonicalPath>,
+    /// Functions declared `unsafe fn`
+    pub unsafe_fns: HashSet<CanonicalPath>,
+    /// Functions declared in test-only code, e.g. `#[test]` functions and
+    /// functions in `#[cfg(test)]` modules
+    pub test_fns: HashSet<CanonicalPath>,
     fns_with_effects: HashSet<CanonicalPath>,
 
     pub call_graph: DiGraph<CanonicalPath, SrcLoc>,
+    #[serde_as(as = "Vec<(_, _)>")]
     pub node_idxs: HashMap<CanonicalPath, NodeIndex>,
 
     /* Tracking lines of code (LoC) and skipped/unsupported cases */
@@ -58,11 +172,31 @@ pub struct ScanResults {
     pub skipped_other: LoCTracker,
     pub unsafe_traits: LoCTracker,
     pub unsafe_impls: LoCTracker,
+    /// The `unsafe impl` declarations, with their trait and implementing type
+    pub unsafe_impl_decls: Vec<UnsafeImpl>,
+    /// Unsafe blocks without any unsafe operations, which could be removed
+    pub empty_unsafe_blocks: Vec<SrcLoc>,
+    #[serde_as(as = "Vec<(_, _)>")]
     pub fn_loc_tracker: HashMap<CanonicalPath, LoCTracker>,
+    /// Number of effects with each function as the caller, for functions with
+    /// at least one effect. Counted from `effects` at the end of the scan.
+    #[serde_as(as = "Vec<(_, _)>")]
+    pub fn_effect_count: HashMap<CanonicalPath, usize>,
+    /// Files that weren't scanned because they exceeded the maximum file size,
+    /// with their size in bytes
+    pub skipped_large_files: Vec<(PathBuf, u64)>,
+    /// Location of the crate's `#![no_std]` attribute, if any (including
+    /// `#![cfg_attr(..., no_std)]`)
+    pub no_std: Option<SrcLoc>,
+    /// Uses of the `alloc` crate: `extern crate alloc` and `alloc::` paths
+    pub alloc_uses: Vec<SrcLoc>,
+
+    /// Set if the crate couldn't be loaded or resolved in full mode and was
+    /// scanned in quick mode instead, so the results are less precise
+    pub quick_mode_fallback: bool,
 
     // TODO other cases:
     pub _effects_loc: LoCTracker,
-    pub _skipped_build_rs: LoCTracker,
 }
 
 impl ScanResults {
@@ -70,10 +204,65 @@ impl ScanResults {
         Default::default()
     }
 
+    /// The tracker counting the skipped code of the given kind
+    pub fn skipped(&self, kind: SkippedKind) -> &LoCTracker {
+        match kind {
+            SkippedKind::Macro => &self.skipped_macros,
+            SkippedKind::ConditionalCode => &self.skipped_conditional_code,
+            SkippedKind::FnCall => &self.skipped_fn_calls,
+            SkippedKind::FnPtr => &self.skipped_fn_ptrs,
+            SkippedKind::Other => &self.skipped_other,
+        }
+    }
+
+    fn skipped_mut(&mut self, kind: SkippedKind) -> &mut LoCTracker {
+        match kind {
+            SkippedKind::Macro => &mut self.skipped_macros,
+            SkippedKind::ConditionalCode => &mut self.skipped_conditional_code,
+            SkippedKind::FnCall => &mut self.skipped_fn_calls,
+            SkippedKind::FnPtr => &mut self.skipped_fn_ptrs,
+            SkippedKind::Other => &mut self.skipped_other,
+        }
+    }
+
+    /// List the code the scanner skipped, i.e. the parts of the crate that
+    /// weren't analyzed for effects and need to be reviewed manually, sorted
+    /// by location. Empty unless the scan recorded them (`record_skipped`).
+    pub fn skipped_report(&self) -> Vec<(SkippedKind, SrcLoc)> {
+        let mut report = SkippedKind::all()
+            .into_iter()
+            .flat_map(|kind| {
+                self.skipped(kind).get_locs().iter().map(move |loc| (kind, loc.clone()))
+            })
+            .collect::<Vec<_>>();
+        report.sort_by(|(k1, l1), (k2, l2)| {
+            l1.dir()
+                .join(l1.file())
+                .cmp(&l2.dir().join(l2.file()))
+                .then_with(|| l1.start_line().cmp(&l2.start_line()))
+                .then_with(|| l1.start_col().cmp(&l2.start_col()))
+                .then_with(|| k1.cmp(k2))
+        });
+        report
+    }
+
     pub fn effects_set(&self) -> HashSet<&EffectInstance> {
         self.effects.iter().collect::<HashSet<_>>()
     }
 
+    /// Count the effects in each source file, for the files with at least one
+    /// effect. Sorted by the number of effects, most first, then by path.
+    pub fn effect_file_histogram(&self) -> Vec<(PathBuf, usize)> {
+        let mut counts: HashMap<PathBuf, usize> = HashMap::new();
+        for e in &self.effects {
+            let loc = e.call_loc();
+            *counts.entry(loc.dir().join(loc.file())).or_default() += 1;
+        }
+        let mut histogram = counts.into_iter().collect::<Vec<_>>();
+        histogram.sort_by(|(p1, n1), (p2, n2)| n2.cmp(n1).then_with(|| p1.cmp(p2)));
+        histogram
+    }
+
     pub fn get_callers(&self, callee: &CanonicalPath) -> Result<HashSet<EffectInfo>> {
         let callee_node = self
             .node_idxs
@@ -92,6 +281,215 @@ impl ScanResults {
         Ok(effects)
     }
 
+    /// Get the functions that call `callee` directly, sorted by path. Empty
+    /// if `callee` isn't in the call graph.
+    pub fn callers_of(&self, callee: &CanonicalPath) -> Vec<CanonicalPath> {
+        self.call_graph_neighbors(callee, Direction::Incoming)
+    }
+
+    /// Get the functions that `caller` calls directly, sorted by path. Empty
+    /// if `caller` isn't in the call graph.
+    pub fn callees_of(&self, caller: &CanonicalPath) -> Vec<CanonicalPath> {
+        self.call_graph_neighbors(caller, Direction::Outgoing)
+    }
+
+    fn call_graph_neighbors(
+        &self,
+        f: &CanonicalPath,
+        dir: Direction,
+    ) -> Vec<CanonicalPath> {
+        let Some(node) = self.node_idxs.get(f) else {
+            return Vec::new();
+        };
+        let mut fns = self
+            .call_graph
+            .neighbors_directed(*node, dir)
+            .map(|n| self.call_graph[n].clone())
+            .collect::<Vec<_>>();
+        fns.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        fns.dedup();
+        fns
+    }
+
+    /// Summarize the scan: effect counts by type, function counts, and LoC
+    /// scanned and skipped
+    pub fn stats_summary(&self) -> ScanSummary {
+        let mut effect_counts = BTreeMap::new();
+        for e in &self.effects {
+            *effect_counts.entry(EffectType::from(e.eff_type())).or_default() += 1;
+        }
+        let fns_with_effects =
+            self.effects.iter().map(|e| e.caller()).collect::<HashSet<_>>().len();
+        let skipped_loc =
+            SkippedKind::all().into_iter().map(|k| self.skipped(k).get_loc()).sum();
+
+        let mut fn_effects = self
+            .fn_effect_count
+            .iter()
+            .map(|(f, &effects)| FnEffectCount {
+                fn_name: f.clone(),
+                effects,
+                loc: self.fn_loc_tracker.get(f).map_or(0, |t| t.get_loc()),
+            })
+            .collect::<Vec<_>>();
+        fn_effects.sort_by(|a, b| {
+            b.density()
+                .total_cmp(&a.density())
+                .then_with(|| a.fn_name.as_str().cmp(b.fn_name.as_str()))
+        });
+
+        ScanSummary {
+            effect_counts,
+            total_fns: self.fn_locs.len(),
+            pub_fns: self.pub_fns.len(),
+            fns_with_effects,
+            total_loc: self.total_loc.get_loc(),
+            skipped_loc,
+            fn_effects,
+        }
+    }
+
+    /// Remove duplicate effects, i.e. effects with the same caller, callee,
+    /// effect type and call location, keeping the first of each.
+    ///
+    /// These can come up when the same call is seen both in the normal scan
+    /// and in a macro expansion.
+    ///
+    /// Since this is the last step of every scan, the effect counts per
+    /// function are also recounted here, to match the effects that were kept
+    /// after filtering by effect type.
+    pub fn dedup_effects(&mut self) {
+        let mut seen = HashSet::new();
+        self.effects.retain(|e| {
+            seen.insert((
+                e.caller().clone(),
+                e.callee().clone(),
+                EffectType::from(e.eff_type()),
+                e.call_loc().clone(),
+            ))
+        });
+        self.recount_fn_effects();
+    }
+
+    /// Remove the effects in test-only code (see `EffectInstance::in_test`),
+    /// which usually aren't a risk in production, along with the test-only
+    /// functions and their calls
+    pub fn remove_test_effects(&mut self) {
+        let test_fns = std::mem::take(&mut self.test_fns);
+
+        self.effects.retain(|e| !e.in_test());
+        self.fn_ptr_effects.retain(|e| !e.in_test());
+        self.fn_locs.retain(|f, _| !test_fns.contains(f));
+        self.pub_fns.retain(|f| !test_fns.contains(f));
+        self.trait_meths.retain(|f| !test_fns.contains(f));
+        self.unsafe_fns.retain(|f| !test_fns.contains(f));
+        self.fns_with_effects.retain(|f| !test_fns.contains(f));
+        self.fn_loc_tracker.retain(|f, _| !test_fns.contains(f));
+
+        self.call_graph.retain_edges(|g, e| {
+            let (caller, _) = g.edge_endpoints(e).unwrap();
+            !test_fns.contains(&g[caller])
+        });
+        self.prune_call_graph();
+        self.recount_fn_effects();
+    }
+
+    fn recount_fn_effects(&mut self) {
+        self.fn_effect_count.clear();
+        for e in &self.effects {
+            *self.fn_effect_count.entry(e.caller().clone()).or_default() += 1;
+        }
+    }
+
+    /// Get all effects transitively reachable from the given function through
+    /// the call graph, including the function's own effects
+    pub fn effects_reachable_from(&self, root: &CanonicalPath) -> Vec<&EffectInstance> {
+        let Some(root_node) = self.node_idxs.get(root) else {
+            return Vec::new();
+        };
+
+        let mut reachable = HashSet::new();
+        let mut bfs = Bfs::new(&self.call_graph, *root_node);
+        while let Some(node) = bfs.next(&self.call_graph) {
+            reachable.insert(&self.call_graph[node]);
+        }
+
+        self.effects.iter().filter(|e| reachable.contains(e.caller())).collect()
+    }
+
+    /// Get the uses of the `alloc` crate if the crate is `no_std`, i.e. the
+    /// places where a `no_std` crate still relies on a global allocator
+    pub fn no_std_alloc_uses(&self) -> &[SrcLoc] {
+        if self.no_std.is_some() {
+            &self.alloc_uses
+        } else {
+            &[]
+        }
+    }
+
+    /// Get the public functions with a safe signature that contain or
+    /// transitively reach an effect Rust considers unsafe, i.e. the functions
+    /// that encapsulate unsafe code behind a safe API. Sorted by path.
+    pub fn encapsulated_unsafe_fns(&self) -> Vec<&CanonicalPath> {
+        let mut fns = self
+            .pub_fns
+            .iter()
+            .filter(|f| !self.unsafe_fns.contains(f))
+            .filter(|f| self.effects_reachable_from(f).iter().any(|e| e.is_rust_unsafe()))
+            .collect::<Vec<_>>();
+        fns.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        fns
+    }
+
+    /// Get the public functions with a safe signature that contain or
+    /// transitively reach an FFI call or declaration, the first places to
+    /// look for soundness issues. Sorted by path.
+    pub fn safe_fns_reaching_ffi(&self) -> Vec<&CanonicalPath> {
+        let mut fns = self
+            .pub_fns
+            .iter()
+            .filter(|f| !self.unsafe_fns.contains(f))
+            .filter(|f| {
+                self.effects_reachable_from(f).iter().any(|e| {
+                    matches!(e.eff_type(), Effect::FFICall(_) | Effect::FFIDecl(_))
+                })
+            })
+            .collect::<Vec<_>>();
+        fns.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        fns
+    }
+
+    /// Write the effects as newline-delimited JSON, one object per effect,
+    /// after the manifest of the scan if given
+    pub fn to_ndjson(
+        &self,
+        writer: impl Write,
+        manifest: Option<&ScanManifest>,
+    ) -> Result<()> {
+        ndjson::write_effects(writer, &self.effects, manifest)
+    }
+
+    /// Render the call graph in the GraphViz DOT format. Nodes are labeled
+    /// with their canonical path and edges with the `file:line` of the call.
+    pub fn call_graph_dot(&self) -> String {
+        let graph = self.call_graph.map(
+            |_, n| n.to_string(),
+            |_, loc| format!("{}:{}", loc.file().display(), loc.start_line()),
+        );
+        format!("{}", Dot::new(&graph))
+    }
+
+    /// Add the effects from scanning the code templates of `macro_rules!`
+    /// definitions (see `macro_templates`) as potential effects. The function
+    /// declarations, calls and LoC in the templates aren't part of the crate,
+    /// so only the effects are kept.
+    fn add_macro_def_effects(&mut self, template_results: ScanResults) {
+        for mut eff in template_results.effects {
+            eff.set_found_via(EffectProvenance::MacroDefinition);
+            self.macro_def_effects.push(eff);
+        }
+    }
+
     pub fn add_fn_dec(&mut self, f: FnDec) {
         let fn_name = f.fn_name;
 
@@ -105,6 +503,277 @@ impl ScanResults {
         self.fn_locs.insert(fn_name, f.src_loc);
     }
 
+    /// Merge `other` into these results. Call graph nodes are matched by
+    /// their canonical path rather than their index in `other`, so calls
+    /// between functions from different result sets are kept.
+    pub fn combine_scan_results(&mut self, other: ScanResults) {
+        self.effects.extend(other.effects);
+        self.fn_ptr_effects.extend(other.fn_ptr_effects);
+        self.macro_def_effects.extend(other.macro_def_effects);
+
+        self.pub_fns.extend(other.pub_fns);
+        self.fn_locs.extend(other.fn_locs);
+        self.trait_meths.extend(other.trait_meths);
+        self.unsafe_fns.extend(other.unsafe_fns);
+        self.test_fns.extend(other.test_fns);
+        self.fns_with_effects.extend(other.fns_with_effects);
+        self.skipped_large_files.extend(other.skipped_large_files);
+        self.no_std = self.no_std.take().or(other.no_std);
+        self.alloc_uses.extend(other.alloc_uses);
+        self.quick_mode_fallback |= other.quick_mode_fallback;
+
+        for node in other.call_graph.node_weights() {
+            self.update_call_graph(node);
+        }
+        for edge in other.call_graph.raw_edges() {
+            let caller = &other.call_graph[edge.source()];
+            let callee = &other.call_graph[edge.target()];
+            self.add_call(caller, callee, edge.weight.clone());
+        }
+
+        self.total_loc.combine(&other.total_loc);
+        self.skipped_macros.combine(&other.skipped_macros);
+        self.skipped_conditional_code.combine(&other.skipped_conditional_code);
+        self.skipped_fn_calls.combine(&other.skipped_fn_calls);
+        self.skipped_fn_ptrs.combine(&other.skipped_fn_ptrs);
+        self.skipped_other.combine(&other.skipped_other);
+        self.unsafe_traits.combine(&other.unsafe_traits);
+        self.unsafe_impls.combine(&other.unsafe_impls);
+        self.unsafe_impl_decls.extend(other.unsafe_impl_decls);
+        self.empty_unsafe_blocks.extend(other.empty_unsafe_blocks);
+        for (f, tracker) in other.fn_loc_tracker {
+            self.fn_loc_tracker.entry(f).or_default().combine(&tracker);
+        }
+    }
+
+    /// Save the results to `path` to be reloaded with `load` instead of
+    /// re-scanning the crate. `key` identifies what the results were produced
+    /// from, e.g. the hash of the crate's sources from `hash_dir`.
+    pub fn save(&self, path: &FilePath, key: &[u8; 32]) -> Result<()> {
+        let f = File::create(path)?;
+        serde_json::to_writer(std::io::BufWriter::new(f), &(key, self))?;
+        Ok(())
+    }
+
+    /// Load results saved with `save`. Returns None if there are no saved
+    /// results, or if they were saved under a different key (i.e. they are
+    /// stale).
+    pub fn load(path: &FilePath, key: &[u8; 32]) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let json = std::fs::read_to_string(path)?;
+        let (saved_key, results): ([u8; 32], Self) = serde_json::from_str(&json)
+            .with_context(|| format!("Couldn't parse saved scan results: {:?}", path))?;
+        Ok((&saved_key == key).then_some(results))
+    }
+
+    /// Re-scan a single file that changed since these results were produced.
+    ///
+    /// Everything that originated from `filepath` (effects, call edges and
+    /// the functions declared there) is dropped, then the file is scanned
+    /// again and merged back in. Pass `None` for the resolver to rescan in
+    /// quick mode. The crate-wide LoC counters are left as they are, since
+    /// they aren't tracked per file.
+    pub fn rescan_file(
+        &mut self,
+        crate_name: &str,
+        filepath: &FilePath,
+        resolver: Option<&Resolver>,
+        sinks: HashSet<IdentPath>,
+        relevant_effects: &[EffectType],
+        enabled_cfg: &HashMap<String, Vec<String>>,
+    ) -> Result<()> {
+        let mut file_results = ScanResults::new();
+        match resolver {
+            Some(resolver) => scan_file(
+                crate_name,
+                filepath,
+                resolver,
+                &mut file_results,
+                sinks,
+                enabled_cfg,
+            )?,
+            None => scan_file_quick(
+                crate_name,
+                filepath,
+                &mut file_results,
+                sinks,
+                enabled_cfg,
+            )?,
+        }
+
+        let fn_ptr_effects = file_results.fn_ptr_effects.clone();
+        file_results
+            .effects
+            .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+        file_results
+            .macro_def_effects
+            .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+        self.replace_file_results(filepath, file_results);
+        if resolver.is_none() {
+            self.resolve_quick_callees();
+        }
+
+        // Function pointer effects depend on the call graph, so check them
+        // against the merged results
+        for p in fn_ptr_effects {
+            if EffectType::matches_effect(relevant_effects, p.eff_type())
+                && keep_fn_ptr_effect(self, crate_name, &p)
+            {
+                self.fns_with_effects.insert(p.caller().clone());
+                self.effects.push(p);
+            }
+        }
+        self.dedup_effects();
+
+        Ok(())
+    }
+
+    /// Replace everything that originated from `filepath` (effects, call
+    /// edges and the functions declared there) with `file_results`, the
+    /// results of scanning just that file, e.g. with `scan_file`. Nodes of the
+    /// call graph that are no longer connected to anything are dropped. As in
+    /// `rescan_file`, the crate-wide LoC counters are left as they are.
+    pub fn replace_file_results(
+        &mut self,
+        filepath: &FilePath,
+        file_results: ScanResults,
+    ) {
+        self.remove_file(filepath);
+        self.combine_scan_results(ScanResults {
+            total_loc: LoCTracker::new(),
+            skipped_macros: LoCTracker::new(),
+            skipped_conditional_code: LoCTracker::new(),
+            skipped_fn_calls: LoCTracker::new(),
+            skipped_fn_ptrs: LoCTracker::new(),
+            skipped_other: LoCTracker::new(),
+            unsafe_traits: LoCTracker::new(),
+            unsafe_impls: LoCTracker::new(),
+            ..file_results
+        });
+        self.recount_fn_effects();
+    }
+
+    /// Remove everything that originated from the given file
+    fn remove_file(&mut self, filepath: &FilePath) {
+        let in_file = |loc: &SrcLoc| loc.dir().join(loc.file()) == filepath;
+
+        let mut removed_fns: HashSet<CanonicalPath> = self
+            .fn_locs
+            .iter()
+            .filter(|(_, loc)| in_file(loc))
+            .map(|(f, _)| f.clone())
+            .collect();
+        removed_fns.extend(
+            self.effects
+                .iter()
+                .filter(|e| in_file(e.call_loc()))
+                .map(|e| e.caller().clone()),
+        );
+
+        self.effects.retain(|e| !in_file(e.call_loc()));
+        self.fn_ptr_effects.retain(|e| !in_file(e.call_loc()));
+        self.macro_def_effects.retain(|e| !in_file(e.call_loc()));
+        self.fn_locs.retain(|_, loc| !in_file(loc));
+        self.pub_fns.retain(|f| !removed_fns.contains(f));
+        self.trait_meths.retain(|f| !removed_fns.contains(f));
+        self.unsafe_fns.retain(|f| !removed_fns.contains(f));
+        self.test_fns.retain(|f| !removed_fns.contains(f));
+        self.fns_with_effects.retain(|f| !removed_fns.contains(f));
+        self.fn_loc_tracker.retain(|f, _| !removed_fns.contains(f));
+        self.empty_unsafe_blocks.retain(|loc| !in_file(loc));
+        self.unsafe_impl_decls.retain(|i| !in_file(&i.src_loc));
+        self.no_std = self.no_std.take().filter(|loc| !in_file(loc));
+        self.alloc_uses.retain(|loc| !in_file(loc));
+
+        // Drop the file's edges, then any node left without edges that
+        // isn't a function declared elsewhere
+        self.call_graph.retain_edges(|g, e| !in_file(&g[e]));
+        self.prune_call_graph();
+    }
+
+    /// Drop the call graph nodes without edges that aren't declared
+    /// functions, and reindex the remaining nodes
+    fn prune_call_graph(&mut self) {
+        let fn_locs = &self.fn_locs;
+        let graph = &self.call_graph;
+        self.call_graph = graph.filter_map(
+            |n, f| {
+                let connected = graph.neighbors_undirected(n).next().is_some();
+                (connected || fn_locs.contains_key(f)).then(|| f.clone())
+            },
+            |_, loc| Some(loc.clone()),
+        );
+        self.node_idxs = self
+            .call_graph
+            .node_indices()
+            .map(|n| (self.call_graph[n].clone(), n))
+            .collect();
+    }
+
+    /// Best-effort fix-up of a call graph built in quick mode. Without name
+    /// resolution, callees are recorded as they are written (after looking up
+    /// `use` declarations), so a call to `helper()` from `my_crate::m::f` goes
+    /// to a `helper` node rather than to `my_crate::m::helper`, and the
+    /// caller is missing from `get_callers` of the function.
+    ///
+    /// Each call to a callee that isn't a declared function is redirected to
+    /// the first declared function found by looking up the callee path in the
+    /// modules enclosing the caller, innermost first. Leading `self` and
+    /// `super` segments are handled loosely, by the same lookup. This restores
+    /// the calls between functions of the crate, at the cost of some
+    /// precision: a call can be attributed to a function of the same name in
+    /// an enclosing module that isn't actually in scope, and calls through
+    /// traits, methods and glob imports stay unresolved.
+    fn resolve_quick_callees(&mut self) {
+        let moved = self
+            .call_graph
+            .edge_references()
+            .filter_map(|e| {
+                let callee = &self.call_graph[e.target()];
+                if self.fn_locs.contains_key(callee) {
+                    return None;
+                }
+                let caller = &self.call_graph[e.source()];
+                let f = self.lookup_local_fn(caller, callee)?;
+                Some((e.id(), caller.clone(), f, e.weight().clone()))
+            })
+            .collect::<Vec<_>>();
+
+        let moved_edges = moved.iter().map(|(e, ..)| *e).collect::<HashSet<_>>();
+        self.call_graph.retain_edges(|_, e| !moved_edges.contains(&e));
+        for (_, caller, callee, loc) in moved {
+            self.add_call(&caller, &callee, loc);
+        }
+    }
+
+    /// Find the declared function that `callee`, as written in `caller`,
+    /// most likely refers to (see `resolve_quick_callees`)
+    fn lookup_local_fn(
+        &self,
+        caller: &CanonicalPath,
+        callee: &CanonicalPath,
+    ) -> Option<CanonicalPath> {
+        let rel = match callee.crate_name().as_str() {
+            "crate" => {
+                let f = callee.with_crate(caller.crate_name().as_str());
+                return self.fn_locs.contains_key(&f).then_some(f);
+            }
+            "self" | "super" => callee.strip_crate_prefix(),
+            _ => callee.as_path().clone(),
+        };
+        let mut scope = caller.clone();
+        while scope.pop_ident().is_some() {
+            let mut f = scope.clone();
+            f.append_path(&rel);
+            if self.fn_locs.contains_key(&f) {
+                return Some(f);
+            }
+        }
+        None
+    }
+
     fn update_call_graph(&mut self, method: &CanonicalPath) -> NodeIndex {
         if let Some(node_idx) = self.node_idxs.get(method) {
             return node_idx.to_owned();
@@ -149,9 +818,42 @@ where
     /// Accessing a union field to assign to it is safe.
     scope_assign_lhs: bool,
 
+    /// Whether we are scanning the operand of a reference expression.
+    /// Taking a reference to a union field (`&mut u.f`) is unsafe,
+    /// even on the left-hand side of an assignment.
+    scope_reference: bool,
+
+    /// Whether the first argument of the call currently being scanned is a
+    /// string literal or some other expression (None if it has no arguments).
+    /// Set after scanning the arguments, right before the call is pushed.
+    call_program_arg: Option<ProgramArg>,
+
+    /// Number of nested test-only items (`#[test]`, `#[bench]`, or
+    /// `#[cfg(test)]`) we are currently in
+    scope_test: usize,
+
+    /// Number of nested transparent macro arguments (see
+    /// `expand_transparent_macros`) we are currently in
+    scope_macro_args: usize,
+
     /// Functions inside
     scope_fns: Vec<FnDec>,
 
+    /// The kind of each function in `scope_fns`
+    scope_fn_contexts: Vec<FnContext>,
+
+    /// Effects in the closures stored in each local collection, e.g. with
+    /// `v.push(Box::new(|| ...))`, keyed by the collection variable
+    stored_closure_effects: HashMap<CanonicalPath, Vec<EffectInstance>>,
+
+    /// Loop variables iterating over a collection of stored closures, mapped
+    /// to the collection
+    stored_closure_items: HashMap<CanonicalPath, CanonicalPath>,
+
+    /// Start positions of the closures in the current functions that escape
+    /// them (see `escaping_closures`)
+    escaping_closures: HashSet<(usize, usize)>,
+
     /// Target to accumulate scan results
     data: &'a mut ScanResults,
 
@@ -160,6 +862,13 @@ where
 
     /// The set of enabled cfg options for this crate.
     enabled_cfg: &'a HashMap<String, Vec<String>>,
+
+    /// Whether to keep the location of each skipped construct
+    record_skipped: bool,
+
+    /// Whether method calls through a generic type bounded by a trait (not
+    /// just through `dyn Trait`) get call edges to every impl of the method
+    virtual_dispatch: bool,
 }
 
 impl<'a, R> Scanner<'a, R>
@@ -183,10 +892,20 @@ where
             scope_unsafe: 0,
             scope_unsafe_effects: 0,
             scope_assign_lhs: false,
+            scope_reference: false,
+            call_program_arg: None,
+            scope_test: 0,
+            scope_macro_args: 0,
             scope_fns: Vec::new(),
+            scope_fn_contexts: Vec::new(),
+            stored_closure_effects: HashMap::new(),
+            stored_closure_items: HashMap::new(),
+            escaping_closures: HashSet::new(),
             data,
             sinks: Sink::default_sinks(),
             enabled_cfg,
+            record_skipped: false,
+            virtual_dispatch: false,
         }
     }
 
@@ -196,12 +915,32 @@ where
         debug_assert!(self.scope_fns.is_empty());
         debug_assert_eq!(self.scope_unsafe, 0);
         debug_assert_eq!(self.scope_unsafe_effects, 0);
+        debug_assert_eq!(self.scope_test, 0);
+        debug_assert_eq!(self.scope_macro_args, 0);
     }
 
     pub fn add_sinks(&mut self, new_sinks: HashSet<IdentPath>) {
         self.sinks.extend(new_sinks);
     }
 
+    /// Use the sinks and scanner settings of the scan options
+    pub fn set_options(&mut self, options: &ScanOptions) {
+        self.add_sinks(options.sinks.clone());
+        self.record_skipped = options.record_skipped;
+        self.virtual_dispatch = options.virtual_dispatch;
+    }
+
+    /// Count skipped code of the given kind, and keep its location if the
+    /// scan records skipped code
+    fn skip<S: Spanned>(&mut self, kind: SkippedKind, s: S) {
+        if self.record_skipped {
+            let loc = SrcLoc::from_span(self.filepath, &s);
+            self.data.skipped_mut(kind).add_at(s, loc);
+        } else {
+            self.data.skipped_mut(kind).add(s);
+        }
+    }
+
     /*
         Additional top-level items and modules
 
@@ -210,26 +949,63 @@ where
     */
 
     pub fn scan_file(&mut self, f: &'a syn::File) {
-        // track lines of code (LoC) at the file level
-        self.data.total_loc.add(f);
+        self.scan_file_attrs(f);
         // scan the file and return a list of all calls in it
         for i in &f.items {
             self.scan_item(i);
         }
     }
 
+    /// Scan a file like `scan_file`, but pass each effect to `on_effect`
+    /// instead of keeping it in the results. Effects are passed on as soon as
+    /// the top-level item they are in has been scanned, so memory use is
+    /// bounded by the largest item rather than the whole crate.
+    ///
+    /// Function pointer effects are still kept in the results, since whether
+    /// they are reported depends on the call graph of the whole crate.
+    pub fn scan_file_with(
+        &mut self,
+        f: &'a syn::File,
+        on_effect: &mut dyn FnMut(&EffectInstance),
+    ) {
+        self.scan_file_attrs(f);
+        for i in &f.items {
+            let start = self.data.effects.len();
+            self.scan_item(i);
+            for eff in self.data.effects.drain(start..) {
+                on_effect(&eff);
+            }
+        }
+    }
+
+    fn scan_file_attrs(&mut self, f: &'a syn::File) {
+        // track lines of code (LoC) at the file level
+        self.data.total_loc.add(f);
+        if let Some(attr) = f.attrs.iter().find(|a| is_no_std_attr(a)) {
+            self.data.no_std = Some(SrcLoc::from_span(self.filepath, attr));
+        }
+    }
+
     pub fn scan_item(&mut self, i: &'a syn::Item) {
         match i {
             syn::Item::Mod(m) => self.scan_mod(m),
             syn::Item::Use(u) => {
+                if matches!(&u.tree, syn::UseTree::Path(p) if p.ident == "alloc") {
+                    self.data.alloc_uses.push(SrcLoc::from_span(self.filepath, u));
+                }
                 self.resolver.scan_use(u);
             }
+            syn::Item::ExternCrate(x) => {
+                if x.ident == "alloc" {
+                    self.data.alloc_uses.push(SrcLoc::from_span(self.filepath, x));
+                }
+            }
             syn::Item::Impl(imp) => self.scan_impl(imp),
             syn::Item::Fn(fun) => self.scan_fn_decl(fun),
             syn::Item::Trait(t) => self.scan_trait(t),
             syn::Item::ForeignMod(fm) => self.scan_foreign_mod(fm),
             syn::Item::Macro(m) => {
-                self.data.skipped_macros.add(m);
+                self.skip(SkippedKind::Macro, m);
             }
             _ => (),
             // For all syntax elements see
@@ -268,6 +1044,31 @@ where
         attrs.iter().any(|x| self.skip_attr(x))
     }
 
+    // Return true if the attribute marks test-only code: a test harness
+    // attribute, or a cfg that only holds when `test` is enabled
+    pub fn is_test_attr(&self, attr: &'a syn::Attribute) -> bool {
+        let path = attr.path();
+        if path.segments.last().is_some_and(|s| s.ident == "test" || s.ident == "bench") {
+            return true;
+        }
+        if path.is_ident("cfg") {
+            let syn::Meta::List(l) = &attr.meta else { return false };
+            let cfg_pred = CfgPred::parse(&l.tokens);
+            let mut with_test = self.enabled_cfg.clone();
+            with_test.insert("test".to_string(), Vec::new());
+            let mut without_test = self.enabled_cfg.clone();
+            without_test.remove("test");
+            return cfg_pred.is_enabled(&with_test)
+                && !cfg_pred.is_enabled(&without_test);
+        }
+        false
+    }
+
+    // Return true if the attributes mark test-only code
+    pub fn is_test_attrs(&self, attrs: &'a [syn::Attribute]) -> bool {
+        attrs.iter().any(|x| self.is_test_attr(x))
+    }
+
     // pub fn scan_mod(&mut self, m: &'a syn::ItemMod) {
     //     if self.skip_attrs(&m.attrs) {
     //         self.data.skipped_conditional_code.add(m);
@@ -285,10 +1086,15 @@ where
 
     pub fn scan_mod(&mut self, m: &'a syn::ItemMod) {
         if self.skip_attrs(&m.attrs) {
-            self.data.skipped_conditional_code.add(m);
+            self.skip(SkippedKind::ConditionalCode, m);
             return;
         }
 
+        let is_test = self.is_test_attrs(&m.attrs);
+        if is_test {
+            self.scope_test += 1;
+        }
+
         if let Some((_, items)) = &m.content {
             self.resolver.push_mod(&m.ident);
             for i in items {
@@ -296,6 +1102,10 @@ where
             }
             self.resolver.pop_mod();
         }
+
+        if is_test {
+            self.scope_test -= 1;
+        }
     }
 
     /*
@@ -323,7 +1133,7 @@ where
 
     fn scan_foreign_mod(&mut self, fm: &'a syn::ItemForeignMod) {
         if self.skip_attrs(&fm.attrs) {
-            self.data.skipped_conditional_code.add(fm);
+            self.skip(SkippedKind::ConditionalCode, fm);
             return;
         }
 
@@ -336,10 +1146,10 @@ where
         match i {
             syn::ForeignItem::Fn(f) => self.scan_foreign_fn(f),
             syn::ForeignItem::Macro(m) => {
-                self.data.skipped_macros.add(m);
+                self.skip(SkippedKind::Macro, m);
             }
             other => {
-                self.data.skipped_other.add(other);
+                self.skip(SkippedKind::Other, other);
             }
         }
         // Ignored: Static, Type, Macro, Verbatim
@@ -348,7 +1158,7 @@ where
 
     fn scan_foreign_fn(&mut self, f: &'a ForeignItemFn) {
         if self.skip_attrs(&f.attrs) {
-            self.data.skipped_conditional_code.add(f);
+            self.skip(SkippedKind::ConditionalCode, f);
             return;
         }
 
@@ -383,7 +1193,7 @@ where
 
     fn scan_trait(&mut self, t: &'a syn::ItemTrait) {
         if self.skip_attrs(&t.attrs) {
-            self.data.skipped_conditional_code.add(t);
+            self.skip(SkippedKind::ConditionalCode, t);
             return;
         }
 
@@ -408,13 +1218,13 @@ where
                     self.scan_trait_method(m, &t.vis, impls_for_meth);
                 }
                 syn::TraitItem::Macro(m) => {
-                    self.data.skipped_macros.add(m);
+                    self.skip(SkippedKind::Macro, m);
                 }
                 syn::TraitItem::Verbatim(v) => {
                     self.syn_info("skipping Verbatim expression", v);
                 }
                 other => {
-                    self.data.skipped_other.add(other);
+                    self.skip(SkippedKind::Other, other);
                 }
             }
         }
@@ -422,56 +1232,80 @@ where
 
     fn scan_impl(&mut self, imp: &'a syn::ItemImpl) {
         if self.skip_attrs(&imp.attrs) {
-            self.data.skipped_conditional_code.add(imp);
+            self.skip(SkippedKind::ConditionalCode, imp);
             return;
         }
 
-        self.resolver.push_impl(imp);
-
-        if let Some((_, tr, _)) = &imp.trait_ {
-            self.scan_impl_trait_path(tr, imp);
+        let is_test = self.is_test_attrs(&imp.attrs);
+        if is_test {
+            self.scope_test += 1;
         }
 
+        // Resolve the trait and type before entering the impl scope
+        let ty = self.resolve_type_path(&imp.self_ty);
+        let ctx = match &imp.trait_ {
+            Some((_, tr, _)) => {
+                let trait_ = self.resolver.resolve_path(tr);
+                self.scan_impl_trait_path(tr, imp, &trait_, &ty);
+                FnContext::TraitImpl { trait_, ty }
+            }
+            None => FnContext::InherentImpl(ty),
+        };
+
+        self.resolver.push_impl(imp);
+
         for item in &imp.items {
             match item {
                 syn::ImplItem::Fn(m) => {
-                    self.scan_method(m);
+                    self.scan_method(m, &ctx);
                 }
                 syn::ImplItem::Macro(m) => {
-                    self.data.skipped_macros.add(m);
+                    self.skip(SkippedKind::Macro, m);
                 }
                 syn::ImplItem::Verbatim(v) => {
                     self.syn_info("skipping Verbatim expression", v);
                 }
                 other => {
-                    self.data.skipped_other.add(other);
+                    self.skip(SkippedKind::Other, other);
                 }
             }
         }
 
         self.resolver.pop_impl();
+
+        if is_test {
+            self.scope_test -= 1;
+        }
+    }
+
+    /// Path of the named type behind any references, e.g. `Foo` for the
+    /// implementing type of `impl Trait for &Foo`
+    fn resolve_type_path(&self, ty: &'a syn::Type) -> CanonicalPath {
+        match ty {
+            syn::Type::Path(p) => self.resolver.resolve_path(&p.path),
+            syn::Type::Reference(r) => self.resolve_type_path(&r.elem),
+            syn::Type::Paren(p) => self.resolve_type_path(&p.elem),
+            syn::Type::Group(g) => self.resolve_type_path(&g.elem),
+            _ => CanonicalPath::new("UNKNOWN_TYPE"),
+        }
     }
 
-    fn scan_impl_trait_path(&mut self, tr: &'a syn::Path, imp: &'a syn::ItemImpl) {
+    fn scan_impl_trait_path(
+        &mut self,
+        tr: &'a syn::Path,
+        imp: &'a syn::ItemImpl,
+        trait_path: &CanonicalPath,
+        self_ty: &CanonicalPath,
+    ) {
         if imp.unsafety.is_some() {
             // we found an `unsafe impl` declaration
-            // let tr_name = self.resolver.resolve_path(tr);
-            // let self_ty = imp
-            //     .self_ty
-            //     .to_token_stream()
-            //     .into_iter()
-            //     .filter_map(|token| match token {
-            //         TokenTree::Ident(i) => Some(i),
-            //         _ => None,
-            //     })
-            //     .last();
-            // // resolve the implementing type of the trait, if there is one
-            // let tr_type = match &self_ty {
-            //     Some(ident) => Some(self.resolver.resolve_ident(ident)),
-            //     _ => None,
-            // };
-
             self.data.unsafe_impls.add(tr);
+            self.data.unsafe_impl_decls.push(UnsafeImpl::new(
+                self.filepath,
+                imp,
+                trait_path.clone(),
+                self_ty.clone(),
+            ));
         }
     }
 
@@ -483,11 +1317,20 @@ where
         self.syn_debug("scanning function", f);
 
         if self.skip_attrs(&f.attrs) {
-            self.data.skipped_conditional_code.add(f);
+            self.skip(SkippedKind::ConditionalCode, f);
             return;
         }
 
-        self.scan_fn(&f.sig, &f.block, &f.vis);
+        let is_test = self.is_test_attrs(&f.attrs);
+        if is_test {
+            self.scope_test += 1;
+        }
+
+        self.scan_fn(&f.sig, &f.block, &f.vis, &f.attrs, FnContext::Free);
+
+        if is_test {
+            self.scope_test -= 1;
+        }
     }
 
     fn scan_trait_method(
@@ -497,7 +1340,7 @@ where
         impl_methods: Vec<&CanonicalPath>,
     ) {
         if self.skip_attrs(&m.attrs) {
-            self.data.skipped_conditional_code.add(m);
+            self.skip(SkippedKind::ConditionalCode, m);
             return;
         }
 
@@ -505,7 +1348,7 @@ where
         // Otherwise, just create a node in the call graph for the abstract trait method.
         let f_name = self.resolver.resolve_def(&m.sig.ident);
         if let Some(body) = &m.default {
-            self.scan_fn(&m.sig, body, vis);
+            self.scan_fn(&m.sig, body, vis, &m.attrs, FnContext::TraitDefault);
         } else {
             // Update call graph
             self.data.update_call_graph(&f_name);
@@ -522,14 +1365,23 @@ where
         }
     }
 
-    fn scan_method(&mut self, m: &'a syn::ImplItemFn) {
+    fn scan_method(&mut self, m: &'a syn::ImplItemFn, ctx: &FnContext) {
         if self.skip_attrs(&m.attrs) {
-            self.data.skipped_conditional_code.add(m);
+            self.skip(SkippedKind::ConditionalCode, m);
             return;
         }
 
+        let is_test = self.is_test_attrs(&m.attrs);
+        if is_test {
+            self.scope_test += 1;
+        }
+
         // NB: may or may not be a method, if there is no self keyword
-        self.scan_fn(&m.sig, &m.block, &m.vis);
+        self.scan_fn(&m.sig, &m.block, &m.vis, &m.attrs, ctx.clone());
+
+        if is_test {
+            self.scope_test -= 1;
+        }
     }
 
     fn scan_fn(
@@ -537,6 +1389,8 @@ where
         f_sig: &'a syn::Signature,
         body: &'a syn::Block,
         vis: &'a syn::Visibility,
+        attrs: &'a [syn::Attribute],
+        ctx: FnContext,
     ) {
         // Create fn decl
         let f_ident = &f_sig.ident;
@@ -551,14 +1405,22 @@ where
         // Always push the new function declaration before scanning the
         // body so we have access to the function its in
         self.scope_fns.push(fn_dec.clone());
+        self.scope_fn_contexts.push(ctx);
 
         // Notify resolver
         self.resolver.push_fn(f_ident);
 
         // Notify ScanResults
         self.data.add_fn_dec(fn_dec);
+        if self.scope_test > 0 {
+            self.data.test_fns.insert(f_name.clone());
+        }
 
-        // Update unsafety
+        // Update unsafety. The effects of a nested fn don't count for the
+        // unsafe block or fn around it.
+        let outer_unsafe_effects = std::mem::take(&mut self.scope_unsafe_effects);
+        // Neither does a reference the nested fn is in
+        let outer_reference = std::mem::take(&mut self.scope_reference);
         let f_unsafety: &Option<syn::token::Unsafe> = &f_sig.unsafety;
         if f_unsafety.is_some() {
             self.scope_unsafe += 1;
@@ -568,6 +1430,7 @@ where
             // end of the scan, if the pointer points to an
             // unsafe function
             self.data.fns_with_effects.insert(f_name.clone());
+            self.data.unsafe_fns.insert(f_name.clone());
         }
 
         // Similarly, we need to track local FFI declarations to
@@ -575,16 +1438,32 @@ where
         // end of the scan, if the pointer points to a foreign function
         if f_sig.abi.is_some() {
             self.data.fns_with_effects.insert(f_name.clone());
+
+            // A public or fixed-name `extern` fn can be called from foreign code
+            if matches!(vis, syn::Visibility::Public(_)) || is_exported_symbol(attrs) {
+                self.push_effect(
+                    f_sig,
+                    f_name.clone(),
+                    Effect::FFIExport(f_name.clone()),
+                );
+            }
         }
 
         // ***** Scan body *****
+        self.escaping_closures.extend(escaping_closures(body));
         for s in &body.stmts {
             self.scan_fn_statement(s);
         }
 
         // Reset state
         self.scope_fns.pop();
+        self.scope_fn_contexts.pop();
         self.resolver.pop_fn();
+        if self.scope_fns.is_empty() {
+            self.stored_closure_effects.clear();
+            self.stored_closure_items.clear();
+            self.escaping_closures.clear();
+        }
 
         // Reset unsafety
         if let Some(f_unsafety) = f_unsafety {
@@ -593,8 +1472,9 @@ where
             if self.scope_unsafe_effects == 0 {
                 self.syn_debug("unsafe block without any unsafe effects", f_unsafety)
             }
-            self.scope_unsafe_effects = 0;
         }
+        self.scope_unsafe_effects = outer_unsafe_effects;
+        self.scope_reference = outer_reference;
     }
 
     fn scan_fn_statement(&mut self, s: &'a syn::Stmt) {
@@ -603,7 +1483,7 @@ where
             syn::Stmt::Expr(e, _semi) => self.scan_expr(e),
             syn::Stmt::Item(i) => self.scan_item_in_fn(i),
             syn::Stmt::Macro(m) => {
-                self.data.skipped_macros.add(m);
+                self.skip(SkippedKind::Macro, m);
             }
         }
     }
@@ -615,7 +1495,7 @@ where
 
     fn scan_fn_local(&mut self, l: &'a syn::Local) {
         if self.skip_attrs(&l.attrs) {
-            self.data.skipped_conditional_code.add(l);
+            self.skip(SkippedKind::ConditionalCode, l);
             return;
         }
 
@@ -636,7 +1516,7 @@ where
         match e {
             syn::Expr::Array(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -646,7 +1526,7 @@ where
             }
             syn::Expr::Assign(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -657,7 +1537,7 @@ where
             }
             syn::Expr::Async(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -667,7 +1547,7 @@ where
             }
             syn::Expr::Await(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -675,7 +1555,7 @@ where
             }
             syn::Expr::Binary(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -684,7 +1564,7 @@ where
             }
             syn::Expr::Block(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -694,7 +1574,7 @@ where
             }
             syn::Expr::Break(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -704,7 +1584,7 @@ where
             }
             syn::Expr::Call(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
                 // ***** THE FIRST IMPORTANT CASE *****
@@ -715,7 +1595,7 @@ where
             }
             syn::Expr::Cast(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -734,7 +1614,7 @@ where
             }
             syn::Expr::Closure(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -747,7 +1627,7 @@ where
             syn::Expr::Continue(_) => (),
             syn::Expr::Field(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -756,18 +1636,19 @@ where
             }
             syn::Expr::ForLoop(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
                 self.scan_expr(&x.expr);
+                self.scan_stored_closure_loop(x);
                 for s in &x.body.stmts {
                     self.scan_fn_statement(s);
                 }
             }
             syn::Expr::Group(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -775,7 +1656,7 @@ where
             }
             syn::Expr::If(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -789,7 +1670,7 @@ where
             }
             syn::Expr::Index(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -798,7 +1679,7 @@ where
             }
             syn::Expr::Let(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -807,7 +1688,7 @@ where
             syn::Expr::Lit(_) => (),
             syn::Expr::Loop(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -816,18 +1697,18 @@ where
                 }
             }
             syn::Expr::Macro(m) => {
-                self.data.skipped_macros.add(m);
+                self.skip(SkippedKind::Macro, m);
             }
             syn::Expr::Match(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
                 self.scan_expr(&x.expr);
                 for a in &x.arms {
                     if self.skip_attrs(&a.attrs) {
-                        self.data.skipped_conditional_code.add(a);
+                        self.skip(SkippedKind::ConditionalCode, a);
                         return;
                     }
 
@@ -839,7 +1720,7 @@ where
             }
             syn::Expr::MethodCall(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -847,13 +1728,22 @@ where
                 // Receiver object
                 self.scan_expr(&x.receiver);
                 // Arguments
+                let effects_num = self.data.effects.len();
                 self.scan_expr_call_args(&x.args);
+                self.scan_stored_closures(x, effects_num);
                 // Function call
-                self.scan_expr_call_method(&x.method);
+                if self.is_ptr_offset(x) {
+                    self.push_ptr_offset(&x.method);
+                } else {
+                    self.scan_expr_call_method(&x.method);
+                }
+                if x.method == "as_ptr" {
+                    self.scan_cstr_ptr(x);
+                }
             }
             syn::Expr::Paren(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -861,7 +1751,7 @@ where
             }
             syn::Expr::Path(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -870,7 +1760,7 @@ where
             }
             syn::Expr::Range(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -883,15 +1773,18 @@ where
             }
             syn::Expr::Reference(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
+                let prev_reference = self.scope_reference;
+                self.scope_reference = true;
                 self.scan_expr(&x.expr);
+                self.scope_reference = prev_reference;
             }
             syn::Expr::Repeat(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -900,7 +1793,7 @@ where
             }
             syn::Expr::Return(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -910,13 +1803,13 @@ where
             }
             syn::Expr::Struct(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
                 for y in x.fields.iter() {
                     if self.skip_attrs(&y.attrs) {
-                        self.data.skipped_conditional_code.add(y);
+                        self.skip(SkippedKind::ConditionalCode, y);
                         return;
                     }
 
@@ -928,7 +1821,7 @@ where
             }
             syn::Expr::Try(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -936,7 +1829,7 @@ where
             }
             syn::Expr::TryBlock(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -947,17 +1840,25 @@ where
             }
             syn::Expr::Tuple(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
+                let is_macro_args =
+                    x.attrs.iter().any(|a| a.path().is_ident(MACRO_ARGS_ATTR));
+                if is_macro_args {
+                    self.scope_macro_args += 1;
+                }
                 for y in x.elems.iter() {
                     self.scan_expr(y);
                 }
+                if is_macro_args {
+                    self.scope_macro_args -= 1;
+                }
             }
             syn::Expr::Unary(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -968,7 +1869,7 @@ where
             }
             syn::Expr::Unsafe(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -980,7 +1881,7 @@ where
             }
             syn::Expr::While(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -991,7 +1892,7 @@ where
             }
             syn::Expr::Yield(x) => {
                 if self.skip_attrs(&x.attrs) {
-                    self.data.skipped_conditional_code.add(x);
+                    self.skip(SkippedKind::ConditionalCode, x);
                     return;
                 }
 
@@ -1002,7 +1903,7 @@ where
             }
             syn::Expr::Const(c) => {
                 if self.skip_attrs(&c.attrs) {
-                    self.data.skipped_conditional_code.add(c);
+                    self.skip(SkippedKind::ConditionalCode, c);
                     return;
                 }
 
@@ -1021,13 +1922,21 @@ where
         }
     }
 
+    /// Record a path into the `alloc` crate, e.g. `alloc::vec::Vec::new`
+    fn scan_alloc_path(&mut self, x: &'a syn::Path) {
+        if x.segments.first().is_some_and(|s| s.ident == "alloc") {
+            self.data.alloc_uses.push(SrcLoc::from_span(self.filepath, x));
+        }
+    }
+
     fn scan_path(&mut self, x: &'a syn::Path) {
+        self.scan_alloc_path(x);
         let ty = self.resolver.resolve_path_type(x);
         if ty.is_function() {
             // Skip constant or immutable static function pointers
             if self.resolver.resolve_const_or_static(x) {
                 self.syn_info("Skipping const or static item", x);
-                self.data.skipped_fn_ptrs.add(x.span());
+                self.skip(SkippedKind::FnPtr, x.span());
             } else {
                 let cp = self.resolver.resolve_path(x);
                 self.push_effect(x.span(), cp, Effect::FnPtrCreation);
@@ -1054,33 +1963,88 @@ where
         // Scan closure's body first. If it does not contain
         // any effects, the closure is not dangerous and
         // we do not create a new effect instance for it.
+        // A reference to the closure isn't a reference to its body.
+        let outer_reference = std::mem::take(&mut self.scope_reference);
         self.scan_expr(&x.body);
+        self.scope_reference = outer_reference;
         if self.data.effects.len() > effects_num {
             let cl_name = self.resolver.resolve_closure(x);
             self.push_effect(x.span(), cl_name, Effect::ClosureCreation);
+            let escapes = self.escaping_closures.contains(&span_start(x));
+            if let Some(eff) = self.data.effects.last_mut() {
+                eff.set_closure_escapes(escapes);
+            }
         }
     }
 
+    /// Find the identifier whose resolved type is the type of the expression,
+    /// if there is one: a variable, a field, or a called function or method
+    /// (whose type resolves to its return type)
+    fn expr_type_ident(x: &syn::Expr) -> Option<&syn::Ident> {
+        match x {
+            syn::Expr::Path(p) => p.path.segments.last().map(|s| &s.ident),
+            syn::Expr::Field(f) => match &f.member {
+                syn::Member::Named(i) => Some(i),
+                syn::Member::Unnamed(_) => None,
+            },
+            syn::Expr::Call(c) => match &*c.func {
+                syn::Expr::Path(p) => p.path.segments.last().map(|s| &s.ident),
+                _ => None,
+            },
+            syn::Expr::MethodCall(m) => Some(&m.method),
+            syn::Expr::Paren(p) => Self::expr_type_ident(&p.expr),
+            syn::Expr::Group(g) => Self::expr_type_ident(&g.expr),
+            _ => None,
+        }
+    }
+
+    /// Push a `RawPointer` effect if the dereferenced expression is a raw
+    /// pointer. Other identifiers in the expression (e.g. raw pointer
+    /// arguments to a function returning a reference) don't count.
     fn scan_deref(&mut self, x: &'a syn::Expr) {
-        let mut tokens: TokenStream = TokenStream::new();
-        x.to_tokens(&mut tokens);
-        tokens.into_iter().for_each(|tt| {
-            if let TokenTree::Ident(i) = tt {
-                let ty = self.resolver.resolve_field_type(&i);
-                let p = self.resolver.resolve_field(&i);
-                if ty.is_raw_ptr() {
-                    // NOTE: Can only be done in an unsafe block
-                    self.push_effect(x.span(), p.clone(), Effect::RawPointer(p));
-                }
+        let (ident, is_raw_ptr) = match x {
+            // The cast type is known without resolving anything, and the
+            // pointer may not have a name, as in `*(0x1000 as *const u8)`, in
+            // which case the effect is named after the pointee type
+            syn::Expr::Cast(c) => {
+                let syn::Type::Ptr(ptr) = &*c.ty else { return };
+                let p = match Self::expr_type_ident(&c.expr) {
+                    Some(i) => self.resolver.resolve_field(i),
+                    None => self.resolve_type_path(&ptr.elem),
+                };
+                // NOTE: Can only be done in an unsafe block
+                self.push_effect(x.span(), p.clone(), Effect::RawPointer(p));
+                return;
             }
-        });
+            syn::Expr::Paren(p) => return self.scan_deref(&p.expr),
+            _ => {
+                let ident = Self::expr_type_ident(x);
+                (
+                    ident,
+                    ident.is_some_and(|i| {
+                        self.resolver.resolve_field_type(i).is_raw_ptr()
+                    }),
+                )
+            }
+        };
+        if let (Some(i), true) = (ident, is_raw_ptr) {
+            let p = self.resolver.resolve_field(i);
+            // NOTE: Can only be done in an unsafe block
+            self.push_effect(x.span(), p.clone(), Effect::RawPointer(p));
+        }
     }
 
-    // Check if the field being accessed is a Union field
+    // Check if the field being accessed is a Union field, or a field of a
+    // packed struct that is being referenced
     fn scan_field_access(&mut self, x: &'a syn::ExprField) {
         if let syn::Member::Named(i) = &x.member {
             let ty = self.resolver.resolve_field_type(i);
-            if !ty.is_union_field() || self.scope_assign_lhs {
+            if ty.is_packed_field() && self.scope_reference {
+                let cp = self.resolver.resolve_field(i);
+                self.push_effect(x.span(), cp.clone(), Effect::PackedFieldAccess(cp));
+                return;
+            }
+            if !ty.is_union_field() || (self.scope_assign_lhs && !self.scope_reference) {
                 return;
             }
             let cp = self.resolver.resolve_field(i);
@@ -1090,6 +2054,7 @@ where
     }
 
     fn scan_unsafe_block(&mut self, x: &'a syn::ExprUnsafe) {
+        let outer_unsafe_effects = std::mem::take(&mut self.scope_unsafe_effects);
         self.scope_unsafe += 1;
         for s in &x.block.stmts {
             self.scan_fn_statement(s);
@@ -1099,9 +2064,15 @@ where
         debug_assert!(self.scope_unsafe >= 1);
         self.scope_unsafe -= 1;
         if self.scope_unsafe_effects == 0 {
-            self.syn_debug("unsafe block without any unsafe effects", x)
+            self.syn_debug("unsafe block without any unsafe effects", x);
+            self.data.empty_unsafe_blocks.push(SrcLoc::from_span(self.filepath, x));
         }
-        self.scope_unsafe_effects = 0;
+        // The effects in the block also count for an enclosing unsafe block
+        self.scope_unsafe_effects = if self.scope_unsafe > 0 {
+            outer_unsafe_effects + self.scope_unsafe_effects
+        } else {
+            0
+        };
     }
 
     /*
@@ -1114,6 +2085,27 @@ where
         for y in a.iter() {
             self.scan_expr(y);
         }
+        self.call_program_arg = a.first().map(|y| match strip_parens(y) {
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(_), .. }) => {
+                ProgramArg::Literal
+            }
+            _ => ProgramArg::Dynamic,
+        });
+    }
+
+    /// The kind of function currently being scanned
+    fn fn_context(&self) -> FnContext {
+        self.scope_fn_contexts.last().cloned().unwrap_or_default()
+    }
+
+    /// How effects in the current scope are found: in the arguments of a
+    /// transparent macro, or directly
+    fn found_via(&self) -> EffectProvenance {
+        if self.scope_macro_args > 0 {
+            EffectProvenance::MacroExpansion
+        } else {
+            EffectProvenance::Direct
+        }
     }
 
     /// Push an effect into the current `EffectBlock`. Should be used when
@@ -1130,13 +2122,16 @@ where
             &containing_fn.fn_name
         };
 
-        let eff = EffectInstance::new_effect(
+        let mut eff = EffectInstance::new_effect(
             self.filepath,
             caller.clone(),
             callee.clone(),
             &eff_span,
             eff_type.clone(),
         );
+        eff.set_in_test(self.scope_test > 0);
+        eff.set_found_via(self.found_via());
+        eff.set_fn_context(self.fn_context());
 
         if self.scope_unsafe > 0 && eff.is_rust_unsafe() {
             self.scope_unsafe_effects += 1;
@@ -1170,8 +2165,9 @@ where
             &callee,
             SrcLoc::from_span(self.filepath, &callee_span.span()),
         );
+        let program_arg = self.call_program_arg.take();
 
-        let Some(eff) = EffectInstance::new_call(
+        let effs = EffectInstance::new_call(
             self.filepath,
             caller.clone(),
             callee,
@@ -1179,25 +2175,87 @@ where
             is_unsafe,
             ffi,
             &self.sinks,
-        ) else {
+        );
+        if effs.is_empty() {
             return;
-        };
+        }
 
-        if self.scope_unsafe > 0 && eff.is_rust_unsafe() {
+        // Calls to unsafe functions that are reported as another effect, e.g.
+        // a sink, still need the unsafe block
+        if self.scope_unsafe > 0 && (is_unsafe || effs.iter().any(|e| e.is_rust_unsafe()))
+        {
             self.scope_unsafe_effects += 1;
         }
-        self.data.effects.push(eff);
+        for mut eff in effs {
+            eff.set_in_test(self.scope_test > 0);
+            eff.set_found_via(self.found_via());
+            eff.set_fn_context(self.fn_context());
+            if let Some(program_arg) = program_arg.filter(|_| runs_program(eff.callee()))
+            {
+                eff.set_program_arg(program_arg);
+            }
+            self.data.effects.push(eff);
+        }
         self.data.fns_with_effects.insert(caller.clone());
     }
 
+    /// push a Transmute effect for a call to `mem::transmute`
+    fn push_transmute<S>(&mut self, callee_span: S, callee: CanonicalPath)
+    where
+        S: Debug + Spanned,
+    {
+        let containing_fn = self.scope_fns.last().expect("not inside a function!");
+        let caller = containing_fn.fn_name.clone();
+        self.data.add_call(
+            &caller,
+            &callee,
+            SrcLoc::from_span(self.filepath, &callee_span.span()),
+        );
+        self.push_effect(callee_span, callee.clone(), Effect::Transmute(callee));
+    }
+
+    /// Check if a method call offsets a raw pointer, e.g. `p.add(1)`. The
+    /// receiver's type is only known in full mode, apart from casts.
+    fn is_ptr_offset(&self, x: &'a syn::ExprMethodCall) -> bool {
+        if !PTR_OFFSET_METHODS.iter().any(|m| x.method == m) {
+            return false;
+        }
+        let callee = self.resolver.resolve_method(&x.method);
+        if RAW_PTR_IMPL_MODS.iter().any(|m| callee.as_str().starts_with(m)) {
+            return true;
+        }
+        match strip_parens(&x.receiver) {
+            syn::Expr::Cast(c) => matches!(*c.ty, syn::Type::Ptr(_)),
+            r => Self::expr_type_ident(r)
+                .is_some_and(|i| self.resolver.resolve_field_type(i).is_raw_ptr()),
+        }
+    }
+
+    /// push a PtrOffset effect for a raw pointer offset method call
+    fn push_ptr_offset(&mut self, method: &'a syn::Ident) {
+        let callee = self.resolver.resolve_method(method);
+        let containing_fn = self.scope_fns.last().expect("not inside a function!");
+        let caller = containing_fn.fn_name.clone();
+        self.data.add_call(&caller, &callee, SrcLoc::from_span(self.filepath, method));
+        self.push_effect(method, callee.clone(), Effect::PtrOffset(callee));
+    }
+
     // f in a call of the form (f)(args)
     fn scan_expr_call(&mut self, f: &'a syn::Expr) {
         match f {
             syn::Expr::Path(p) => {
+                self.scan_alloc_path(&p.path);
                 let callee = self.resolver.resolve_path(&p.path);
+                if TRANSMUTE_PATHS.contains(&callee.as_str()) {
+                    self.push_transmute(p, callee);
+                    return;
+                }
                 let ffi = self.resolver.resolve_ffi(&p.path);
                 let is_unsafe =
                     self.resolver.resolve_unsafe_path(&p.path) && self.scope_unsafe > 0;
+                if let Some(coll) = self.stored_closure_items.get(&callee).cloned() {
+                    self.push_stored_closure_effects(p, &coll);
+                }
                 self.push_callsite(p, callee, ffi, is_unsafe);
             }
             syn::Expr::Paren(x) => {
@@ -1210,24 +2268,107 @@ where
                 self.scan_expr_call_field(&x.member)
             }
             syn::Expr::Macro(m) => {
-                self.data.skipped_macros.add(m);
+                self.skip(SkippedKind::Macro, m);
             }
             other => {
                 // anything else could be a function, too -- could return a closure
                 // or fn pointer. No way to tell w/o type information.
                 self.syn_info("Skipped function call", other);
-                self.data.skipped_fn_calls.add(other);
+                self.skip(SkippedKind::FnCall, other);
             }
         }
     }
 
-    fn scan_expr_call_field(&mut self, m: &'a syn::Member) {
-        match m {
-            syn::Member::Named(i) => {
-                let is_unsafe =
-                    self.resolver.resolve_unsafe_ident(i) && self.scope_unsafe > 0;
-                self.push_callsite(i, self.resolver.resolve_field(i), None, is_unsafe);
-            }
+    /// Record the effects of closures added to a local collection, e.g. with
+    /// `v.push(Box::new(|| ...))`, so that they can be attributed to the
+    /// places where the stored closures are called. The effects are the ones
+    /// found in the arguments since `effects_num`.
+    fn scan_stored_closures(&mut self, x: &'a syn::ExprMethodCall, effects_num: usize) {
+        if !COLLECTION_ADD_METHODS.iter().any(|m| x.method == m)
+            || !x.args.iter().any(is_stored_closure)
+        {
+            return;
+        }
+        let Some(coll) = local_var_path(&x.receiver) else {
+            return;
+        };
+        let coll = self.resolver.resolve_path(coll);
+        let effects = self.data.effects[effects_num..]
+            .iter()
+            .filter(|e| !matches!(e.eff_type(), Effect::ClosureCreation))
+            .cloned();
+        self.stored_closure_effects.entry(coll).or_default().extend(effects);
+    }
+
+    /// Track the variable of a `for` loop over a collection of stored
+    /// closures, e.g. `item` in `for item in &v`
+    fn scan_stored_closure_loop(&mut self, x: &'a syn::ExprForLoop) {
+        let (syn::Pat::Ident(pat), Some(coll)) = (&*x.pat, local_var_path(&x.expr))
+        else {
+            return;
+        };
+        let coll = self.resolver.resolve_path(coll);
+        if self.stored_closure_effects.contains_key(&coll) {
+            let item = self.resolver.resolve_ident(&pat.ident);
+            self.stored_closure_items.insert(item, coll);
+        }
+    }
+
+    /// Push the effects of the closures stored in `coll` at a call to one of
+    /// them
+    fn push_stored_closure_effects(
+        &mut self,
+        callsite: &'a syn::ExprPath,
+        coll: &CanonicalPath,
+    ) {
+        let Some(stored) = self.stored_closure_effects.get(coll).cloned() else {
+            return;
+        };
+        let containing_fn = self.scope_fns.last().expect("not inside a function!");
+        let caller = containing_fn.fn_name.clone();
+        for stored_eff in stored {
+            let mut eff = EffectInstance::new_effect(
+                self.filepath,
+                caller.clone(),
+                stored_eff.callee().clone(),
+                callsite,
+                stored_eff.eff_type().clone(),
+            );
+            eff.set_in_test(self.scope_test > 0);
+            eff.set_found_via(self.found_via());
+            eff.set_fn_context(self.fn_context());
+            self.data.effects.push(eff);
+        }
+        self.data.fns_with_effects.insert(caller);
+    }
+
+    /// Check if an `as_ptr` call gets a pointer to a C string
+    fn scan_cstr_ptr(&mut self, x: &'a syn::ExprMethodCall) {
+        let callee = self.resolver.resolve_method(&x.method);
+        if !CSTR_AS_PTR_PATHS.contains(&callee.as_str()) && !is_cstr_expr(&x.receiver) {
+            return;
+        }
+
+        // Heuristic: a receiver that is the result of a call is a temporary
+        // that will be dropped at the end of the statement
+        let on_temporary = matches!(
+            strip_parens(&x.receiver),
+            syn::Expr::Call(_) | syn::Expr::MethodCall(_) | syn::Expr::Try(_)
+        );
+        self.push_effect(
+            &x.method,
+            callee.clone(),
+            Effect::CStrPtr { callee, on_temporary },
+        );
+    }
+
+    fn scan_expr_call_field(&mut self, m: &'a syn::Member) {
+        match m {
+            syn::Member::Named(i) => {
+                let is_unsafe =
+                    self.resolver.resolve_unsafe_ident(i) && self.scope_unsafe > 0;
+                self.push_callsite(i, self.resolver.resolve_field(i), None, is_unsafe);
+            }
             syn::Member::Unnamed(idx) => {
                 self.push_callsite(
                     idx,
@@ -1242,9 +2383,398 @@ where
     fn scan_expr_call_method(&mut self, i: &'a syn::Ident) {
         let is_unsafe = self.resolver.resolve_unsafe_ident(i) && self.scope_unsafe > 0;
         self.push_callsite(i, self.resolver.resolve_method(i), None, is_unsafe);
+
+        // A call on a `dyn Trait` object could dispatch to any of the impls,
+        // so conservatively add an edge to each of them. With virtual
+        // dispatch, do the same for any call to a trait method, e.g. on a
+        // generic type bounded by the trait.
+        let containing_fn = self.scope_fns.last().expect("not inside a function!");
+        let caller = containing_fn.fn_name.clone();
+        let impl_meths = if self.virtual_dispatch {
+            self.resolver.resolve_virtual_impl_methods(i)
+        } else {
+            self.resolver.resolve_dyn_impl_methods(i)
+        };
+        for impl_meth in impl_meths {
+            self.data.add_call(&caller, &impl_meth, SrcLoc::from_span(self.filepath, i));
+        }
     }
 }
 
+fn strip_parens(e: &syn::Expr) -> &syn::Expr {
+    match e {
+        syn::Expr::Paren(x) => strip_parens(&x.expr),
+        _ => e,
+    }
+}
+
+/// Syntactic check for whether an expression builds a C string, e.g.
+/// `CString::new(s).unwrap()`. Used when the method can't be resolved.
+fn is_cstr_expr(e: &syn::Expr) -> bool {
+    match strip_parens(e) {
+        syn::Expr::Call(x) => match strip_parens(&x.func) {
+            syn::Expr::Path(p) => {
+                p.path.segments.iter().any(|s| s.ident == "CString" || s.ident == "CStr")
+            }
+            _ => false,
+        },
+        syn::Expr::MethodCall(x) => is_cstr_expr(&x.receiver),
+        syn::Expr::Try(x) => is_cstr_expr(&x.expr),
+        _ => false,
+    }
+}
+
+/// Return true for a `#![no_std]` attribute, or a `#![cfg_attr(...)]` that
+/// makes the crate `no_std` under some configuration
+fn is_no_std_attr(attr: &syn::Attribute) -> bool {
+    if !matches!(attr.style, syn::AttrStyle::Inner(_)) {
+        return false;
+    }
+    match &attr.meta {
+        syn::Meta::Path(p) => p.is_ident("no_std"),
+        syn::Meta::List(l) if l.path.is_ident("cfg_attr") => l
+            .tokens
+            .clone()
+            .into_iter()
+            .any(|tt| matches!(tt, TokenTree::Ident(i) if i == "no_std")),
+        _ => false,
+    }
+}
+
+/// The local variable an expression refers to, if any, looking through
+/// references and iterator adapters, e.g. `v` in `&mut v` or `v.iter()`
+fn local_var_path(e: &syn::Expr) -> Option<&syn::Path> {
+    match strip_parens(e) {
+        syn::Expr::Path(p) if p.path.segments.len() == 1 => Some(&p.path),
+        syn::Expr::Reference(r) => local_var_path(&r.expr),
+        syn::Expr::MethodCall(m)
+            if m.method == "iter"
+                || m.method == "iter_mut"
+                || m.method == "into_iter" =>
+        {
+            local_var_path(&m.receiver)
+        }
+        _ => None,
+    }
+}
+
+/// The value an expression stores, looking through parentheses and
+/// wrappers like `Box::new(...)`
+fn stored_value(e: &syn::Expr) -> &syn::Expr {
+    match strip_parens(e) {
+        syn::Expr::Call(x) if x.args.len() == 1 => match strip_parens(&x.func) {
+            syn::Expr::Path(p)
+                if p.path.segments.last().is_some_and(|s| s.ident == "new") =>
+            {
+                stored_value(&x.args[0])
+            }
+            _ => strip_parens(e),
+        },
+        e => e,
+    }
+}
+
+/// Syntactic check for a closure that is being stored, possibly boxed, e.g.
+/// `Box::new(|| ...)`
+fn is_stored_closure(e: &syn::Expr) -> bool {
+    matches!(stored_value(e), syn::Expr::Closure(_))
+}
+
+/// Start position of a syntax node, as (line, column)
+fn span_start<S: Spanned>(s: &S) -> (usize, usize) {
+    let start = s.span().start();
+    (start.line, start.column)
+}
+
+/// Collects the expressions in a function body whose values escape the
+/// function, and the closures bound to local variables
+#[derive(Default)]
+struct EscapeVisitor<'a> {
+    escaping: Vec<&'a syn::Expr>,
+    local_closures: Vec<(&'a syn::Ident, &'a syn::ExprClosure)>,
+}
+
+impl<'a> Visit<'a> for EscapeVisitor<'a> {
+    fn visit_expr_return(&mut self, x: &'a syn::ExprReturn) {
+        if let Some(e) = &x.expr {
+            self.escaping.push(e);
+        }
+        visit::visit_expr_return(self, x);
+    }
+
+    fn visit_field_value(&mut self, x: &'a syn::FieldValue) {
+        self.escaping.push(&x.expr);
+        visit::visit_field_value(self, x);
+    }
+
+    fn visit_expr_assign(&mut self, x: &'a syn::ExprAssign) {
+        if matches!(strip_parens(&x.left), syn::Expr::Field(_)) {
+            self.escaping.push(&x.right);
+        }
+        visit::visit_expr_assign(self, x);
+    }
+
+    fn visit_local(&mut self, x: &'a syn::Local) {
+        let pat = match &x.pat {
+            syn::Pat::Type(t) => &*t.pat,
+            pat => pat,
+        };
+        if let (syn::Pat::Ident(p), Some(init)) = (pat, &x.init) {
+            if let syn::Expr::Closure(c) = stored_value(&init.expr) {
+                self.local_closures.push((&p.ident, c));
+            }
+        }
+        visit::visit_local(self, x);
+    }
+
+    // Nested items are scanned as functions of their own
+    fn visit_item(&mut self, _: &'a syn::Item) {}
+}
+
+/// Whether the attributes give a function a fixed symbol name, with
+/// `#[no_mangle]` or `#[export_name = "..."]` (or the `#[unsafe(...)]`
+/// forms of these)
+fn is_exported_symbol(attrs: &[syn::Attribute]) -> bool {
+    let is_export = |i: &syn::Ident| i == "no_mangle" || i == "export_name";
+    attrs.iter().any(|a| match &a.meta {
+        syn::Meta::List(l) if l.path.is_ident("unsafe") => l
+            .tokens
+            .clone()
+            .into_iter()
+            .any(|t| matches!(&t, TokenTree::Ident(i) if is_export(i))),
+        m => m.path().get_ident().is_some_and(is_export),
+    })
+}
+
+/// Heuristically find the closures in a function body that escape the
+/// function, rather than being called locally: those that are returned or
+/// stored in a struct field, either directly or through a local variable
+/// (e.g. `let f = || ...; Handler { f }`). Returns their start positions.
+fn escaping_closures(body: &syn::Block) -> HashSet<(usize, usize)> {
+    let mut visitor = EscapeVisitor::default();
+    visitor.visit_block(body);
+    if let Some(syn::Stmt::Expr(tail, None)) = body.stmts.last() {
+        visitor.escaping.push(tail);
+    }
+
+    let escaping_vars = visitor
+        .escaping
+        .iter()
+        .filter_map(|e| local_var_path(stored_value(e))?.get_ident())
+        .collect::<HashSet<_>>();
+    let direct = visitor.escaping.iter().filter_map(|e| match stored_value(e) {
+        syn::Expr::Closure(c) => Some(c),
+        _ => None,
+    });
+    let via_locals = visitor
+        .local_closures
+        .iter()
+        .filter(|(i, _)| escaping_vars.contains(i))
+        .map(|(_, c)| *c);
+
+    direct.chain(via_locals).map(span_start).collect()
+}
+
+fn is_repetition_op(tt: &TokenTree) -> bool {
+    matches!(tt, TokenTree::Punct(p) if matches!(p.as_char(), '*' | '+' | '?'))
+}
+
+/// Replace the metavariables in the body of a `macro_rules!` arm with plain
+/// identifiers (`$x` becomes `x`), and repetitions (`$(...),*`) with a single
+/// copy of their contents, so that the body can be parsed as regular code
+fn strip_macro_metavars(body: TokenStream) -> TokenStream {
+    let mut out = Vec::new();
+    let mut tokens = body.into_iter().peekable();
+    while let Some(tt) = tokens.next() {
+        match tt {
+            TokenTree::Punct(p) if p.as_char() == '$' => match tokens.next() {
+                Some(TokenTree::Group(g)) => {
+                    out.extend(strip_macro_metavars(g.stream()));
+                    // Skip the separator, if any, and the repetition operator
+                    if tokens.peek().is_some_and(|tt| !is_repetition_op(tt)) {
+                        tokens.next();
+                    }
+                    if tokens.peek().is_some_and(is_repetition_op) {
+                        tokens.next();
+                    }
+                }
+                Some(other) => out.push(other),
+                None => (),
+            },
+            TokenTree::Group(g) => {
+                let mut stripped = proc_macro2::Group::new(
+                    g.delimiter(),
+                    strip_macro_metavars(g.stream()),
+                );
+                stripped.set_span(g.span());
+                out.push(TokenTree::Group(stripped));
+            }
+            other => out.push(other),
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Get the code templates of the `macro_rules!` definitions in the items,
+/// as functions named after the macro with one function per arm, so they can
+/// be scanned like any other code. The module structure and `use`
+/// declarations are kept so that paths in the templates resolve as in the
+/// macro definition. Arms that don't parse as statements are skipped.
+fn macro_templates(items: &[syn::Item]) -> Vec<syn::Item> {
+    let mut templates = Vec::new();
+    for item in items {
+        match item {
+            syn::Item::Use(u) => templates.push(syn::Item::Use(u.clone())),
+            syn::Item::Mod(m) => {
+                let Some((_, mod_items)) = &m.content else { continue };
+                let mod_templates = macro_templates(mod_items);
+                if mod_templates
+                    .iter()
+                    .any(|i| matches!(i, syn::Item::Fn(_) | syn::Item::Mod(_)))
+                {
+                    let mut m = m.clone();
+                    m.content = Some((syn::token::Brace::default(), mod_templates));
+                    templates.push(syn::Item::Mod(m));
+                }
+            }
+            syn::Item::Macro(m) if m.mac.path.is_ident("macro_rules") => {
+                let Some(name) = &m.ident else { continue };
+                // Arms are `(matcher) => { body }`, separated by semicolons
+                let arm_bodies =
+                    m.mac.tokens.clone().into_iter().filter_map(|tt| match tt {
+                        TokenTree::Group(g) => Some(g),
+                        _ => None,
+                    });
+                for body in arm_bodies.skip(1).step_by(2) {
+                    let body = strip_macro_metavars(body.stream());
+                    match syn::parse2::<syn::ItemFn>(
+                        quote::quote! { fn #name() { #body } },
+                    ) {
+                        Ok(mut f) => {
+                            TransparentMacros.visit_item_fn_mut(&mut f);
+                            templates.push(syn::Item::Fn(f))
+                        }
+                        Err(err) => {
+                            debug!("Could not parse arm of macro {} ({})", name, err)
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    templates
+}
+
+/// Parse the arguments of an invocation of one of the `TRANSPARENT_MACROS`,
+/// e.g. `a, b` or `elem; len` (as in `vec!`). The macro may be invoked by
+/// path, as in `ptr::addr_of!`. Returns None for other macros and for
+/// arguments that aren't plain expressions.
+fn transparent_macro_args(
+    mac: &syn::Macro,
+) -> Option<Punctuated<syn::Expr, syn::Token![,]>> {
+    let name = mac.path.segments.last()?.ident.to_string();
+    if !TRANSPARENT_MACROS.contains(&name.as_str()) {
+        return None;
+    }
+    let parse_repeat = |input: ParseStream| -> syn::Result<_> {
+        let elem: syn::Expr = input.parse()?;
+        input.parse::<syn::Token![;]>()?;
+        let len: syn::Expr = input.parse()?;
+        Ok(Punctuated::from_iter([elem, len]))
+    };
+    Punctuated::parse_terminated
+        .parse2(mac.tokens.clone())
+        .or_else(|_| parse_repeat.parse2(mac.tokens.clone()))
+        .map_err(|err| debug!("Could not parse arguments of macro {} ({})", name, err))
+        .ok()
+}
+
+/// Marks the tuples that replace transparent macro invocations, so that the
+/// effects in them are reported as found via macro expansion
+const MACRO_ARGS_ATTR: &str = "cargo_scan_macro_args";
+
+/// The arguments of a transparent macro as a tuple, marked with
+/// `MACRO_ARGS_ATTR`, to be scanned in place of the macro invocation
+fn macro_args_tuple(
+    mut attrs: Vec<syn::Attribute>,
+    elems: Punctuated<syn::Expr, syn::Token![,]>,
+) -> syn::Expr {
+    let marker = syn::Ident::new(MACRO_ARGS_ATTR, Span::call_site());
+    attrs.push(syn::parse_quote!(#[#marker]));
+    args_tuple(attrs, elems)
+}
+
+/// The expressions as a tuple, to be scanned in their place
+fn args_tuple(
+    attrs: Vec<syn::Attribute>,
+    mut elems: Punctuated<syn::Expr, syn::Token![,]>,
+) -> syn::Expr {
+    if elems.len() == 1 && !elems.trailing_punct() {
+        elems.push_punct(Default::default());
+    }
+    syn::Expr::Tuple(syn::ExprTuple { attrs, paren_token: Default::default(), elems })
+}
+
+/// Parse the place of a raw borrow, `&raw const place` or `&raw mut place`,
+/// which syn keeps as a verbatim expression
+fn raw_borrow_place(tokens: &TokenStream) -> Option<syn::Expr> {
+    let parse = |input: ParseStream| -> syn::Result<syn::Expr> {
+        input.parse::<syn::Token![&]>()?;
+        let raw: syn::Ident = input.parse()?;
+        if raw != "raw" {
+            return Err(input.error("expected `raw`"));
+        }
+        if input.parse::<Option<syn::Token![mut]>>()?.is_none() {
+            input.parse::<syn::Token![const]>()?;
+        }
+        input.parse()
+    };
+    parse.parse2(tokens.clone()).ok()
+}
+
+/// Replaces invocations of the `TRANSPARENT_MACROS` with their arguments, and
+/// raw borrows with the borrowed place, which is scanned without creating a
+/// reference
+struct TransparentMacros;
+
+impl VisitMut for TransparentMacros {
+    fn visit_expr_mut(&mut self, e: &mut syn::Expr) {
+        if let syn::Expr::Macro(m) = e {
+            if let Some(args) = transparent_macro_args(&m.mac) {
+                *e = macro_args_tuple(std::mem::take(&mut m.attrs), args);
+            }
+        } else if let syn::Expr::Verbatim(v) = e {
+            if let Some(place) = raw_borrow_place(v) {
+                *e = args_tuple(Vec::new(), Punctuated::from_iter([place]));
+            }
+        }
+        visit_mut::visit_expr_mut(self, e);
+    }
+
+    fn visit_stmt_mut(&mut self, s: &mut syn::Stmt) {
+        if let syn::Stmt::Macro(m) = s {
+            if let Some(args) = transparent_macro_args(&m.mac) {
+                let semi = m.semi_token;
+                *s = syn::Stmt::Expr(
+                    macro_args_tuple(std::mem::take(&mut m.attrs), args),
+                    semi,
+                );
+            }
+        }
+        visit_mut::visit_stmt_mut(self, s);
+    }
+}
+
+/// Replace invocations of common macros whose arguments are plain
+/// expressions, like `println!` and `dbg!`, with their arguments, so that the
+/// effects in them are found. Other macros are left as-is, and are skipped by
+/// the scanner, as are transparent macros whose arguments don't parse. Raw
+/// borrows, which syn doesn't parse, are replaced with the borrowed place.
+fn expand_transparent_macros(syntax_tree: &mut syn::File) {
+    TransparentMacros.visit_file_mut(syntax_tree);
+}
+
 /// Load the Rust file at the filepath and scan it (quick mode)
 pub fn scan_file_quick(
     crate_name: &str,
@@ -1252,20 +2782,53 @@ pub fn scan_file_quick(
     scan_results: &mut ScanResults,
     sinks: HashSet<IdentPath>,
     enabled_cfg: &HashMap<String, Vec<String>>,
+) -> Result<()> {
+    let options = ScanOptions { sinks, ..ScanOptions::default() };
+    scan_file_quick_with_options(
+        crate_name,
+        filepath,
+        scan_results,
+        enabled_cfg,
+        &options,
+    )
+}
+
+/// Load the Rust file at the filepath and scan it in quick mode, with the
+/// sinks and scanner settings of `options`
+fn scan_file_quick_with_options(
+    crate_name: &str,
+    filepath: &FilePath,
+    scan_results: &mut ScanResults,
+    enabled_cfg: &HashMap<String, Vec<String>>,
+    options: &ScanOptions,
 ) -> Result<()> {
     let mut file = File::open(filepath)?;
     let mut src = String::new();
     file.read_to_string(&mut src)?;
-    let syntax_tree = syn::parse_file(&src)?;
+    let mut syntax_tree = syn::parse_file(&src)?;
+    let templates = macro_templates(&syntax_tree.items);
+    expand_transparent_macros(&mut syntax_tree);
 
     let hacky_resolver = HackyResolver::new(crate_name, filepath);
 
     let mut scanner =
         Scanner::new(filepath, hacky_resolver.unwrap(), scan_results, enabled_cfg);
-    scanner.add_sinks(sinks);
+    scanner.set_options(options);
 
     scanner.scan_file(&syntax_tree);
 
+    if !templates.is_empty() {
+        let mut template_results = ScanResults::new();
+        let hacky_resolver = HackyResolver::new(crate_name, filepath)?;
+        let mut scanner =
+            Scanner::new(filepath, hacky_resolver, &mut template_results, enabled_cfg);
+        scanner.set_options(options);
+        for item in &templates {
+            scanner.scan_item(item);
+        }
+        scan_results.add_macro_def_effects(template_results);
+    }
+
     Ok(())
 }
 
@@ -1277,6 +2840,27 @@ pub fn scan_file(
     scan_results: &mut ScanResults,
     sinks: HashSet<IdentPath>,
     enabled_cfg: &HashMap<String, Vec<String>>,
+) -> Result<()> {
+    let options = ScanOptions { sinks, ..ScanOptions::default() };
+    scan_file_with_options(
+        crate_name,
+        filepath,
+        resolver,
+        scan_results,
+        enabled_cfg,
+        &options,
+    )
+}
+
+/// Load the Rust file at the filepath and scan it, with the sinks and
+/// scanner settings of `options`
+fn scan_file_with_options(
+    crate_name: &str,
+    filepath: &FilePath,
+    resolver: &Resolver,
+    scan_results: &mut ScanResults,
+    enabled_cfg: &HashMap<String, Vec<String>>,
+    options: &ScanOptions,
 ) -> Result<()> {
     debug!("Scanning file: {:?}", filepath);
 
@@ -1284,18 +2868,33 @@ pub fn scan_file(
     let mut file = File::open(filepath)?;
     let mut src = String::new();
     file.read_to_string(&mut src)?;
-    let syntax_tree = syn::parse_file(&src)?;
+    let mut syntax_tree = syn::parse_file(&src)?;
+    let templates = macro_templates(&syntax_tree.items);
+    expand_transparent_macros(&mut syntax_tree);
 
     // Initialize resolver
     let file_resolver = FileResolver::new(crate_name, resolver, filepath)?;
 
     // Initialize scanner
     let mut scanner = Scanner::new(filepath, file_resolver, scan_results, enabled_cfg);
-    scanner.add_sinks(sinks);
+    scanner.set_options(options);
 
     // Scan file contents
     scanner.scan_file(&syntax_tree);
 
+    // Scan the code in macro definitions
+    if !templates.is_empty() {
+        let mut template_results = ScanResults::new();
+        let file_resolver = FileResolver::new(crate_name, resolver, filepath)?;
+        let mut scanner =
+            Scanner::new(filepath, file_resolver, &mut template_results, enabled_cfg);
+        scanner.set_options(options);
+        for item in &templates {
+            scanner.scan_item(item);
+        }
+        scan_results.add_macro_def_effects(template_results);
+    }
+
     Ok(())
 }
 
@@ -1305,29 +2904,214 @@ pub fn try_scan_file(
     filepath: &FilePath,
     resolver: &Resolver,
     scan_results: &mut ScanResults,
-    sinks: HashSet<IdentPath>,
     enabled_cfg: &HashMap<String, Vec<String>>,
-    quick_mode: bool,
+    options: &ScanOptions,
 ) {
-    if quick_mode {
-        scan_file_quick(crate_name, filepath, scan_results, sinks, enabled_cfg)
-            .unwrap_or_else(|err| {
-                info!("Failed to scan file {} ({})", filepath.to_string_lossy(), err);
+    if options.quick_mode {
+        scan_file_quick_with_options(
+            crate_name,
+            filepath,
+            scan_results,
+            enabled_cfg,
+            options,
+        )
+        .unwrap_or_else(|err| {
+            info!("Failed to scan file {} ({})", filepath.to_string_lossy(), err);
+        })
+    } else {
+        scan_file_with_options(
+            crate_name,
+            filepath,
+            resolver,
+            scan_results,
+            enabled_cfg,
+            options,
+        )
+        .unwrap_or_else(|err| {
+            info!("Failed to scan file: {} ({})", filepath.to_string_lossy(), err);
+        });
+    }
+}
+
+/// Name of the crate that build scripts are compiled as
+const BUILD_SCRIPT_CRATE_NAME: &str = "build_script_build";
+
+/// Scan the given files in quick mode. Each file gets its own HackyResolver,
+/// so files can be scanned in parallel and the results merged afterwards.
+fn scan_files_quick(
+    crate_name: &str,
+    files: &[PathBuf],
+    enabled_cfg: &HashMap<String, Vec<String>>,
+    options: &ScanOptions,
+) -> Result<ScanResults> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.max_threads.unwrap_or(0))
+        .build()?;
+    let file_results: Vec<ScanResults> = pool.install(|| {
+        files
+            .par_iter()
+            .map(|entry| {
+                let mut file_results = ScanResults::new();
+                scan_file_quick_with_options(
+                    crate_name,
+                    entry.as_path(),
+                    &mut file_results,
+                    enabled_cfg,
+                    options,
+                )
+                .unwrap_or_else(|err| {
+                    info!("Failed to scan file {} ({})", entry.to_string_lossy(), err);
+                });
+                file_results
             })
+            .collect()
+    });
+
+    let mut scan_results = ScanResults::new();
+    for res in file_results {
+        scan_results.combine_scan_results(res);
+    }
+    Ok(scan_results)
+}
+
+/// Get the source files of the crate to scan, and its build script if it
+/// has one
+fn crate_files(
+    crate_path: &FilePath,
+    follow_symlinks: bool,
+) -> (Vec<PathBuf>, Option<PathBuf>) {
+    // TODO: For now, only walking through the src dir, but might want to
+    //       include others (e.g. might codegen in other dirs)
+    // If there is no src_dir, we walk through all .rs files in the crate.
+
+    // The build script is scanned separately, since it runs at compile time
+    let build_rs = crate_path.join("build.rs");
+    let build_rs = build_rs.is_file().then_some(build_rs);
+
+    let src_dir = crate_path.join(FilePath::new("src"));
+    let files: Vec<_> = if src_dir.is_dir() {
+        util::fs::walk_unique_files_with_extension(&src_dir, "rs", follow_symlinks)
+            .collect()
     } else {
-        scan_file(crate_name, filepath, resolver, scan_results, sinks, enabled_cfg)
-            .unwrap_or_else(|err| {
-                info!("Failed to scan file: {} ({})", filepath.to_string_lossy(), err);
-            });
+        info!("crate has no src dir; scanning all .rs files instead");
+        util::fs::walk_unique_files_with_extension(crate_path, "rs", follow_symlinks)
+            .filter(|f| Some(f) != build_rs.as_ref())
+            .collect()
+    };
+
+    (files, build_rs)
+}
+
+/// Split off the files larger than `max_file_size` bytes, so that huge
+/// (typically generated) files don't stall the scan. The skipped files are
+/// returned with their size.
+fn split_large_files(
+    files: Vec<PathBuf>,
+    max_file_size: Option<u64>,
+) -> (Vec<PathBuf>, Vec<(PathBuf, u64)>) {
+    let Some(max_file_size) = max_file_size else {
+        return (files, Vec::new());
+    };
+
+    let mut kept = Vec::new();
+    let mut skipped = Vec::new();
+    for file in files {
+        match file.metadata() {
+            Ok(m) if m.len() > max_file_size => {
+                warn!(
+                    "Skipping {:?} ({} bytes); larger than the maximum file size",
+                    file,
+                    m.len()
+                );
+                skipped.push((file, m.len()));
+            }
+            _ => kept.push(file),
+        }
     }
+    (kept, skipped)
+}
+
+/// Add the sinks under the other name of each renamed dependency. Callees of
+/// renamed dependencies are resolved to the real crate name in full mode, but
+/// to the alias in quick mode, so sinks given with either name should match.
+fn with_renamed_sinks(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+) -> Result<HashSet<IdentPath>> {
+    let renames = util::CrateRenames::load(crate_path)?;
+    Ok(sinks.iter().flat_map(|s| renames.remap(s)).chain(sinks.iter().cloned()).collect())
+}
+
+/// Options for scanning a crate with `scan_crate_with_options`
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Additional sinks to look for, besides the default ones
+    pub sinks: HashSet<IdentPath>,
+    /// Resolve names syntactically, without loading rust-analyzer
+    pub quick_mode: bool,
+    /// Maximum number of threads to scan files with in quick mode (defaults
+    /// to the available parallelism)
+    pub max_threads: Option<usize>,
+    /// Scan `#[cfg(test)]` code as well, and tag effects in test code with
+    /// `in_test`
+    pub include_tests: bool,
+    /// Scan symlinked source files and directories; either way, each real
+    /// file is only scanned once
+    pub follow_symlinks: bool,
+    /// Features to scan `#[cfg(feature = ...)]` code for, in addition to any
+    /// features enabled by rust-analyzer
+    pub enabled_features: Vec<String>,
+    /// Source files larger than this many bytes are not scanned, and are
+    /// recorded in `skipped_large_files` instead
+    pub max_file_size: Option<u64>,
+    /// Keep the location of each skipped construct for `skipped_report`
+    pub record_skipped: bool,
+    /// Give a method call through a generic type bounded by a trait call
+    /// edges to each impl of the method in the crate, as calls on
+    /// `dyn Trait` objects always do. This over-approximates the call graph,
+    /// and only applies in full mode.
+    pub virtual_dispatch: bool,
 }
 
-/// Scan the supplied crate with an additional list of sinks
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            sinks: HashSet::new(),
+            quick_mode: false,
+            max_threads: None,
+            include_tests: false,
+            follow_symlinks: true,
+            enabled_features: Vec::new(),
+            max_file_size: None,
+            record_skipped: false,
+            virtual_dispatch: false,
+        }
+    }
+}
+
+/// Scan the supplied crate with an additional list of sinks, and with code
+/// under `#[cfg(feature = ...)]` scanned for each of the `enabled_features`
 pub fn scan_crate_with_sinks(
     crate_path: &FilePath,
     sinks: HashSet<IdentPath>,
     relevant_effects: &[EffectType],
     quick_mode: bool,
+    enabled_features: &[String],
+) -> Result<ScanResults> {
+    let options = ScanOptions {
+        sinks,
+        quick_mode,
+        enabled_features: enabled_features.to_vec(),
+        ..ScanOptions::default()
+    };
+    scan_crate_with_options(crate_path, relevant_effects, &options)
+}
+
+/// Scan the supplied crate with the given options
+pub fn scan_crate_with_options(
+    crate_path: &FilePath,
+    relevant_effects: &[EffectType],
+    options: &ScanOptions,
 ) -> Result<ScanResults> {
     info!("Scanning crate: {:?}", crate_path);
 
@@ -1342,72 +3126,499 @@ pub fn scan_crate_with_sinks(
         return Err(anyhow!("Path is not a crate; missing Cargo.toml: {:?}", crate_path));
     }
 
-    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
-
     // TODO: this should *not* be created in the quick-mode case
-    let resolver = Resolver::new(crate_path)?;
+    let resolver = load_resolver(crate_path, options.include_tests);
+    scan_crate_with_resolver(crate_path, resolver.as_ref(), relevant_effects, options)
+}
 
-    let mut scan_results = ScanResults::new();
+/// Scan the crate like `scan_crate_with_options`, but reuse the results saved
+/// in `cache` if they are from the same sources, options, and version of
+/// cargo-scan. Otherwise the crate is scanned and the results are saved
+/// there. A cache that can't be read is scanned over with a warning.
+pub fn scan_crate_cached(
+    crate_path: &FilePath,
+    relevant_effects: &[EffectType],
+    options: &ScanOptions,
+    cache: &FilePath,
+) -> Result<ScanResults> {
+    let key = scan_cache_key(crate_path, relevant_effects, options)?;
+    match ScanResults::load(cache, &key) {
+        Ok(Some(results)) => {
+            info!("Reusing cached scan results: {:?}", cache);
+            return Ok(results);
+        }
+        Ok(None) => (),
+        Err(err) => warn!("Ignoring unreadable scan cache: {:#}", err),
+    }
 
-    let enabled_cfg = resolver.get_cfg_options_for_crate(&crate_name).unwrap_or_default();
+    let results = scan_crate_with_options(crate_path, relevant_effects, options)?;
+    results.save(cache, &key)?;
+    Ok(results)
+}
 
-    // TODO: For now, only walking through the src dir, but might want to
-    //       include others (e.g. might codegen in other dirs)
-    // If there is no src_dir, we walk through all .rs files in the crate.
+/// Key for cached scan results: the hash of the crate's sources together with
+/// the cargo-scan version and every option that affects the scan results
+/// (only `max_threads` doesn't)
+fn scan_cache_key(
+    crate_path: &FilePath,
+    relevant_effects: &[EffectType],
+    options: &ScanOptions,
+) -> Result<[u8; 32]> {
+    let mut sinks = options.sinks.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    sinks.sort();
+    let mut hasher = Sha256::new();
+    hasher.update(hash_dir(crate_path)?);
+    hasher.update(format!(
+        "{} {:?} {:?} {} {} {} {:?} {:?} {} {}",
+        env!("CARGO_PKG_VERSION"),
+        sinks,
+        relevant_effects,
+        options.quick_mode,
+        options.include_tests,
+        options.follow_symlinks,
+        options.enabled_features,
+        options.max_file_size,
+        options.record_skipped,
+        options.virtual_dispatch,
+    ));
+    Ok(hasher.finalize().into())
+}
 
-    let src_dir = crate_path.join(FilePath::new("src"));
-    let file_iter = if src_dir.is_dir() {
-        util::fs::walk_files_with_extension(&src_dir, "rs")
-    } else {
-        info!("crate has no src dir; scanning all .rs files instead");
-        util::fs::walk_files_with_extension(crate_path, "rs")
+/// Load rust-analyzer for the workspace at `path`. If it can't load the
+/// workspace, returns None so the scan degrades to quick mode rather than
+/// aborting.
+fn load_resolver(path: &FilePath, include_tests: bool) -> Option<Resolver> {
+    let load_resolver = || Resolver::new_with_tests(path, include_tests);
+    match panic::catch_unwind(load_resolver) {
+        Ok(Ok(resolver)) => Some(resolver),
+        Ok(Err(err)) => {
+            warn!(
+                "Failed to create resolver for {:?}; falling back to quick mode ({})",
+                path, err
+            );
+            None
+        }
+        Err(_) => {
+            warn!(
+                "Resolver panicked while loading {:?}; falling back to quick mode",
+                path
+            );
+            None
+        }
+    }
+}
+
+/// Scan the crate like `scan_crate_with_options`, with an already loaded
+/// resolver for the workspace it's in (None if it couldn't be loaded)
+fn scan_crate_with_resolver(
+    crate_path: &FilePath,
+    resolver: Option<&Resolver>,
+    relevant_effects: &[EffectType],
+    options: &ScanOptions,
+) -> Result<ScanResults> {
+    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+
+    let options = &ScanOptions {
+        sinks: with_renamed_sinks(crate_path, options.sinks.clone())?,
+        ..options.clone()
     };
+    let quick_mode = options.quick_mode;
 
-    for entry in file_iter {
-        try_scan_file(
-            &crate_name,
-            entry.as_path(),
-            &resolver,
-            &mut scan_results,
-            sinks.clone(),
-            &enabled_cfg,
-            quick_mode,
-        );
+    let mut enabled_cfg: HashMap<String, Vec<String>> = resolver
+        .and_then(|r| r.get_cfg_options_for_crate(&crate_name).ok())
+        .unwrap_or_default();
+    if options.include_tests {
+        enabled_cfg.entry("test".to_string()).or_default();
     }
+    for feature in &options.enabled_features {
+        let features = enabled_cfg.entry("feature".to_string()).or_default();
+        if !features.contains(feature) {
+            features.push(feature.clone());
+        }
+    }
+
+    let (files, build_rs) = crate_files(crate_path, options.follow_symlinks);
+    let (files, skipped_large_files) = split_large_files(files, options.max_file_size);
+
+    let full_results = match resolver {
+        Some(resolver) if !quick_mode => {
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                let mut scan_results = ScanResults::new();
+                for entry in &files {
+                    try_scan_file(
+                        &crate_name,
+                        entry.as_path(),
+                        resolver,
+                        &mut scan_results,
+                        &enabled_cfg,
+                        options,
+                    );
+                }
+                scan_results
+            }));
+            match res {
+                Ok(scan_results) => Some(scan_results),
+                Err(_) => {
+                    warn!(
+                        "Resolution panicked while scanning {:?}; falling back to quick mode",
+                        crate_path
+                    );
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let mut scan_results = match full_results {
+        Some(scan_results) => scan_results,
+        None => {
+            let mut scan_results =
+                scan_files_quick(&crate_name, &files, &enabled_cfg, options)?;
+            scan_results.quick_mode_fallback = !quick_mode;
+            scan_results
+        }
+    };
+    scan_results.skipped_large_files = skipped_large_files;
+
+    if let Some(build_rs) = build_rs {
+        let mut build_results = ScanResults::new();
+        match resolver {
+            Some(resolver) if !scan_results.quick_mode_fallback && !quick_mode => {
+                try_scan_file(
+                    &crate_name,
+                    &build_rs,
+                    resolver,
+                    &mut build_results,
+                    &enabled_cfg,
+                    options,
+                );
+            }
+            _ => {
+                // Match the crate name rust-analyzer gives build scripts
+                scan_file_quick_with_options(
+                    BUILD_SCRIPT_CRATE_NAME,
+                    &build_rs,
+                    &mut build_results,
+                    &enabled_cfg,
+                    options,
+                )
+                .unwrap_or_else(|err| {
+                    info!("Failed to scan build script {:?} ({})", build_rs, err);
+                });
+            }
+        }
+        for eff in build_results.effects.iter_mut() {
+            eff.set_from_build_script(true);
+        }
+        for eff in build_results.macro_def_effects.iter_mut() {
+            eff.set_from_build_script(true);
+        }
+        for eff in build_results.fn_ptr_effects.iter_mut() {
+            eff.set_from_build_script(true);
+        }
+        scan_results.combine_scan_results(build_results);
+    }
+
+    let quick_mode = quick_mode || scan_results.quick_mode_fallback;
+    finish_scan(&mut scan_results, crate_name, relevant_effects, quick_mode);
+
+    Ok(scan_results)
+}
 
-    filter_fn_ptr_effects(&mut scan_results, crate_name);
+/// Post-process the results of scanning a whole crate: resolve the callees
+/// defined in the crate if it was scanned in quick mode, drop unneeded
+/// function pointer effects, keep only the relevant effect types, and
+/// deduplicate the effects.
+fn finish_scan(
+    scan_results: &mut ScanResults,
+    crate_name: String,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+) {
+    if quick_mode {
+        scan_results.resolve_quick_callees();
+    }
+    filter_fn_ptr_effects(scan_results, crate_name);
     scan_results
         .effects
         .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+    scan_results
+        .macro_def_effects
+        .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+    scan_results.dedup_effects();
+}
+
+/// Scan all `.rs` files under `dir`, which doesn't need to be a crate (i.e.
+/// it may have no Cargo.toml), in quick mode. Paths defined in the files are
+/// resolved as if they were in a crate named `crate_name`.
+pub fn scan_loose_files(
+    dir: &FilePath,
+    crate_name: &str,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+) -> Result<ScanResults> {
+    info!("Scanning loose files: {:?}", dir);
+
+    if !dir.is_dir() {
+        return Err(anyhow!("Path is not a directory: {:?}", dir));
+    }
+
+    let files =
+        util::fs::walk_unique_files_with_extension(dir, "rs", true).collect::<Vec<_>>();
+    let options = ScanOptions { sinks, quick_mode: true, ..ScanOptions::default() };
+    let mut scan_results =
+        scan_files_quick(crate_name, &files, &HashMap::new(), &options)?;
+
+    finish_scan(&mut scan_results, crate_name.to_string(), relevant_effects, true);
 
     Ok(scan_results)
 }
 
+/// Scan the supplied crate in quick mode, passing each effect to
+/// `write_effect` as soon as its file has been scanned instead of collecting
+/// all of them, so memory use is bounded by the largest file rather than the
+/// whole crate. Function pointer effects are written last, since whether they
+/// are kept depends on the call graph of the whole crate. Otherwise, the
+/// effects are the same and in the same order as from `scan_crate` in quick
+/// mode. Returns the number of effects written.
+///
+/// Features that need the full scan results, like the call graph and
+/// caller-checked auditing, are unavailable in this mode. Since rust-analyzer
+/// isn't loaded, `#[cfg(...)]` attributes are checked against only the
+/// `enabled_features`.
+pub fn scan_crate_streaming<F>(
+    crate_path: &FilePath,
+    sinks: HashSet<IdentPath>,
+    relevant_effects: &[EffectType],
+    enabled_features: &[String],
+    mut write_effect: F,
+) -> Result<usize>
+where
+    F: FnMut(&EffectInstance) -> Result<()>,
+{
+    info!("Scanning crate (streaming): {:?}", crate_path);
+
+    let crate_name = util::load_cargo_toml(crate_path)?.crate_name;
+    let sinks = with_renamed_sinks(crate_path, sinks)?;
+    let mut enabled_cfg = HashMap::new();
+    if !enabled_features.is_empty() {
+        enabled_cfg.insert("feature".to_string(), enabled_features.to_vec());
+    }
+
+    let (files, build_rs) = crate_files(crate_path, true);
+    let files = files
+        .iter()
+        .map(|f| (crate_name.as_str(), f, false))
+        .chain(build_rs.iter().map(|f| (BUILD_SCRIPT_CRATE_NAME, f, true)));
+
+    // Everything but the effects is kept to filter function pointer effects
+    let mut graph_results = ScanResults::new();
+    let mut n_written = 0;
+    for (file_crate_name, file, is_build_script) in files {
+        let mut file_results = ScanResults::new();
+        scan_file_quick(
+            file_crate_name,
+            file,
+            &mut file_results,
+            sinks.clone(),
+            &enabled_cfg,
+        )
+        .unwrap_or_else(|err| {
+            info!("Failed to scan file {} ({})", file.to_string_lossy(), err);
+        });
+        file_results
+            .effects
+            .retain(|e| EffectType::matches_effect(relevant_effects, e.eff_type()));
+        file_results.dedup_effects();
+        for mut eff in std::mem::take(&mut file_results.effects) {
+            eff.set_from_build_script(is_build_script);
+            write_effect(&eff)?;
+            n_written += 1;
+        }
+        // Written after the whole crate is scanned, so flag them now
+        for eff in file_results.fn_ptr_effects.iter_mut() {
+            eff.set_from_build_script(is_build_script);
+        }
+        graph_results.combine_scan_results(file_results);
+    }
+
+    finish_scan(&mut graph_results, crate_name, relevant_effects, true);
+    for eff in &graph_results.effects {
+        write_effect(eff)?;
+        n_written += 1;
+    }
+
+    Ok(n_written)
+}
+
 /// Scan the supplied crate
 pub fn scan_crate(
     crate_path: &FilePath,
     relevant_effects: &[EffectType],
     quick_mode: bool,
 ) -> Result<ScanResults> {
-    scan_crate_with_sinks(crate_path, HashSet::new(), relevant_effects, quick_mode)
+    scan_crate_with_sinks(crate_path, HashSet::new(), relevant_effects, quick_mode, &[])
+}
+
+/// Scan each member crate of the Cargo workspace at `workspace_path`.
+/// rust-analyzer loads the whole workspace anyway, so it's only loaded once
+/// and shared between the members, rather than once per member as with
+/// `scan_crate`. In quick mode rust-analyzer isn't loaded at all.
+pub fn scan_workspace(
+    workspace_path: &FilePath,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+) -> Result<HashMap<CrateId, ScanResults>> {
+    info!("Scanning workspace: {:?}", workspace_path);
+
+    let members = util::load_workspace_members(workspace_path)?;
+    let resolver = if quick_mode { None } else { load_resolver(workspace_path, false) };
+
+    let mut results = HashMap::new();
+    for member in members {
+        let crate_id = util::load_cargo_toml(&member)?;
+        let options = ScanOptions { quick_mode, ..ScanOptions::default() };
+        let scan_results = scan_crate_with_resolver(
+            &member,
+            resolver.as_ref(),
+            relevant_effects,
+            &options,
+        )?;
+        results.insert(crate_id, scan_results);
+    }
+
+    Ok(results)
+}
+
+/// Scan the supplied crate, including `#[cfg(test)]` code. Effects in test
+/// code are tagged with `in_test`.
+pub fn scan_crate_with_tests(
+    crate_path: &FilePath,
+    relevant_effects: &[EffectType],
+    quick_mode: bool,
+) -> Result<ScanResults> {
+    let options =
+        ScanOptions { quick_mode, include_tests: true, ..ScanOptions::default() };
+    scan_crate_with_options(crate_path, relevant_effects, &options)
+}
+
+/// Call `resolve` with the location of the callee name of every effect in
+/// the scan results, and a resolver for the effect's file. This requires
+/// loading the crate with rust-analyzer (full mode); files that can't be
+/// resolved are skipped.
+fn resolve_callees<F>(
+    crate_path: &FilePath,
+    scan_results: &mut ScanResults,
+    mut resolve: F,
+) -> Result<()>
+where
+    F: FnMut(&ResolverImpl, &mut EffectInstance, SrcLoc, Ident),
+{
+    let resolver = Resolver::new(crate_path)?;
+
+    let mut effects_by_file: HashMap<PathBuf, Vec<&mut EffectInstance>> = HashMap::new();
+    for eff in scan_results.effects.iter_mut() {
+        let loc = eff.call_loc();
+        effects_by_file.entry(loc.dir().join(loc.file())).or_default().push(eff);
+    }
+
+    for (filepath, effects) in effects_by_file {
+        let file_resolver = match ResolverImpl::new(&resolver, &filepath) {
+            Ok(r) => r,
+            Err(err) => {
+                info!("Failed to resolve callees in {:?} ({})", filepath, err);
+                continue;
+            }
+        };
+
+        for eff in effects {
+            let Some(callee_name) = eff.callee().as_path().last_ident() else {
+                continue;
+            };
+            // The effect location can cover a whole path (e.g. `libc::sysconf`),
+            // so find the start of the callee name from the end of the location
+            let loc = eff.call_loc();
+            let name_len = callee_name.as_str().len();
+            let Some(start_col) = loc.end_col().checked_sub(name_len) else {
+                continue;
+            };
+            let name_loc = SrcLoc::new(
+                &filepath,
+                loc.end_line(),
+                start_col + 1,
+                loc.end_line(),
+                loc.end_col(),
+            );
+
+            resolve(&file_resolver, eff, name_loc, callee_name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve the signatures of the callees of all effects in the scan results,
+/// and attach them to the effects. This requires loading the crate with
+/// rust-analyzer (full mode); callees that can't be resolved to a function
+/// definition are skipped.
+pub fn resolve_callee_signatures(
+    crate_path: &FilePath,
+    scan_results: &mut ScanResults,
+) -> Result<()> {
+    resolve_callees(crate_path, scan_results, |resolver, eff, name_loc, name| {
+        match resolver.resolve_fn_signature(name_loc, name) {
+            Ok(sig) => eff.set_callee_sig(sig),
+            Err(err) => {
+                debug!("Failed to resolve signature of {} ({})", eff.callee(), err)
+            }
+        }
+    })
+}
+
+/// Resolve whether the callees of all effects in the scan results are stable
+/// or unstable standard library APIs, and attach that to the effects. Like
+/// `resolve_callee_signatures`, this requires full mode. Callees without a
+/// stability attribute (e.g. functions outside the standard library) are
+/// left untagged.
+pub fn resolve_callee_stability(
+    crate_path: &FilePath,
+    scan_results: &mut ScanResults,
+) -> Result<()> {
+    resolve_callees(crate_path, scan_results, |resolver, eff, name_loc, name| {
+        match resolver.resolve_stability(name_loc, name) {
+            Ok(Some(stability)) => eff.set_callee_stability(stability),
+            Ok(None) => (),
+            Err(err) => {
+                debug!("Failed to resolve stability of {} ({})", eff.callee(), err)
+            }
+        }
+    })
 }
 
 /// Keep only the `FnPtrCreation` effect instances for the pointers that
 /// point to functions with effects or functions defined in dependencies
 fn filter_fn_ptr_effects(scan_results: &mut ScanResults, crate_name: String) {
-    let mut crate_name = crate_name;
-    crate::ident::replace_hyphens(&mut crate_name);
-
     for p in scan_results.fn_ptr_effects.iter() {
-        if !p.callee().crate_name().to_string().eq(&crate_name)
-            || check_fn_for_effects(scan_results, p.callee())
-        {
+        if keep_fn_ptr_effect(scan_results, &crate_name, p) {
             scan_results.effects.push(p.clone());
             scan_results.fns_with_effects.insert(p.caller().clone());
         }
     }
 }
 
+fn keep_fn_ptr_effect(
+    scan_results: &ScanResults,
+    crate_name: &str,
+    p: &EffectInstance,
+) -> bool {
+    let mut crate_name = crate_name.to_string();
+    crate::ident::replace_hyphens(&mut crate_name);
+
+    !p.callee().crate_name().to_string().eq(&crate_name)
+        || check_fn_for_effects(scan_results, p.callee())
+}
+
 // We still need to track transitive effects from callees, because the immediate
 // function the pointer points to might not have effects, but it might call other
 // functions with potentially dangerous behavior.
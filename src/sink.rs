@@ -4,34 +4,112 @@ use crate::ident::Ident;
 
 use super::ident::{CanonicalPath, IdentPath, Pattern};
 
-use log::warn;
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use log::debug;
+use parse_display::{Display, FromStr};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
     fmt::{self, Display},
+    fs,
+    path::Path as FilePath,
 };
 
 // TODO: Convert these examples to canonical paths
-/// Hard-coded list of sink patterns
-const SINK_PATTERNS: &[&str] = &[
-    "std::arch",
-    "std::backtrace",
-    "std::env",
-    "std::ffi",
-    "std::fs",
-    "std::intrinsics",
-    "std::io",
-    "std::mem",
-    "std::net",
-    "std::os",
-    "std::path",
-    "std::panic",
-    "std::process",
-    "std::simd",
-    "libc",
-    "winapi",
+/// Hard-coded list of sink patterns, with the capability each one grants
+const SINK_PATTERNS: &[(&str, SinkCategory)] = &[
+    ("std::arch", SinkCategory::Other),
+    ("std::backtrace", SinkCategory::Other),
+    ("std::env", SinkCategory::Env),
+    // Mutating the environment is process-global, and unsafe as of Rust 2024
+    ("std::env::set_var", SinkCategory::EnvWrite),
+    ("std::env::remove_var", SinkCategory::EnvWrite),
+    ("std::ffi", SinkCategory::Other),
+    ("std::fs", SinkCategory::Filesystem),
+    ("std::intrinsics", SinkCategory::Other),
+    ("std::io", SinkCategory::Io),
+    ("std::mem", SinkCategory::Other),
+    ("std::net", SinkCategory::Network),
+    ("std::os", SinkCategory::Other),
+    // Paths are only manipulated in memory, apart from the methods that
+    // query the filesystem
+    ("std::path", SinkCategory::Other),
+    ("std::path::Path::canonicalize", SinkCategory::Filesystem),
+    ("std::path::Path::exists", SinkCategory::Filesystem),
+    ("std::path::Path::is_dir", SinkCategory::Filesystem),
+    ("std::path::Path::is_file", SinkCategory::Filesystem),
+    ("std::path::Path::is_symlink", SinkCategory::Filesystem),
+    ("std::path::Path::metadata", SinkCategory::Filesystem),
+    ("std::path::Path::read_dir", SinkCategory::Filesystem),
+    ("std::path::Path::read_link", SinkCategory::Filesystem),
+    ("std::path::Path::symlink_metadata", SinkCategory::Filesystem),
+    ("std::path::Path::try_exists", SinkCategory::Filesystem),
+    ("std::panic", SinkCategory::Other),
+    ("std::process", SinkCategory::Process),
+    ("std::simd", SinkCategory::Other),
+    ("libc", SinkCategory::Other),
+    ("winapi", SinkCategory::Other),
+    // Network clients outside of std. Both the public paths (as seen in
+    // quick mode) and the canonical paths (as resolved in full mode) are
+    // listed.
+    ("reqwest::get", SinkCategory::Network),
+    ("reqwest::blocking::get", SinkCategory::Network),
+    ("reqwest::Client::new", SinkCategory::Network),
+    ("reqwest::ClientBuilder::build", SinkCategory::Network),
+    ("reqwest::blocking::Client::new", SinkCategory::Network),
+    ("reqwest::blocking::ClientBuilder::build", SinkCategory::Network),
+    ("reqwest::async_impl::client::Client::new", SinkCategory::Network),
+    ("reqwest::async_impl::client::ClientBuilder::build", SinkCategory::Network),
+    ("reqwest::blocking::client::Client::new", SinkCategory::Network),
+    ("reqwest::blocking::client::ClientBuilder::build", SinkCategory::Network),
+    ("hyper::Client::new", SinkCategory::Network),
+    ("hyper::client::Client::new", SinkCategory::Network),
+    ("hyper::client::Builder::build", SinkCategory::Network),
+    ("hyper::client::client::Client::new", SinkCategory::Network),
+    ("hyper::client::client::Builder::build", SinkCategory::Network),
+    // Unchecked operations that break the length or UTF-8 invariants of
+    // collections. The slice methods are defined in an inherent impl on
+    // `[T]`, which has no name in the canonical path. Calls to the unsafe
+    // ones are reported as unsafe calls as well.
+    ("std::vec::Vec::set_len", SinkCategory::Other),
+    ("alloc::vec::Vec::set_len", SinkCategory::Other),
+    ("std::string::String::from_utf8_unchecked", SinkCategory::Other),
+    ("alloc::string::String::from_utf8_unchecked", SinkCategory::Other),
+    ("std::str::from_utf8_unchecked", SinkCategory::Other),
+    ("core::str::from_utf8_unchecked", SinkCategory::Other),
+    ("core::str::converts::from_utf8_unchecked", SinkCategory::Other),
+    ("core::slice::*::get_unchecked", SinkCategory::Other),
+    ("core::slice::*::get_unchecked_mut", SinkCategory::Other),
+    // Moving a value out from behind a `&mut` can break the pinning
+    // invariants of `!Unpin` (e.g. self-referential) types. These are
+    // defined in core, so the std paths only match in quick mode.
+    ("core::mem::swap", SinkCategory::Other),
+    ("core::mem::replace", SinkCategory::Other),
+    ("core::mem::take", SinkCategory::Other),
+    // Manual ownership transfer through raw pointers, e.g. across an FFI
+    // boundary, where the pointer has to be reclaimed exactly once. Calls to
+    // the unsafe `from_raw` are reported as unsafe calls as well.
+    ("std::boxed::Box::into_raw", SinkCategory::Other),
+    ("std::boxed::Box::from_raw", SinkCategory::Other),
+    ("alloc::boxed::Box::into_raw", SinkCategory::Other),
+    ("alloc::boxed::Box::from_raw", SinkCategory::Other),
+    ("std::rc::Rc::into_raw", SinkCategory::Other),
+    ("std::rc::Rc::from_raw", SinkCategory::Other),
+    ("alloc::rc::Rc::into_raw", SinkCategory::Other),
+    ("alloc::rc::Rc::from_raw", SinkCategory::Other),
+    ("std::sync::Arc::into_raw", SinkCategory::Other),
+    ("std::sync::Arc::from_raw", SinkCategory::Other),
+    ("alloc::sync::Arc::into_raw", SinkCategory::Other),
+    ("alloc::sync::Arc::from_raw", SinkCategory::Other),
 ];
 
+/// Methods that are matched against sink patterns by name alone when the
+/// receiver type can't be resolved (e.g. in quick mode). Only names that are
+/// unlikely to be shared with unrelated methods are included.
+const UNRESOLVED_METHOD_SINKS: &[&str] =
+    &["set_len", "get_unchecked", "get_unchecked_mut"];
+
 // Removed sink patterns on 2023-11-16
 // "mio::net",
 // "mio::unix",
@@ -45,44 +123,166 @@ const SINK_PATTERNS: &[&str] = &[
 // "tokio_util::net",
 // "socket2",
 
+/// The kind of capability a sink grants, grouping sink patterns the way
+/// an auditor would think about them.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    Display,
+    FromStr,
+    ValueEnum,
+)]
+pub enum SinkCategory {
+    #[display("fs")]
+    #[value(name = "fs")]
+    Filesystem,
+    #[display("net")]
+    #[value(name = "net")]
+    Network,
+    #[display("io")]
+    #[value(name = "io")]
+    Io,
+    #[display("proc")]
+    #[value(name = "proc")]
+    Process,
+    #[display("env")]
+    #[value(name = "env")]
+    Env,
+    #[display("env-write")]
+    #[value(name = "env-write")]
+    EnvWrite,
+    #[display("other")]
+    #[value(name = "other")]
+    Other,
+}
+
+impl SinkCategory {
+    /// Get the category of a sink pattern, based on the most specific
+    /// default sink pattern it falls under. Patterns that don't fall under
+    /// any default sink (e.g. custom sinks) are categorized as `Other`.
+    pub fn of_pattern(pat: &Pattern) -> Self {
+        let path = CanonicalPath::new(pat.as_str());
+        SINK_PATTERNS
+            .iter()
+            .filter(|(p, _)| path.matches(&Pattern::new(p)))
+            .max_by_key(|(p, _)| p.len())
+            .map(|(_, c)| *c)
+            .unwrap_or(SinkCategory::Other)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct Sink(Pattern);
+#[serde(from = "Pattern", into = "Pattern")]
+pub struct Sink {
+    pattern: Pattern,
+    category: SinkCategory,
+}
+
+impl From<Pattern> for Sink {
+    fn from(pattern: Pattern) -> Self {
+        let category = SinkCategory::of_pattern(&pattern);
+        Self { pattern, category }
+    }
+}
+
+impl From<Sink> for Pattern {
+    fn from(sink: Sink) -> Self {
+        sink.pattern
+    }
+}
 
 impl Display for Sink {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.fmt(f)
+        self.pattern.fmt(f)
     }
 }
 
 impl Sink {
-    /// Get the sink pattern matching a callee.
+    /// Find the sink pattern matching the callee. If several patterns match,
+    /// the most specific (longest) one is used, so user-provided patterns like
+    /// `std::fs::write` take precedence over the default `std::fs`.
+    ///
+    /// Unresolved method calls only carry the method name, so those listed in
+    /// `UNRESOLVED_METHOD_SINKS` match any pattern ending in that name.
     pub fn new_match(callee: &CanonicalPath, sinks: &HashSet<IdentPath>) -> Option<Self> {
-        let mut result = None;
+        let method = callee
+            .as_str()
+            .strip_prefix("UNKNOWN_METHOD::")
+            .filter(|m| UNRESOLVED_METHOD_SINKS.contains(m));
+        let mut result: Option<Pattern> = None;
         for pat_raw in sinks {
+            let method_match =
+                method.is_some_and(|m| pat_raw.as_str().rsplit("::").next() == Some(m));
+            if !callee.matches_glob(pat_raw.as_str()) && !method_match {
+                continue;
+            }
             let pat = Pattern::new(pat_raw.as_str());
-            if callee.matches(&pat) {
-                if let Some(x) = result {
-                    warn!(
-                    "Found multiple patterns of interest for {} (overwriting {} with {})",
+            if let Some(x) = &result {
+                debug!(
+                    "Found multiple patterns of interest for {} ({} and {})",
                     callee, x, pat
                 );
+                let longer =
+                    (pat.as_str().len(), pat.as_str()) > (x.as_str().len(), x.as_str());
+                if !longer {
+                    continue;
                 }
-                result = Some(pat)
             }
+            result = Some(pat)
         }
-        Some(Self(result?))
+        Some(result?.into())
     }
 
     pub fn first_ident(&self) -> Option<Ident> {
-        self.0.first_ident()
+        self.pattern.first_ident()
     }
 
     /// convert to str
     pub fn as_str(&self) -> &str {
-        self.0.as_str()
+        self.pattern.as_str()
+    }
+
+    pub fn category(&self) -> SinkCategory {
+        self.category
     }
 
     pub fn default_sinks() -> HashSet<IdentPath> {
-        SINK_PATTERNS.iter().map(|x| IdentPath::new(x)).collect::<HashSet<_>>()
+        SINK_PATTERNS.iter().map(|(x, _)| IdentPath::new(x)).collect::<HashSet<_>>()
+    }
+
+    /// Load additional sink patterns from a file with one pattern per line,
+    /// e.g. `data/of_interest.txt`. Patterns may contain `*` wildcards (see
+    /// `Pattern`). Blank lines and lines starting with `#` are ignored.
+    pub fn load_from_file(path: &FilePath) -> Result<HashSet<IdentPath>> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read sinks file: {}", path.display()))?;
+
+        let mut sinks = HashSet::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let sink = IdentPath::new(line);
+            if !sink.invariant() {
+                return Err(anyhow!(
+                    "Invalid sink pattern at {}:{}: {}",
+                    path.display(),
+                    i + 1,
+                    line
+                ));
+            }
+            sinks.insert(sink);
+        }
+
+        Ok(sinks)
     }
 }
@@ -69,6 +69,7 @@ pub mod iter {
 
 /// Filesystem util
 pub mod fs {
+    use std::collections::HashSet;
     use std::fmt::Debug;
     use std::fs::File;
     use std::io::{BufRead, BufReader, BufWriter};
@@ -93,6 +94,34 @@ pub mod fs {
             .filter(|entry| entry.extension().map_or(false, |x| x.to_str() == Some(ext)))
     }
 
+    /// Walk the files with the given extension, visiting each real file once.
+    ///
+    /// If `follow_symlinks` is set, symlinked directories are descended into
+    /// (symlink cycles are reported and skipped) and symlinked files are
+    /// included; otherwise all symlinks are skipped. Files reachable through
+    /// more than one path are only returned for the first path in sorted order.
+    pub fn walk_unique_files_with_extension<'a>(
+        p: &'a Path,
+        ext: &'a str,
+        follow_symlinks: bool,
+    ) -> impl Iterator<Item = PathBuf> + 'a {
+        debug_assert!(p.is_dir());
+        let mut visited = HashSet::new();
+        WalkDir::new(p)
+            .follow_links(follow_symlinks)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(super::iter::warn_ok)
+            .filter(move |entry| follow_symlinks || !entry.path_is_symlink())
+            .map(DirEntry::into_path)
+            .filter(|entry| entry.is_file())
+            .filter(|entry| entry.extension().map_or(false, |x| x.to_str() == Some(ext)))
+            .filter(move |entry| {
+                let canonical = entry.canonicalize().unwrap_or_else(|_| entry.clone());
+                visited.insert(canonical)
+            })
+    }
+
     pub fn file_lines(p: &PathBuf) -> impl Iterator<Item = String> {
         let file = File::open(p).unwrap();
         let reader = BufReader::new(file).lines();
@@ -111,16 +140,21 @@ pub mod fs {
 
 /// Parse Cargo TOML
 use anyhow::{Context, Result};
+use cargo::core::Workspace;
+use cargo::util::context::GlobalContext;
 use cargo_lock::{Dependency, Package};
 use log::debug;
 use semver::Version;
 use serde::de::{self, Unexpected, Visitor};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::read_to_string;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use toml::{self, value::Table};
 
+use crate::ident::IdentPath;
+
 #[derive(Eq, Hash, PartialEq, Debug, Clone)]
 pub struct CrateId {
     pub crate_name: String,
@@ -257,3 +291,100 @@ pub fn load_cargo_toml(crate_path: &Path) -> Result<CrateId> {
     debug!("Loaded: {:?}", result);
     Ok(result)
 }
+
+/// Load the paths of the member crates of the workspace at `workspace_path`.
+/// Cargo resolves the members, so globs in `members` and the `exclude` list
+/// behave as they do for `cargo build`. If the root Cargo.toml is also a
+/// package, the root crate comes first; the other members are sorted by path.
+pub fn load_workspace_members(workspace_path: &Path) -> Result<Vec<PathBuf>> {
+    debug!("Loading workspace members at: {:?}", workspace_path);
+
+    // Cargo needs an absolute manifest path, but the members are returned
+    // relative to `workspace_path` like the rest of the scan paths
+    let root = workspace_path.canonicalize()?;
+    let gctx = GlobalContext::default()?;
+    let ws = Workspace::new(&root.join("Cargo.toml"), &gctx)?;
+
+    let mut result: Vec<PathBuf> = ws
+        .members()
+        .map(|p| workspace_path.join(p.root().strip_prefix(&root).unwrap_or(p.root())))
+        .collect();
+    result.sort_by_key(|p| (p != workspace_path, p.clone()));
+
+    debug!("Loaded workspace members: {:?}", result);
+    Ok(result)
+}
+
+/// Dependencies renamed in Cargo.toml with the `package` key, e.g.
+/// `alias = { package = "real-name" }`. Maps the alias used in the crate's
+/// code to the real crate name, both as they appear in paths (with
+/// underscores instead of hyphens).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrateRenames(HashMap<String, String>);
+
+impl CrateRenames {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Load the renamed dependencies (including dev, build, and
+    /// target-specific dependencies) from the crate's Cargo.toml
+    pub fn load(crate_path: &Path) -> Result<Self> {
+        let toml_string = read_to_string(crate_path.join("Cargo.toml"))?;
+        let cargo_toml =
+            toml::from_str::<Table>(&toml_string).context("Couldn't parse Cargo.toml")?;
+
+        let dep_tables = |t: &Table| -> Vec<Table> {
+            ["dependencies", "dev-dependencies", "build-dependencies"]
+                .iter()
+                .filter_map(|k| t.get(*k)?.as_table().cloned())
+                .collect()
+        };
+        let mut tables = dep_tables(&cargo_toml);
+        if let Some(targets) = cargo_toml.get("target").and_then(|t| t.as_table()) {
+            for target in targets.values().filter_map(|t| t.as_table()) {
+                tables.extend(dep_tables(target));
+            }
+        }
+
+        let mut renames = Self::new();
+        for (alias, dep) in tables.iter().flatten() {
+            if let Some(real) = dep.get("package").and_then(|p| p.as_str()) {
+                renames.insert(alias, real);
+            }
+        }
+        debug!("Loaded dependency renames: {:?}", renames);
+        Ok(renames)
+    }
+
+    /// Add a rule renaming the crate `real` to `alias`
+    pub fn insert(&mut self, alias: &str, real: &str) {
+        self.0.insert(alias.replace('-', "_"), real.replace('-', "_"));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Rewrite the crate segment of the path from an alias to the real crate
+    /// name, or from a real crate name to its aliases. Returns the rewritten
+    /// paths; empty if the crate isn't renamed.
+    pub fn remap(&self, path: &IdentPath) -> Vec<IdentPath> {
+        let s = path.as_str();
+        let krate = s.split("::").next().unwrap_or_default();
+        let rest = &s[krate.len()..];
+        self.0
+            .iter()
+            .filter_map(|(alias, real)| {
+                if krate == alias {
+                    Some(real)
+                } else if krate == real {
+                    Some(alias)
+                } else {
+                    None
+                }
+            })
+            .map(|k| IdentPath::new_owned(format!("{}{}", k, rest)))
+            .collect()
+    }
+}
@@ -0,0 +1,658 @@
+use anyhow::Result;
+use cargo_scan::audit_chain::{collect_propagated_sinks, create_new_audit_chain, Create};
+use cargo_scan::audit_file::AuditFile;
+use cargo_scan::audit_file::{EffectQuery, SafetyAnnotation};
+use cargo_scan::auditing::audit::{
+    annotate_matching_effects, audit_without_test_effects, scoped_audit_file,
+    AuditHistory,
+};
+use cargo_scan::auditing::tui::TuiAudit;
+use cargo_scan::download_crate::download_crate_from_info;
+use cargo_scan::effect::{Effect, EffectType, DEFAULT_EFFECT_TYPES};
+use cargo_scan::ident::CanonicalPath;
+use cargo_scan::regression;
+use cargo_scan::scanner::{scan_crate, scan_crate_with_tests};
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+#[test]
+fn scoped_audit_of_pub_fn() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/caller-checked");
+    let scan_res = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file = AuditFile::empty(crate_path, DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+
+    let scope_fn = CanonicalPath::new("caller_checked::has_indirect_effect");
+    let scoped = scoped_audit_file(&audit_file, &scan_res, &scope_fn)?;
+
+    let sub_effect = CanonicalPath::new("caller_checked::sub::effect");
+    assert!(!scoped.audit_trees.is_empty());
+    assert!(scoped.audit_trees.keys().all(|e| e.caller() == &sub_effect));
+    assert_eq!(
+        scoped.audit_trees.len(),
+        scan_res.effects.iter().filter(|e| e.caller() == &sub_effect).count()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_effects_kept_when_excluded() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/cfg-ex");
+    let scan_res = scan_crate_with_tests(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file = AuditFile::empty(crate_path, DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+    let total = audit_file.audit_trees.len();
+
+    let seen = audit_without_test_effects(&mut audit_file, &scan_res.pub_fns, |a| {
+        assert!(a.audit_trees.keys().all(|e| !e.in_test()));
+        Ok(annotate_matching_effects(a, "std::fs", SafetyAnnotation::Safe))
+    })?;
+    assert!(seen > 0);
+
+    // The test effects are still there, unaudited
+    assert_eq!(audit_file.audit_trees.len(), total);
+    for (e, t) in &audit_file.audit_trees {
+        let expected =
+            if e.in_test() { SafetyAnnotation::Skipped } else { SafetyAnnotation::Safe };
+        assert_eq!(t.get_leaf_annotation(), Some(expected));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bulk_annotate_matching_effects() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/caller-checked");
+    let scan_res = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file = AuditFile::empty(crate_path, DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+
+    let is_libc = |callee: &str| callee.starts_with("libc::");
+    let libc_effects =
+        audit_file.audit_trees.keys().filter(|e| is_libc(e.callee_path())).count();
+    assert!(libc_effects > 0);
+    assert!(libc_effects < audit_file.audit_trees.len());
+
+    let annotated =
+        annotate_matching_effects(&mut audit_file, "libc::*", SafetyAnnotation::Safe);
+    assert_eq!(annotated, libc_effects);
+    for (e, t) in &audit_file.audit_trees {
+        let expected = if is_libc(e.callee_path()) {
+            SafetyAnnotation::Safe
+        } else {
+            SafetyAnnotation::Skipped
+        };
+        assert_eq!(t.get_leaf_annotation(), Some(expected));
+    }
+
+    // Already audited effects aren't changed, and plain patterns match substrings
+    assert_eq!(
+        annotate_matching_effects(&mut audit_file, "sysconf", SafetyAnnotation::Unsafe),
+        0
+    );
+    assert!(
+        annotate_matching_effects(&mut audit_file, "as_ptr", SafetyAnnotation::Unsafe)
+            > 0
+    );
+
+    Ok(())
+}
+
+#[test]
+fn query_audit_effects() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/caller-checked");
+    let scan_res = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file = AuditFile::empty(crate_path, DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+    annotate_matching_effects(
+        &mut audit_file,
+        "libc::*::sysconf",
+        SafetyAnnotation::Safe,
+    );
+
+    let ffi_calls =
+        EffectQuery { effect_types: vec![EffectType::FFICall], ..Default::default() };
+    let all_ffi = audit_file.query_effects(&ffi_calls);
+    assert!(!all_ffi.is_empty());
+    assert!(all_ffi.iter().all(|(e, _)| matches!(e.eff_type(), Effect::FFICall(_))));
+
+    let unaudited_ffi = audit_file.query_effects(&EffectQuery {
+        annotation: Some(SafetyAnnotation::Skipped),
+        ..ffi_calls.clone()
+    });
+    assert!(!unaudited_ffi.is_empty());
+    assert!(unaudited_ffi.iter().all(|(e, _)| !e.callee_path().ends_with("sysconf")));
+    assert_eq!(
+        unaudited_ffi.len(),
+        all_ffi.iter().filter(|(e, _)| !e.callee_path().ends_with("sysconf")).count()
+    );
+
+    let in_sub = audit_file.query_effects(&EffectQuery {
+        file: Some("*/sub.rs".to_string()),
+        callee: Some("sysctl".to_string()),
+        ..ffi_calls
+    });
+    assert_eq!(in_sub.len(), 1);
+    assert_eq!(in_sub[0].0.caller_path(), "caller_checked::sub::effect");
+
+    Ok(())
+}
+
+#[test]
+fn undo_restores_previous_annotation() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/caller-checked");
+    let scan_res = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file = AuditFile::empty(crate_path, DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+
+    let effects = audit_file.audit_trees.keys().cloned().collect::<Vec<_>>();
+    assert!(effects.len() >= 3);
+    let (first, second) = (&effects[0], &effects[1]);
+
+    let mut history = AuditHistory::default();
+    for (e, annotation) in
+        [(first, SafetyAnnotation::Safe), (second, SafetyAnnotation::Unsafe)]
+    {
+        let t = audit_file.audit_trees.get_mut(e).unwrap();
+        let prev_tree = t.clone();
+        t.set_annotation(annotation);
+        history.record(&audit_file, e, prev_tree);
+    }
+
+    // Skipping an effect leaves its tree unchanged, so there's nothing to undo
+    let third = &effects[2];
+    let prev_tree = audit_file.audit_trees[third].clone();
+    history.record(&audit_file, third, prev_tree);
+
+    let leaf = |f: &AuditFile, e| f.audit_trees[e].get_leaf_annotation();
+    assert_eq!(history.undo(&mut audit_file).as_ref(), Some(second));
+    assert_eq!(leaf(&audit_file, second), Some(SafetyAnnotation::Skipped));
+    assert_eq!(leaf(&audit_file, first), Some(SafetyAnnotation::Safe));
+
+    assert_eq!(history.undo(&mut audit_file).as_ref(), Some(first));
+    assert_eq!(leaf(&audit_file, first), Some(SafetyAnnotation::Skipped));
+    assert!(history.is_empty());
+    assert_eq!(history.undo(&mut audit_file), None);
+
+    Ok(())
+}
+
+#[test]
+fn tui_annotations_update_effect_trees() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/caller-checked");
+    let scan_res = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file = AuditFile::empty(crate_path, DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+
+    let mut tui = TuiAudit::new(&audit_file);
+    assert_eq!(tui.pending().len(), audit_file.audit_trees.len());
+
+    // Marking an effect caller-checked queues up its callers right after it
+    let sub_effect = CanonicalPath::new("caller_checked::sub::effect");
+    let i = tui.pending().iter().position(|p| p.base.caller() == &sub_effect).unwrap();
+    tui.select(i);
+    let base = tui.selected().unwrap().base.clone();
+    tui.annotate(&mut audit_file, &scan_res, SafetyAnnotation::CallerChecked)?;
+
+    let callers = scan_res.get_callers(&sub_effect)?;
+    assert!(!callers.is_empty());
+    let queued = tui.pending().iter().filter(|p| p.base == base).collect::<Vec<_>>();
+    assert_eq!(queued.len(), callers.len());
+    assert!(queued.iter().all(|p| p.path.len() == 1));
+    assert_eq!(tui.selected(), Some(queued[0]));
+    assert_eq!(tui.call_stack(&audit_file).len(), 2);
+    assert_eq!(tui.call_stack(&audit_file)[0].caller_path, sub_effect);
+
+    // Mark all of the callers safe
+    for _ in 0..callers.len() {
+        tui.annotate(&mut audit_file, &scan_res, SafetyAnnotation::Safe)?;
+    }
+    assert!(tui.pending().iter().all(|p| p.base != base));
+    let annotations = audit_file.audit_trees[&base].get_all_annotations();
+    assert_eq!(annotations.len(), callers.len() + 1);
+
+    // Going back restores the last caller, and asking later moves on
+    let pending = tui.pending().len();
+    assert_eq!(tui.undo(&mut audit_file), Some(base.clone()));
+    assert_eq!(tui.pending().len(), pending + 1);
+    assert_eq!(tui.selected().unwrap().base, base);
+    tui.annotate(&mut audit_file, &scan_res, SafetyAnnotation::Skipped)?;
+    assert_eq!(tui.pending().len(), pending + 1);
+
+    Ok(())
+}
+
+#[test]
+fn markdown_report_of_caller_checked_audit() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/caller-checked");
+    let audit_file =
+        AuditFile::new_caller_checked_default(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let report = audit_file.to_markdown_report();
+
+    assert!(report.starts_with("# Audit report: caller-checked\n"));
+    let total = audit_file.audit_trees.len();
+    assert!(report.contains(&format!("- Total base effects: {}\n", total)));
+    assert!(report.contains("- Unsafe: 0\n"));
+    assert!(report.contains("- Package fully audited\n"));
+
+    // One section per file, and one row per base effect
+    assert!(report.contains("\n## ") && report.contains("src/sub.rs\n"));
+    let rows = report.lines().filter(|l| l.starts_with("| ") && !l.starts_with("| Line"));
+    assert_eq!(rows.count(), total);
+
+    // Effects in sub::effect flow into the public function that calls it
+    let sub_row = report
+        .lines()
+        .find(|l| l.contains("`caller_checked::sub::effect`") && l.contains("sysconf"))
+        .unwrap();
+    assert!(sub_row.contains("`caller_checked::has_indirect_effect`"));
+
+    Ok(())
+}
+
+#[test]
+fn diff_audits_of_two_versions() -> Result<()> {
+    // The new version moves the existing effect in other.rs down a line and
+    // adds another one
+    let old_path = PathBuf::from("./data/test-packages/rescan-ex");
+    let tmp_dir = tempfile::tempdir()?;
+    let new_path = tmp_dir.path().join("rescan-ex");
+    fs::create_dir_all(new_path.join("src"))?;
+    for f in ["Cargo.toml", "src/lib.rs"] {
+        fs::copy(old_path.join(f), new_path.join(f))?;
+    }
+    fs::write(
+        new_path.join("src/other.rs"),
+        format!(
+            "\n{}\npub fn other_var2() -> bool {{\n    std::env::var(\"USER\").is_ok()\n}}\n",
+            fs::read_to_string(old_path.join("src/other.rs"))?
+        ),
+    )?;
+
+    let new_audit = |crate_path: &PathBuf| -> Result<AuditFile> {
+        let scan_res = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+        let mut audit_file =
+            AuditFile::empty(crate_path.clone(), DEFAULT_EFFECT_TYPES.to_vec())?;
+        audit_file.set_base_audit_trees(scan_res.effects_set());
+        Ok(audit_file)
+    };
+    let annotate = |audit_file: &mut AuditFile, caller: &str, a| {
+        for (e, t) in audit_file.audit_trees.iter_mut() {
+            if e.caller_path() == caller {
+                t.set_annotation(a);
+            }
+        }
+    };
+
+    let mut old = new_audit(&old_path)?;
+    annotate(&mut old, "rescan_ex::lib_var", SafetyAnnotation::Safe);
+    annotate(&mut old, "rescan_ex::other::other_var", SafetyAnnotation::Safe);
+    let mut new = new_audit(&new_path)?;
+    annotate(&mut new, "rescan_ex::lib_var", SafetyAnnotation::Unsafe);
+    annotate(&mut new, "rescan_ex::other::other_var", SafetyAnnotation::Safe);
+
+    assert!(AuditFile::diff(&old, &old).is_empty());
+
+    let diff = AuditFile::diff(&old, &new);
+    assert!(diff.removed.is_empty());
+    // The env::var call is both a sink call and untrusted input
+    assert_eq!(diff.added.len(), 2);
+    assert!(diff.added.iter().all(|e| e.caller_path() == "rescan_ex::other::other_var2"));
+    assert_eq!(diff.changed.len(), 2);
+    for c in &diff.changed {
+        assert_eq!(c.effect.caller_path(), "rescan_ex::lib_var");
+        assert_eq!(c.old, SafetyAnnotation::Safe);
+        assert_eq!(c.new, SafetyAnnotation::Unsafe);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn merge_audits_from_different_paths() -> Result<()> {
+    // The other audit is of the same crate, checked out somewhere else
+    let our_path = PathBuf::from("./data/test-packages/caller-checked");
+    let tmp_dir = tempfile::tempdir()?;
+    let their_path = tmp_dir.path().join("caller-checked");
+    fs::create_dir_all(their_path.join("src"))?;
+    for f in ["Cargo.toml", "Cargo.lock", "src/lib.rs", "src/main.rs", "src/sub.rs"] {
+        fs::copy(our_path.join(f), their_path.join(f))?;
+    }
+
+    let new_audit = |crate_path: &PathBuf| -> Result<(AuditFile, Vec<_>)> {
+        let scan_res = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+        let mut audit_file =
+            AuditFile::empty(crate_path.clone(), DEFAULT_EFFECT_TYPES.to_vec())?;
+        audit_file.set_base_audit_trees(scan_res.effects_set());
+        let mut effects = audit_file.audit_trees.keys().cloned().collect::<Vec<_>>();
+        effects.sort_by_key(|e| {
+            let loc = e.call_loc();
+            (loc.file().clone(), loc.start_line(), loc.start_col())
+        });
+        Ok((audit_file, effects))
+    };
+    let (mut ours, our_effects) = new_audit(&our_path)?;
+    let (mut theirs, their_effects) = new_audit(&their_path)?;
+    assert!(our_effects.len() >= 4);
+
+    let annotate = |audit_file: &mut AuditFile, e, a| {
+        audit_file.audit_trees.get_mut(e).unwrap().set_annotation(a);
+    };
+    // Only we audited the first effect, only they audited the second, and
+    // we disagree on the third
+    annotate(&mut ours, &our_effects[0], SafetyAnnotation::Safe);
+    annotate(&mut theirs, &their_effects[1], SafetyAnnotation::Unsafe);
+    annotate(&mut ours, &our_effects[2], SafetyAnnotation::Safe);
+    annotate(&mut theirs, &their_effects[2], SafetyAnnotation::Unsafe);
+    // ... and the fourth is missing from our audit
+    ours.audit_trees.remove(&our_effects[3]);
+
+    let report = ours.merge(&theirs)?;
+    assert_eq!(report.adopted, vec![our_effects[1].clone()]);
+    assert_eq!(report.added, vec![our_effects[3].clone()]);
+    assert_eq!(report.conflicts.len(), 1);
+    assert_eq!(report.conflicts[0].effect, our_effects[2]);
+    assert_eq!(
+        report.conflicts[0].theirs.get_leaf_annotation(),
+        Some(SafetyAnnotation::Unsafe)
+    );
+
+    let annotation = |e| ours.audit_trees[e].get_leaf_annotation().unwrap();
+    assert_eq!(ours.audit_trees.len(), our_effects.len());
+    assert_eq!(annotation(&our_effects[0]), SafetyAnnotation::Safe);
+    assert_eq!(annotation(&our_effects[1]), SafetyAnnotation::Unsafe);
+    assert_eq!(annotation(&our_effects[2]), SafetyAnnotation::Safe);
+    assert_eq!(annotation(&our_effects[3]), SafetyAnnotation::Skipped);
+
+    // Audits of different crates can't be merged
+    let (other_crate, _) = new_audit(&PathBuf::from("./data/test-packages/libc-ex"))?;
+    assert!(ours.merge(&other_crate).is_err());
+
+    Ok(())
+}
+
+/// Create the audit chain for multi-dependency-ex in a fresh directory and
+/// load all of its audit files, keyed by file name
+fn create_multi_dependency_chain(
+    jobs: Option<usize>,
+    trusted_crates: &[&str],
+) -> Result<HashMap<String, AuditFile>> {
+    let tmp_dir = tempfile::tempdir()?;
+    let audit_dir = tmp_dir.path();
+
+    let create = Create {
+        crate_path: "./data/test-packages/multi-dependency-ex".to_string(),
+        manifest_path: audit_dir.join("policy.manifest").to_string_lossy().to_string(),
+        audit_path: audit_dir.to_string_lossy().to_string(),
+        jobs,
+        trusted_crates: trusted_crates.iter().map(|c| c.to_string()).collect(),
+        ..Default::default()
+    };
+    let crate_download_path = audit_dir.join("crates");
+    create_new_audit_chain(create, &crate_download_path.to_string_lossy(), true)?;
+
+    let mut audit_files = HashMap::new();
+    for entry in fs::read_dir(audit_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "audit") {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            let audit_file =
+                AuditFile::read_audit_file(path)?.expect("missing audit file");
+            audit_files.insert(name, audit_file);
+        }
+    }
+
+    Ok(audit_files)
+}
+
+#[test]
+fn parallel_audit_chain_matches_serial() -> Result<()> {
+    let serial = create_multi_dependency_chain(Some(1), &[])?;
+    let parallel = create_multi_dependency_chain(Some(4), &[])?;
+
+    assert_eq!(serial.len(), 3);
+    assert_eq!(serial.len(), parallel.len());
+    for (name, s) in &serial {
+        let p = parallel.get(name).expect("audit file missing from parallel chain");
+        assert_eq!(s.audit_trees, p.audit_trees, "{}", name);
+        assert_eq!(s.pub_caller_checked, p.pub_caller_checked, "{}", name);
+        assert_eq!(s.base_dir, p.base_dir, "{}", name);
+        assert_eq!(s.hash, p.hash, "{}", name);
+        assert_eq!(s.version, p.version, "{}", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn trusted_crates_not_scanned() -> Result<()> {
+    let audit_files =
+        create_multi_dependency_chain(None, &["union-ex", "no-such-crate"])?;
+
+    let trusted = &audit_files["union-ex-0.1.0.audit"];
+    assert!(trusted.audit_trees.is_empty());
+    assert!(trusted.pub_caller_checked.is_empty());
+
+    // Other dependencies are audited as usual
+    let untrusted = &audit_files["dependency-ex-0.1.0.audit"];
+    assert!(untrusted
+        .audit_trees
+        .values()
+        .any(|t| t.get_leaf_annotation() != Some(SafetyAnnotation::Safe)));
+
+    Ok(())
+}
+
+#[test]
+fn trusted_crate_dependencies_not_audited() -> Result<()> {
+    let audit_dir = tempfile::tempdir()?;
+    let create = Create {
+        crate_path: "./data/test-packages/trusted-chain-ex".to_string(),
+        manifest_path: audit_dir
+            .path()
+            .join("policy.manifest")
+            .to_string_lossy()
+            .to_string(),
+        audit_path: audit_dir.path().to_string_lossy().to_string(),
+        trusted_crates: vec!["trusted-wrapper-ex".to_string()],
+        ..Default::default()
+    };
+    let crate_download_path = audit_dir.path().join("crates");
+    let mut chain =
+        create_new_audit_chain(create, &crate_download_path.to_string_lossy(), true)?;
+
+    let mut audited =
+        chain.all_crates().into_iter().map(|c| c.crate_name.clone()).collect::<Vec<_>>();
+    audited.sort();
+    assert_eq!(audited, vec!["trusted-chain-ex", "trusted-wrapper-ex", "union-ex"]);
+
+    // Nothing is propagated from the dependencies of the trusted crate
+    let sinks = collect_propagated_sinks(&mut chain)?;
+    assert!(sinks.keys().any(|e| e.caller_path() == "trusted_chain_ex::home"));
+    assert!(sinks.keys().all(|e| e.caller_path().starts_with("trusted_chain_ex")));
+
+    Ok(())
+}
+
+#[test]
+fn new_effects_since_other_version() -> Result<()> {
+    let published_path = PathBuf::from("./data/test-packages/rescan-ex");
+    let tmp_dir = tempfile::tempdir()?;
+    let local_path = tmp_dir.path().join("rescan-ex");
+    fs::create_dir_all(local_path.join("src"))?;
+    for f in ["Cargo.toml", "src/lib.rs", "src/other.rs"] {
+        fs::copy(published_path.join(f), local_path.join(f))?;
+    }
+    let mut lib = fs::read_to_string(published_path.join("src/lib.rs"))?;
+    lib.push_str(
+        "\npub fn lib_var2() -> bool {\n    std::env::var(\"HOME\").is_ok()\n}\n",
+    );
+    fs::write(local_path.join("src/lib.rs"), lib)?;
+
+    assert!(regression::new_effects(
+        &published_path,
+        &published_path,
+        DEFAULT_EFFECT_TYPES,
+        false
+    )?
+    .is_empty());
+    let added = regression::new_effects(
+        &published_path,
+        &local_path,
+        DEFAULT_EFFECT_TYPES,
+        false,
+    )?;
+    // The env::var call is both a sink call and untrusted input
+    assert_eq!(added.len(), 2);
+    assert!(added.iter().all(|e| e.caller_path() == "rescan_ex::lib_var2"));
+
+    Ok(())
+}
+
+#[test]
+#[ignore = "requires network access to crates.io"]
+fn new_effects_since_published_version() -> Result<()> {
+    // Use a copy of a published version as the local crate, with a new effect
+    let tmp_dir = tempfile::tempdir()?;
+    let local_dir = tmp_dir.path().join("local");
+    let published_dir = tmp_dir.path().join("published");
+    fs::create_dir_all(&local_dir)?;
+    let local_path =
+        download_crate_from_info("cfg-if", "1.0.0", local_dir.to_str().unwrap(), None)?;
+    let mut lib = fs::read_to_string(local_path.join("src/lib.rs"))?;
+    lib.push_str("\npub fn spawn() {\n    std::process::Command::new(\"ls\");\n}\n");
+    fs::write(local_path.join("src/lib.rs"), lib)?;
+
+    let (version, added) = regression::new_effects_since_published(
+        &local_path,
+        Some("1.0.0"),
+        published_dir.to_str().unwrap(),
+        None,
+        DEFAULT_EFFECT_TYPES,
+        true,
+    )?;
+    assert_eq!(version, "1.0.0");
+    assert_eq!(added.len(), 1);
+    assert_eq!(added[0].caller_path(), "cfg_if::spawn");
+    assert_eq!(added[0].callee_path(), "std::process::Command::new");
+
+    Ok(())
+}
+
+#[test]
+fn download_from_local_registry() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let registry_dir = tmp_dir.path().join("registry");
+    let download_dir = tmp_dir.path().join("crates");
+    fs::create_dir_all(&registry_dir)?;
+    fs::create_dir_all(&download_dir)?;
+
+    // Package a test crate the way crates are published
+    let tarball = fs::File::create(registry_dir.join("libc-ex-0.1.0.crate"))?;
+    let mut builder =
+        tar::Builder::new(GzEncoder::new(tarball, flate2::Compression::default()));
+    builder.append_dir_all("libc-ex-0.1.0", "./data/test-packages/libc-ex")?;
+    builder.into_inner()?.finish()?;
+
+    let registry = Url::from_directory_path(registry_dir.canonicalize()?)
+        .expect("absolute registry path");
+    let crate_path = download_crate_from_info(
+        "libc-ex",
+        "0.1.0",
+        download_dir.to_str().unwrap(),
+        Some(&registry),
+    )?;
+    assert_eq!(crate_path, download_dir.join("libc-ex-0.1.0"));
+    assert!(crate_path.join("Cargo.toml").is_file());
+    assert!(crate_path.join("src/lib.rs").is_file());
+
+    // Crates missing from the registry aren't fetched from anywhere else
+    assert!(download_crate_from_info(
+        "libc-ex",
+        "0.2.0",
+        download_dir.to_str().unwrap(),
+        Some(&registry),
+    )
+    .is_err());
+
+    // Nor is the latest version looked up on crates.io
+    assert!(regression::new_effects_since_published(
+        Path::new("./data/test-packages/libc-ex"),
+        None,
+        download_dir.to_str().unwrap(),
+        Some(&registry),
+        DEFAULT_EFFECT_TYPES,
+        true,
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn caller_checked_max_depth() -> Result<()> {
+    let crate_path = PathBuf::from("./data/test-packages/recursion-ex");
+    let truncated = |max_depth| -> Result<usize> {
+        let (audit_file, _) =
+            AuditFile::new_caller_checked_default_with_sinks_and_results(
+                &crate_path,
+                Default::default(),
+                DEFAULT_EFFECT_TYPES,
+                false,
+                None,
+                max_depth,
+            )?;
+        Ok(audit_file
+            .audit_trees
+            .values()
+            .flat_map(|t| t.get_all_annotations())
+            .filter(|(_, a)| *a == SafetyAnnotation::Truncated.to_string())
+            .count())
+    };
+
+    // The mutually recursive callers of the effects are cut off at depth 1
+    assert_eq!(truncated(None)?, 0);
+    assert!(truncated(Some(1))? > 0);
+
+    // Truncated effects are still unaudited, so they can be annotated in bulk
+    let mut audit_file = AuditFile::new_caller_checked_default_with_sinks(
+        &crate_path,
+        Default::default(),
+        DEFAULT_EFFECT_TYPES,
+        false,
+        Some(0),
+    )?;
+    let truncated_roots = |audit_file: &AuditFile| {
+        audit_file
+            .audit_trees
+            .values()
+            .filter(|t| t.get_leaf_annotation() == Some(SafetyAnnotation::Truncated))
+            .count()
+    };
+    assert!(truncated_roots(&audit_file) > 0);
+    annotate_matching_effects(&mut audit_file, "::", SafetyAnnotation::Safe);
+    assert_eq!(truncated_roots(&audit_file), 0);
+
+    // Public functions with truncated effects stay caller-checked when the
+    // list is recalculated
+    let (mut audit_file, scan_res) =
+        AuditFile::new_caller_checked_default_with_sinks_and_results(
+            &PathBuf::from("./data/test-packages/caller-checked"),
+            Default::default(),
+            DEFAULT_EFFECT_TYPES,
+            false,
+            None,
+            Some(0),
+        )?;
+    assert!(truncated_roots(&audit_file) > 0);
+    let pub_caller_checked = audit_file.pub_caller_checked.clone();
+    assert!(!pub_caller_checked.is_empty());
+    audit_file.recalc_pub_caller_checked(&scan_res.pub_fns);
+    assert_eq!(audit_file.pub_caller_checked, pub_caller_checked);
+
+    Ok(())
+}
@@ -0,0 +1,427 @@
+use anyhow::Result;
+use assert_cmd::prelude::*;
+use cargo_scan::audit_file::{AuditFile, SafetyAnnotation};
+use cargo_scan::auditing::review::ReviewEntry;
+use cargo_scan::auditing::util::hash_dir;
+use cargo_scan::effect::{EffectFilter, EffectType, Severity, DEFAULT_EFFECT_TYPES};
+use cargo_scan::output::manifest::{hex_hash, ScanManifest};
+use cargo_scan::scanner::scan_crate;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Scan the test package in quick mode with the given extra arguments, and
+/// count the effects in the CSV output
+fn count_scan_effects(test_package: &str, args: &[&str]) -> Result<usize> {
+    let output = Command::cargo_bin("scan")?
+        .arg(format!("data/test-packages/{}", test_package))
+        .args(["-q", "-s"])
+        .args(args)
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    Ok(stdout.lines().skip(1).filter(|l| !l.is_empty()).count())
+}
+
+#[test]
+fn explain_effect_type() -> Result<()> {
+    let output =
+        Command::cargo_bin("scan")?.args(["--explain", "UnionField"]).output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.starts_with("UnionField: read of a union field"));
+    assert!(stdout.contains("Example:"));
+
+    Ok(())
+}
+
+#[test]
+fn scan_with_sinks_file() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let sinks_file = tmp_dir.path().join("sinks.txt");
+    std::fs::write(&sinks_file, "# crate-local sinks\n\ncaller_checked::sub\n")?;
+
+    let output = Command::cargo_bin("scan")?
+        .arg("data/test-packages/caller-checked")
+        .arg("--sinks-file")
+        .arg(&sinks_file)
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.lines().any(|l| l.contains(
+        "caller_checked::has_indirect_effect, caller_checked::sub::effect, caller_checked::sub,"
+    )));
+
+    Ok(())
+}
+
+#[test]
+fn scan_config_check() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let sinks_file = tmp_dir.path().join("sinks.txt");
+    std::fs::write(&sinks_file, "std::fs::write\n")?;
+
+    let output = Command::cargo_bin("scan")?
+        .args(["data/test-packages/config-ex", "--config-check", "--sinks-file"])
+        .arg(&sinks_file)
+        .output()?;
+    assert!(output.status.success());
+
+    // Sinks from both the config file and the command line
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("\"config_ex::storage\""));
+    assert!(stdout.contains("\"std::fs::write\""));
+    assert!(stdout.lines().any(|l| l == "quick-mode = true"));
+
+    // Conflicting options are an error
+    let output = Command::cargo_bin("scan")?
+        .args(["data/test-packages/config-ex", "--config-check"])
+        .args(["--only-category", "fs", "--effect-types", "FFICall"])
+        .output()?;
+    assert!(!output.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn scan_only_category() -> Result<()> {
+    let output = Command::cargo_bin("scan")?
+        .args(["data/test-packages/permissions-ex", "--only-category", "proc", "-s"])
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let effects = stdout.lines().skip(1).filter(|l| !l.is_empty()).collect::<Vec<_>>();
+    assert!(!effects.is_empty());
+    assert!(effects.iter().all(|l| l.contains("std::process")));
+
+    Ok(())
+}
+
+#[test]
+fn scan_only_provenance() -> Result<()> {
+    let scan_effects =
+        |only: &str| count_scan_effects("macro-args-ex", &["--only", only]);
+
+    // One effect is called directly, and the others in the arguments of
+    // `println!`, `vec!` and `assert!`
+    assert_eq!(scan_effects("direct")?, 1);
+    assert_eq!(scan_effects("macro-expanded")?, 3);
+    // Only found with --macro-definitions
+    assert_eq!(scan_effects("macro-definition")?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn scan_fail_on() -> Result<()> {
+    let scan = |fail_on: &str| -> Result<Output> {
+        Ok(Command::cargo_bin("scan")?
+            .args(["data/test-packages/libc-ex", "-s", "--fail-on", fail_on])
+            .output()?)
+    };
+
+    // The effects are still written to stdout, and the failing ones to stderr
+    let output = scan("ffi-call,unsafe-call")?;
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout)?;
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stdout.lines().any(|l| l.contains("libc::unix::sysconf")));
+    assert!(stderr.lines().any(|l| l.contains("libc::unix::sysconf")));
+
+    // The names of the effect types as displayed are accepted too
+    assert!(scan("UnionField")?.status.success());
+
+    // --encapsulated-unsafe doesn't check for failing effects
+    let output = Command::cargo_bin("scan")?
+        .args(["data/test-packages/libc-ex", "--encapsulated-unsafe"])
+        .args(["--fail-on", "ffi-call"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn scan_table_format() -> Result<()> {
+    let output = Command::cargo_bin("scan")?
+        .args(["data/test-packages/libc-ex", "--format", "table"])
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.lines().next().is_some_and(|l| l.starts_with('┌')));
+    assert!(stdout.lines().any(|l| l.contains("libc::unix::sysconf")
+        && l.contains("FFICall")
+        && l.contains("libc_ex::main")
+        && l.contains("main.rs:6:8")));
+
+    Ok(())
+}
+
+#[test]
+fn scan_sarif_format() -> Result<()> {
+    let output = Command::cargo_bin("scan")?
+        .args(["data/test-packages/libc-ex", "--format", "sarif"])
+        .output()?;
+    assert!(output.status.success());
+
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert_eq!(run["properties"]["crate"], "libc-ex");
+
+    let results = run["results"].as_array().expect("missing SARIF results");
+    let ffi_call = results
+        .iter()
+        .find(|r| r["ruleId"] == "cargo-scan/FFICall")
+        .expect("missing FFI call result");
+    assert!(ffi_call["message"]["text"]
+        .as_str()
+        .is_some_and(|m| m.contains("libc_ex::main")));
+    let location = &ffi_call["locations"][0]["physicalLocation"];
+    assert!(location["artifactLocation"]["uri"]
+        .as_str()
+        .is_some_and(|uri| uri.ends_with("main.rs")));
+    assert_eq!(location["region"]["startLine"], 6);
+    assert_eq!(location["region"]["startColumn"], 9);
+
+    let rule_index = ffi_call["ruleIndex"].as_u64().expect("missing rule index") as usize;
+    assert_eq!(run["tool"]["driver"]["rules"][rule_index]["id"], "cargo-scan/FFICall");
+
+    Ok(())
+}
+
+#[test]
+fn scan_output_manifest() -> Result<()> {
+    let crate_path = "data/test-packages/libc-ex";
+    let crate_hash = hex_hash(&hash_dir(crate_path)?);
+    let check_manifest = |manifest: &serde_json::Value| {
+        assert_eq!(manifest["cargo-scan-version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest["effect-types"], serde_json::json!(["SinkCall", "FFICall"]));
+        assert_eq!(manifest["sinks"], serde_json::json!([]));
+        assert_eq!(manifest["quick-mode"], true);
+        assert_eq!(manifest["features"], serde_json::json!(["extra"]));
+        assert_eq!(manifest["filter"]["min-severity"], "Medium");
+        assert_eq!(manifest["crate-hash"], crate_hash.as_str());
+    };
+    let scan_args = [crate_path, "-q", "--effect-types", "SinkCall", "FFICall"];
+    let filter_args = ["--features", "extra", "--min-severity", "medium"];
+
+    let output = Command::cargo_bin("scan")?
+        .args(scan_args)
+        .args(filter_args)
+        .args(["--format", "ndjson"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let header: serde_json::Value =
+        serde_json::from_str(stdout.lines().next().expect("missing manifest"))?;
+    check_manifest(&header["manifest"]);
+
+    let output = Command::cargo_bin("scan")?
+        .args(scan_args)
+        .args(filter_args)
+        .args(["--format", "sarif"])
+        .output()?;
+    assert!(output.status.success());
+    let log: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    check_manifest(&log["runs"][0]["properties"]["manifest"]);
+
+    // The library writes the same manifest for the same options
+    let effect_types = [EffectType::SinkCall, EffectType::FFICall];
+    let filter =
+        EffectFilter { min_severity: Some(Severity::Medium), ..Default::default() };
+    let manifest = ScanManifest::new(
+        Path::new(crate_path),
+        &effect_types,
+        Vec::<String>::new(),
+        true,
+        &["extra".to_string()],
+        filter,
+    )?;
+    let results = scan_crate(Path::new(crate_path), &effect_types, true)?;
+    let mut ndjson = Vec::new();
+    results.to_ndjson(&mut ndjson, Some(&manifest))?;
+    let lib_header: serde_json::Value =
+        serde_json::from_str(String::from_utf8(ndjson)?.lines().next().unwrap())?;
+    assert_eq!(lib_header, header);
+
+    Ok(())
+}
+
+#[test]
+fn audit_exported_sinks() -> Result<()> {
+    let output = Command::cargo_bin("audit")?
+        .args(["data/test-packages/caller-checked", "--exported-sinks"])
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let ffi_sinks = stdout
+        .lines()
+        .skip_while(|l| l.trim() != "FFICall:")
+        .skip(1)
+        .take_while(|l| l.starts_with("    "))
+        .map(|l| l.trim())
+        .collect::<Vec<_>>();
+    assert!(ffi_sinks.contains(&"caller_checked::has_direct_effect"));
+    assert!(ffi_sinks.contains(&"caller_checked::has_indirect_effect"));
+    assert!(!ffi_sinks.contains(&"caller_checked::no_effect"));
+
+    Ok(())
+}
+
+#[test]
+fn grep_effects_in_audit_file() -> Result<()> {
+    let crate_path = PathBuf::from("data/test-packages/libc-ex");
+    let tmp_dir = tempfile::tempdir()?;
+    let audit_path = tmp_dir.path().join("libc-ex.audit");
+    let scan_res = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file = AuditFile::empty(crate_path, DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+    audit_file.save_to_file(audit_path.clone())?;
+
+    let output = Command::cargo_bin("grep_effect")?
+        .arg(&audit_path)
+        .args(["--effect-types", "FFICall", "--annotation", "skipped"])
+        .args(["--callee", "sysconf"])
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].contains("main.rs:6:"));
+    assert!(lines[0].ends_with("FFICall libc_ex::main -> libc::unix::sysconf [Skipped]"));
+
+    Ok(())
+}
+
+#[test]
+fn review_audit_as_json() -> Result<()> {
+    let crate_path = PathBuf::from("data/test-packages/libc-ex");
+    let tmp_dir = tempfile::tempdir()?;
+    let audit_path = tmp_dir.path().join("libc-ex.audit");
+    let scan_res = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let mut audit_file =
+        AuditFile::empty(crate_path.clone(), DEFAULT_EFFECT_TYPES.to_vec())?;
+    audit_file.set_base_audit_trees(scan_res.effects_set());
+    audit_file.save_to_file(audit_path.clone())?;
+
+    let output = Command::cargo_bin("audit")?
+        .arg(&crate_path)
+        .arg("-a")
+        .arg(&audit_path)
+        .args(["--review", "--review-info", "all", "--json"])
+        .output()?;
+    assert!(output.status.success());
+
+    let entries: Vec<ReviewEntry> = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(entries.len(), audit_file.audit_trees.len());
+    let sysconf = entries
+        .iter()
+        .find(|e| e.effect.callee().as_str() == "libc::unix::sysconf")
+        .expect("missing review entry for sysconf");
+    assert_eq!(sysconf.annotation, SafetyAnnotation::Skipped);
+    assert_eq!(sysconf.caller.as_str(), "libc_ex::main");
+    assert_eq!(sysconf.call_stack.len(), 1);
+
+    // Only the full review can be printed as JSON
+    let output = Command::cargo_bin("audit")?
+        .arg(&crate_path)
+        .args(["--review", "--json"])
+        .output()?;
+    assert_eq!(output.status.code(), Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn resolver_timeout_falls_back_to_quick_mode() -> Result<()> {
+    let output = Command::cargo_bin("scan")?
+        .arg("data/test-packages/libc-ex")
+        .env("CARGO_SCAN_RESOLVER_TIMEOUT", "0")
+        .output()?;
+    assert!(output.status.success());
+
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("timed out"));
+
+    // Quick mode can't tell that sysconf is an FFI function
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.lines().any(|l| l.contains("libc::sysconf")));
+
+    Ok(())
+}
+
+#[test]
+fn scan_output_per_file() -> Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let out_dir = tmp_dir.path().join("out");
+
+    let output = Command::cargo_bin("scan")?
+        .arg("data/test-packages/caller-checked")
+        .arg("--output-per-file")
+        .arg(&out_dir)
+        .output()?;
+    assert!(output.status.success());
+
+    let src_dir = out_dir.join("src");
+    let mut files = std::fs::read_dir(&src_dir)?
+        .map(|f| Ok(f?.file_name().to_string_lossy().to_string()))
+        .collect::<Result<Vec<_>>>()?;
+    files.sort();
+    assert_eq!(files, ["lib.rs.csv", "main.rs.csv", "sub.rs.csv"]);
+
+    // Each file only has the effects from its own source file
+    for (file, caller) in [
+        ("lib.rs.csv", "caller_checked::has_direct_effect"),
+        ("main.rs.csv", "caller_checked::local_effect"),
+        ("sub.rs.csv", "caller_checked::sub::effect"),
+    ] {
+        let contents = std::fs::read_to_string(src_dir.join(file))?;
+        let mut lines = contents.lines();
+        assert!(lines.next().unwrap().starts_with("crate, fn_decl"));
+        let rows = lines.collect::<Vec<_>>();
+        assert!(rows.iter().any(|l| l.contains(caller)));
+        let src_file = file.trim_end_matches(".csv");
+        assert!(rows.iter().all(|l| l.contains(&format!(", {}, ", src_file))));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn scan_effect_types() -> Result<()> {
+    let scan_effects = |effect_types: &[&str]| {
+        let args =
+            effect_types.iter().flat_map(|t| ["--effect-type", *t]).collect::<Vec<_>>();
+        count_scan_effects("permissions-ex", &args)
+    };
+
+    // The crate only has sink calls
+    let all = scan_effects(&[])?;
+    assert!(all > 0);
+    assert_eq!(scan_effects(&["sink-call"])?, all);
+    assert_eq!(scan_effects(&["SinkCall"])?, all);
+    assert_eq!(scan_effects(&["ffi-call", "unsafe-call"])?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn scan_min_severity() -> Result<()> {
+    // Sink calls are medium severity
+    let scan_effects = |min_severity: &str| {
+        count_scan_effects("permissions-ex", &["--min-severity", min_severity])
+    };
+    let all = count_scan_effects("permissions-ex", &[])?;
+    assert!(all > 0);
+    assert_eq!(scan_effects("low")?, all);
+    assert_eq!(scan_effects("medium")?, all);
+    assert_eq!(scan_effects("high")?, 0);
+
+    Ok(())
+}
@@ -0,0 +1,1603 @@
+use anyhow::Result;
+use cargo_scan::auditing::util::hash_dir;
+use cargo_scan::capabilities::{CrateCapabilities, CAPABILITY_PROPERTY};
+use cargo_scan::effect::{
+    ApiStability, Effect, EffectInstance, EffectProvenance, EffectType, FnContext,
+    ProgramArg, SrcLoc, DEFAULT_EFFECT_TYPES,
+};
+use cargo_scan::ident::{CanonicalPath, Ident, IdentPath};
+use cargo_scan::resolution::hacky_resolver::HackyResolver;
+use cargo_scan::resolution::name_resolution::{Resolver, ResolverImpl};
+use cargo_scan::scan_stats::ScanSummary;
+use cargo_scan::scanner::{
+    resolve_callee_signatures, resolve_callee_stability, scan_crate, scan_crate_cached,
+    scan_crate_streaming, scan_crate_with_options, scan_crate_with_sinks,
+    scan_crate_with_tests, scan_file, scan_loose_files, scan_workspace, ScanOptions,
+    ScanResults, Scanner, SkippedKind,
+};
+use cargo_scan::sink::{Sink, SinkCategory};
+use cargo_scan::util::fs::walk_unique_files_with_extension;
+use cargo_scan::util::CrateId;
+use semver::Version;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+fn scan_test_package(name: &str) -> Result<ScanResults> {
+    let crate_path = Path::new("./data/test-packages").join(name);
+    scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)
+}
+
+#[test]
+fn libc_alloc_is_raw_alloc() -> Result<()> {
+    let results = scan_test_package("alloc-ex")?;
+    assert!(!results.quick_mode_fallback);
+
+    let libc_effects = results
+        .effects
+        .iter()
+        .filter(|e| e.caller().as_str() == "alloc_ex::libc_alloc")
+        .collect::<Vec<_>>();
+    assert_eq!(libc_effects.len(), 2);
+    assert!(libc_effects.iter().all(|e| matches!(e.eff_type(), Effect::RawAlloc(_))));
+
+    let callees = libc_effects.iter().map(|e| e.callee_path()).collect::<Vec<_>>();
+    assert!(callees.iter().any(|c| c.ends_with("::malloc")));
+    assert!(callees.iter().any(|c| c.ends_with("::free")));
+
+    Ok(())
+}
+
+#[test]
+fn cyclonedx_capabilities() -> Result<()> {
+    let results = scan_test_package("libc-ex")?;
+    let crate_id = CrateId::new("libc-ex".to_string(), Version::new(0, 1, 0));
+    let capabilities = CrateCapabilities::from_effects(crate_id, &results.effects);
+    assert!(capabilities.effect_types.contains(&EffectType::FFICall));
+
+    let component = serde_json::to_value(capabilities.to_cyclonedx())?;
+    assert_eq!(component["type"], "library");
+    assert_eq!(component["name"], "libc-ex");
+    assert_eq!(component["version"], "0.1.0");
+    assert_eq!(component["purl"], "pkg:cargo/libc-ex@0.1.0");
+    assert_eq!(component["bom-ref"], component["purl"]);
+
+    let properties = component["properties"].as_array().unwrap();
+    assert!(properties.iter().all(|p| p["name"] == CAPABILITY_PROPERTY));
+    assert!(properties.iter().any(|p| p["value"] == "FFICall"));
+
+    Ok(())
+}
+
+#[test]
+fn union_field_mut_borrow() -> Result<()> {
+    let results = scan_test_package("union-ex")?;
+
+    let union_callers = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::UnionField(_)))
+        .map(|e| e.caller().as_str())
+        .collect::<Vec<_>>();
+    assert!(union_callers.contains(&"union_ex::borrow_field_mut"));
+    assert!(union_callers.contains(&"union_ex::assign_through_borrow"));
+    assert!(!union_callers.contains(&"union_ex::assign_field"));
+
+    Ok(())
+}
+
+#[test]
+fn packed_field_reference() -> Result<()> {
+    let results = scan_test_package("packed-ex")?;
+
+    let packed_callers = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::PackedFieldAccess(_)))
+        .map(|e| e.caller().as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(packed_callers, vec!["packed_ex::len_ref"]);
+    // Rust doesn't consider the reference unsafe
+    assert!(results.effects.iter().all(|e| !e.is_rust_unsafe()));
+    // The argument of `ptr::addr_of!` is scanned
+    assert_eq!(results.skipped_macros.get_instances(), 0);
+
+    Ok(())
+}
+
+#[test]
+fn resolver_failure_falls_back_to_quick_mode() -> Result<()> {
+    let results = scan_test_package("missing-dep-ex")?;
+    assert!(results.quick_mode_fallback);
+
+    assert!(results.effects.iter().any(|e| {
+        e.caller().as_str() == "missing_dep_ex::read_config"
+            && e.callee_path() == "std::fs::read_to_string"
+    }));
+
+    Ok(())
+}
+
+#[test]
+fn test_effects_tagged_in_test() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/cfg-ex");
+    let mut results = scan_crate_with_tests(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+
+    let in_test = |caller: &str| {
+        results
+            .effects
+            .iter()
+            .filter(|e| e.caller().as_str() == caller)
+            .map(|e| e.in_test())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(in_test("cfg_ex::test_1"), vec![true]);
+    assert_eq!(in_test("cfg_ex::tests::test_1"), vec![true]);
+    assert_eq!(in_test("cfg_ex::test_2"), vec![true]);
+    assert_eq!(in_test("cfg_ex::foo2"), vec![false]);
+
+    results.remove_test_effects();
+    assert!(!results.effects.is_empty());
+    assert!(results.effects.iter().all(|e| !e.in_test()));
+    let test_1 = CanonicalPath::new("cfg_ex::test_1");
+    assert!(!results.fn_effect_count.contains_key(&test_1));
+    assert!(!results.fn_locs.contains_key(&test_1));
+    assert!(!results.node_idxs.contains_key(&test_1));
+    assert!(results.test_fns.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn ffi_callee_signature() -> Result<()> {
+    let mut results = scan_test_package("libc-ex")?;
+    resolve_callee_signatures(Path::new("./data/test-packages/libc-ex"), &mut results)?;
+
+    let sysconf = results
+        .effects
+        .iter()
+        .find(|e| e.callee_path() == "libc::unix::sysconf")
+        .expect("missing sysconf effect");
+    let sig = sysconf.callee_sig().expect("missing sysconf signature");
+    assert_eq!(sig.to_string(), "(c_int) -> c_long");
+
+    Ok(())
+}
+
+#[test]
+fn env_args_is_untrusted_input() -> Result<()> {
+    let results = scan_test_package("input-ex")?;
+
+    let input_effects = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::UntrustedInput(_)))
+        .map(|e| (e.caller_path(), e.callee_path()))
+        .collect::<Vec<_>>();
+    assert!(input_effects.contains(&("input_ex::first_arg", "std::env::args")));
+    assert!(input_effects.contains(&("input_ex::home", "std::env::var")));
+    assert!(input_effects
+        .iter()
+        .any(|(caller, callee)| *caller == "input_ex::read_line"
+            && callee.ends_with("::stdin")));
+
+    // Input sources are still reported as sink calls in their category
+    let env_var = results
+        .effects
+        .iter()
+        .filter(|e| e.callee_path() == "std::env::var")
+        .map(|e| (EffectType::from(e.eff_type()), e.sink_category()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        env_var,
+        vec![
+            (EffectType::SinkCall, Some(SinkCategory::Env)),
+            (EffectType::UntrustedInput, None)
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unstable_callee_is_tagged() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/unstable-ex");
+    let mut results = scan_test_package("unstable-ex")?;
+    resolve_callee_stability(crate_path, &mut results)?;
+
+    let stability_of = |name: &str| {
+        results
+            .effects
+            .iter()
+            .find(|e| e.callee_path().ends_with(name))
+            .unwrap_or_else(|| panic!("missing effect calling {}", name))
+            .callee_stability()
+            .cloned()
+    };
+    assert_eq!(
+        stability_of("::peer_cred"),
+        Some(ApiStability::Unstable { feature: "peer_credentials_unix_socket".into() })
+    );
+    assert_eq!(stability_of("::fs::write"), Some(ApiStability::Stable));
+
+    Ok(())
+}
+
+#[test]
+fn libc_pthread_is_raw_thread() -> Result<()> {
+    let results = scan_test_package("pthread-ex")?;
+
+    let thread_effects = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::RawThread(_)))
+        .collect::<Vec<_>>();
+    assert_eq!(thread_effects.len(), 2);
+    assert!(thread_effects
+        .iter()
+        .all(|e| e.caller().as_str() == "pthread_ex::raw_thread"));
+
+    let callees = thread_effects.iter().map(|e| e.callee_path()).collect::<Vec<_>>();
+    assert!(callees.iter().any(|c| c.ends_with("::pthread_create")));
+    assert!(callees.iter().any(|c| c.ends_with("::pthread_join")));
+
+    // Thread calls aren't also reported as plain FFI calls
+    assert!(!results.effects.iter().any(|e| matches!(e.eff_type(), Effect::FFICall(_))));
+
+    Ok(())
+}
+
+#[test]
+fn cstr_as_ptr() -> Result<()> {
+    let results = scan_test_package("libc-ex")?;
+
+    let cstr_ptrs = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::CStrPtr { .. }))
+        .collect::<Vec<_>>();
+    assert_eq!(cstr_ptrs.len(), 1);
+    assert_eq!(cstr_ptrs[0].caller().as_str(), "libc_ex::main");
+    // The CStr is bound to a local, so the pointer outlives the statement
+    assert!(matches!(
+        cstr_ptrs[0].eff_type(),
+        Effect::CStrPtr { on_temporary: false, .. }
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn transmute_calls() -> Result<()> {
+    let results = scan_test_package("transmute-ex")?;
+
+    let mut transmutes = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::Transmute(_)))
+        .map(|e| e.caller_path())
+        .collect::<Vec<_>>();
+    transmutes.sort();
+    assert_eq!(transmutes, vec!["transmute_ex::pair", "transmute_ex::pair_turbofish"]);
+    // They aren't reported as plain unsafe calls too
+    assert!(!results
+        .effects
+        .iter()
+        .any(|e| matches!(e.eff_type(), Effect::UnsafeCall(_))));
+
+    Ok(())
+}
+
+#[test]
+fn resolver_cache_hits_match() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/permissions-ex");
+    let lib_rs = crate_path.join("src/lib.rs");
+    let resolver = Resolver::new(crate_path)?;
+    let scan_lib = || -> Result<Vec<EffectInstance>> {
+        let mut results = ScanResults::new();
+        results.rescan_file(
+            "permissions-ex",
+            &lib_rs,
+            Some(&resolver),
+            Default::default(),
+            DEFAULT_EFFECT_TYPES,
+            &Default::default(),
+        )?;
+        Ok(results.effects)
+    };
+
+    let first = scan_lib()?;
+    let cached = resolver.cached_idents();
+    assert!(cached > 0);
+    let hits = resolver.ident_cache_hits();
+
+    // The second scan resolves everything from the cache
+    assert_eq!(scan_lib()?, first);
+    assert_eq!(resolver.cached_idents(), cached);
+    assert!(resolver.ident_cache_hits() > hits);
+
+    Ok(())
+}
+
+#[test]
+fn resolve_ident_in_macro_expanded_twice() -> Result<()> {
+    // The argument of `twice!` maps to two places in the expansion, which
+    // both have the same definition
+    let crate_path = Path::new("./data/test-packages/macro-twice-ex");
+    let lib_rs = crate_path.join("src/lib.rs");
+    let resolver = Resolver::new(crate_path)?;
+    let resolver_impl = ResolverImpl::new(&resolver, &lib_rs)?;
+
+    let remove_file = SrcLoc::new(&lib_rs, 14, 21, 14, 32);
+    assert_eq!(
+        resolver_impl.resolve_ident(remove_file, Ident::new("remove_file"))?,
+        CanonicalPath::new("std::fs::remove_file")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rescan_changed_file() -> Result<()> {
+    // Work on a copy, since the test edits one of the files
+    let tmp_dir = tempfile::tempdir()?;
+    let crate_path = tmp_dir.path().join("rescan-ex");
+    let src_dir = crate_path.join("src");
+    std::fs::create_dir_all(&src_dir)?;
+    for f in ["Cargo.toml", "src/lib.rs", "src/other.rs"] {
+        std::fs::copy(
+            Path::new("./data/test-packages/rescan-ex").join(f),
+            crate_path.join(f),
+        )?;
+    }
+    let other_rs = src_dir.join("other.rs");
+
+    let mut results = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let effects_in = |results: &ScanResults, file: &str| {
+        results
+            .effects
+            .iter()
+            .filter(|e| e.call_loc().file() == Path::new(file))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    // Each env::var call is both a sink call and untrusted input
+    let lib_effects = effects_in(&results, "lib.rs");
+    assert_eq!(lib_effects.len(), 2);
+    assert_eq!(effects_in(&results, "other.rs").len(), 2);
+
+    std::fs::write(
+        &other_rs,
+        "pub fn other_var() -> bool {\n    std::env::var(\"PATH\").is_ok()\n}\n\n\
+         pub fn other_var2() -> bool {\n    std::env::var(\"USER\").is_ok()\n}\n",
+    )?;
+    let resolver = Resolver::new(&crate_path)?;
+    results.rescan_file(
+        "rescan-ex",
+        &other_rs,
+        Some(&resolver),
+        Default::default(),
+        DEFAULT_EFFECT_TYPES,
+        &Default::default(),
+    )?;
+
+    assert_eq!(effects_in(&results, "lib.rs"), lib_effects);
+    let other_effects = effects_in(&results, "other.rs");
+    assert_eq!(other_effects.len(), 4);
+    assert!(other_effects
+        .iter()
+        .any(|e| e.caller().as_str() == "rescan_ex::other::other_var2"));
+
+    // The edge from lib.rs into the rescanned file is still there
+    let callee = CanonicalPath::new("rescan_ex::other::other_var");
+    let callers = results.get_callers(&callee)?;
+    assert_eq!(callers.len(), 1);
+    assert!(callers.iter().all(|c| c.caller_path.as_str() == "rescan_ex::call_other"));
+    assert!(results
+        .fn_locs
+        .contains_key(&CanonicalPath::new("rescan_ex::other::other_var2")));
+    assert_eq!(results.fn_locs.len(), 4);
+
+    Ok(())
+}
+
+#[test]
+fn replace_results_of_one_file() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/caller-checked");
+    let sub_rs = crate_path.join("src/sub.rs");
+    let mut results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let in_sub = |loc: &SrcLoc| loc.file() == Path::new("sub.rs");
+    let split_effects =
+        |results: &ScanResults| -> (Vec<EffectInstance>, Vec<EffectInstance>) {
+            results.effects.iter().cloned().partition(|e| in_sub(e.call_loc()))
+        };
+    let other_fn_locs = |results: &ScanResults| {
+        results
+            .fn_locs
+            .iter()
+            .filter(|(_, loc)| !in_sub(loc))
+            .map(|(f, loc)| (f.clone(), loc.clone()))
+            .collect::<HashMap<_, _>>()
+    };
+    let (old_sub_effects, other_effects) = split_effects(&results);
+    let old_fn_locs = other_fn_locs(&results);
+
+    // Fresh results for sub.rs, without the call to sysconf
+    let mut file_results = ScanResults::new();
+    let resolver = Resolver::new(crate_path)?;
+    scan_file(
+        "caller-checked",
+        &sub_rs,
+        &resolver,
+        &mut file_results,
+        Default::default(),
+        &Default::default(),
+    )?;
+    file_results.effects.retain(|e| !e.callee_path().ends_with("::sysconf"));
+    let new_sub_effects = file_results.effects.clone();
+    assert!(new_sub_effects.len() < old_sub_effects.len());
+
+    results.replace_file_results(&sub_rs, file_results);
+
+    let (sub_effects, unchanged_effects) = split_effects(&results);
+    assert_eq!(sub_effects, new_sub_effects);
+    assert_eq!(unchanged_effects, other_effects);
+    assert_eq!(other_fn_locs(&results), old_fn_locs);
+
+    // The call into sub.rs from the rest of the crate is still there
+    let sub_effect = CanonicalPath::new("caller_checked::sub::effect");
+    assert!(results.fn_locs.contains_key(&sub_effect));
+    assert!(!results.get_callers(&sub_effect)?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn quick_mode_call_graph() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/caller-checked");
+    let full = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let quick = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, true)?;
+
+    let local_calls = |results: &ScanResults| {
+        results
+            .call_graph
+            .raw_edges()
+            .iter()
+            .map(|e| {
+                let caller = &results.call_graph[e.source()];
+                let callee = &results.call_graph[e.target()];
+                (caller.clone(), callee.clone())
+            })
+            .filter(|(caller, callee)| {
+                results.fn_locs.contains_key(caller)
+                    && results.fn_locs.contains_key(callee)
+            })
+            .collect::<HashSet<_>>()
+    };
+    let full_calls = local_calls(&full);
+    assert!(!full_calls.is_empty());
+    assert_eq!(local_calls(&quick), full_calls);
+
+    let sub_effect = CanonicalPath::new("caller_checked::sub::effect");
+    assert!(quick
+        .callers_of(&sub_effect)
+        .contains(&CanonicalPath::new("caller_checked::has_indirect_effect")));
+
+    Ok(())
+}
+
+#[test]
+fn save_and_load_scan_results() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/permissions-ex");
+    let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let key = hash_dir(crate_path)?;
+    let tmp_dir = tempfile::tempdir()?;
+    let cache = tmp_dir.path().join("permissions-ex.json");
+    results.save(&cache, &key)?;
+
+    let loaded = ScanResults::load(&cache, &key)?.expect("results saved with this key");
+    assert_eq!(loaded.effects, results.effects);
+    assert_eq!(loaded.fn_locs, results.fn_locs);
+    assert_eq!(loaded.node_idxs, results.node_idxs);
+    assert_eq!(loaded.call_graph.node_count(), results.call_graph.node_count());
+    assert_eq!(loaded.call_graph.edge_count(), results.call_graph.edge_count());
+
+    // Results saved for a different version of the crate are stale
+    assert!(ScanResults::load(&cache, &[0; 32])?.is_none());
+
+    std::fs::remove_file(&cache)?;
+    assert!(ScanResults::load(&cache, &key)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn scan_crate_reuses_cache() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/permissions-ex");
+    let dir = tempfile::tempdir()?;
+    let cache = dir.path().join("permissions-ex.scan");
+    let options = ScanOptions { quick_mode: true, ..Default::default() };
+    let scan = || scan_crate_cached(crate_path, DEFAULT_EFFECT_TYPES, &options, &cache);
+
+    // A cache that can't be read is scanned over
+    std::fs::write(&cache, "not scan results")?;
+    let results = scan()?;
+    assert!(!results.effects.is_empty());
+    let saved = std::fs::read_to_string(&cache)?;
+    assert_ne!(saved, "not scan results");
+
+    // The cache is only written when the crate is scanned
+    let cached = scan()?;
+    assert_eq!(cached.effects, results.effects);
+    assert_eq!(std::fs::read_to_string(&cache)?, saved);
+    let tampered = saved.replace("permissions_ex", "cached_ex");
+    std::fs::write(&cache, &tampered)?;
+    let cached = scan()?;
+    assert!(cached.effects.iter().any(|e| e.caller_path().starts_with("cached_ex")));
+
+    // Other options don't hit the cache
+    for other in [
+        ScanOptions { include_tests: true, ..options.clone() },
+        ScanOptions { record_skipped: true, ..options.clone() },
+    ] {
+        std::fs::write(&cache, &tampered)?;
+        scan_crate_cached(crate_path, DEFAULT_EFFECT_TYPES, &other, &cache)?;
+        assert!(!std::fs::read_to_string(&cache)?.contains("cached_ex"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn scan_workspace_members() -> Result<()> {
+    let workspace_path = Path::new("./data/test-packages/workspace-ex");
+    let results = scan_workspace(workspace_path, DEFAULT_EFFECT_TYPES, false)?;
+    // crates/ws-scratch matches the members glob but is excluded
+    assert_eq!(results.len(), 2);
+
+    let core_id = CrateId::new("ws-core".to_string(), Version::new(0, 1, 0));
+    let app_id = CrateId::new("ws-app".to_string(), Version::new(0, 2, 0));
+    let callees = |id: &CrateId| {
+        results[id]
+            .effects
+            .iter()
+            .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+            .map(|e| e.callee_path().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(callees(&core_id), vec!["std::fs::read_to_string"]);
+    assert_eq!(callees(&app_id), vec!["std::env::var"]);
+
+    // The members are scanned the same as on their own
+    let app_path = workspace_path.join("crates/ws-app");
+    assert_eq!(
+        results[&app_id].effects,
+        scan_crate(&app_path, DEFAULT_EFFECT_TYPES, false)?.effects
+    );
+    let callers =
+        results[&app_id].get_callers(&CanonicalPath::new("ws_core::read_config"))?;
+    assert_eq!(callers.len(), 1);
+    assert!(callers.iter().all(|c| c.caller_path.as_str() == "ws_app::config_or_env"));
+
+    let quick = scan_workspace(workspace_path, DEFAULT_EFFECT_TYPES, true)?;
+    assert_eq!(quick.keys().collect::<HashSet<_>>(), results.keys().collect());
+
+    Ok(())
+}
+
+#[test]
+fn fingerprint_stable_across_line_shifts() -> Result<()> {
+    // Work on a copy, since the test edits one of the files
+    let tmp_dir = tempfile::tempdir()?;
+    let crate_path = tmp_dir.path().join("rescan-ex");
+    let src_dir = crate_path.join("src");
+    std::fs::create_dir_all(&src_dir)?;
+    for f in ["Cargo.toml", "src/lib.rs", "src/other.rs"] {
+        std::fs::copy(
+            Path::new("./data/test-packages/rescan-ex").join(f),
+            crate_path.join(f),
+        )?;
+    }
+    let lib_rs = src_dir.join("lib.rs");
+    let lib_var_fingerprint = |results: &ScanResults| {
+        let fingerprints = EffectInstance::fingerprints(&results.effects);
+        assert_eq!(fingerprints.len(), results.effects.len());
+        let (fingerprint, e) = fingerprints
+            .into_iter()
+            .find(|(_, e)| {
+                e.caller_path() == "rescan_ex::lib_var"
+                    && matches!(e.eff_type(), Effect::SinkCall(_))
+            })
+            .expect("missing effect in lib_var");
+        (fingerprint, e.call_loc().start_line())
+    };
+
+    let before =
+        lib_var_fingerprint(&scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, true)?);
+
+    let lib = std::fs::read_to_string(&lib_rs)?;
+    std::fs::write(&lib_rs, format!("// Moved down\n\nfn unrelated() {{}}\n\n{}", lib))?;
+    let after =
+        lib_var_fingerprint(&scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, true)?);
+
+    assert_eq!(after.1, before.1 + 4);
+    assert_eq!(after.0, before.0);
+
+    Ok(())
+}
+
+#[test]
+fn effects_reachable_from_pub_fn() -> Result<()> {
+    let results = scan_test_package("caller-checked")?;
+    let reachable_callers = |f: &str| {
+        results
+            .effects_reachable_from(&CanonicalPath::new(f))
+            .iter()
+            .map(|e| e.caller().as_str().to_string())
+            .collect::<HashSet<_>>()
+    };
+
+    assert!(reachable_callers("caller_checked::no_effect").is_empty());
+    assert_eq!(
+        reachable_callers("caller_checked::has_direct_effect"),
+        HashSet::from(["caller_checked::has_direct_effect".to_string()])
+    );
+    assert_eq!(
+        reachable_callers("caller_checked::has_indirect_effect"),
+        HashSet::from(["caller_checked::sub::effect".to_string()])
+    );
+    let sub_effects = results
+        .effects
+        .iter()
+        .filter(|e| e.caller().as_str() == "caller_checked::sub::effect");
+    assert_eq!(
+        results
+            .effects_reachable_from(&CanonicalPath::new(
+                "caller_checked::has_indirect_effect"
+            ))
+            .len(),
+        sub_effects.count()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn encapsulated_unsafe_pub_fns() -> Result<()> {
+    let results = scan_test_package("caller-checked")?;
+    let fns = results
+        .encapsulated_unsafe_fns()
+        .iter()
+        .map(|f| f.as_str().to_string())
+        .collect::<HashSet<_>>();
+
+    assert!(fns.contains("caller_checked::has_direct_effect"));
+    assert!(fns.contains("caller_checked::has_indirect_effect"));
+    assert!(!fns.contains("caller_checked::no_effect"));
+
+    Ok(())
+}
+
+#[test]
+fn safe_pub_fns_reaching_ffi() -> Result<()> {
+    let results = scan_test_package("caller-checked")?;
+    let fns = results
+        .safe_fns_reaching_ffi()
+        .iter()
+        .map(|f| f.as_str().to_string())
+        .collect::<HashSet<_>>();
+
+    assert!(fns.contains("caller_checked::has_direct_effect"));
+    assert!(fns.contains("caller_checked::has_indirect_effect"));
+    assert!(fns.contains("caller_checked::sub::effect"));
+    assert!(!fns.contains("caller_checked::no_effect"));
+
+    Ok(())
+}
+
+#[test]
+fn call_graph_queries() -> Result<()> {
+    let results = scan_test_package("caller-checked")?;
+    let effect = CanonicalPath::new("caller_checked::sub::effect");
+    let indirect = CanonicalPath::new("caller_checked::has_indirect_effect");
+
+    assert!(results.callers_of(&effect).contains(&indirect));
+    assert!(results.callees_of(&indirect).contains(&effect));
+    assert!(!results.callers_of(&indirect).contains(&effect));
+
+    let missing = CanonicalPath::new("caller_checked::missing");
+    assert!(results.callers_of(&missing).is_empty());
+    assert!(results.callees_of(&missing).is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn effect_file_histogram() -> Result<()> {
+    let results = scan_test_package("caller-checked")?;
+    let histogram = results.effect_file_histogram();
+
+    let files = histogram
+        .iter()
+        .map(|(p, _)| p.file_name().unwrap().to_str().unwrap())
+        .collect::<HashSet<_>>();
+    assert_eq!(files, HashSet::from(["lib.rs", "main.rs", "sub.rs"]));
+    assert_eq!(histogram.iter().map(|(_, n)| n).sum::<usize>(), results.effects.len());
+    assert!(histogram.windows(2).all(|w| w[0].1 >= w[1].1));
+
+    Ok(())
+}
+
+#[test]
+fn combined_call_graphs() -> Result<()> {
+    let names = ["caller-checked", "recursion-ex", "closure-escape-ex"];
+    let mut combined = ScanResults::new();
+    let mut edges = 0;
+    for name in names {
+        let results = scan_test_package(name)?;
+        edges += results.call_graph.edge_count();
+        combined.combine_scan_results(results);
+    }
+
+    // Edges from every result set are kept, including ones across files
+    assert_eq!(combined.call_graph.edge_count(), edges);
+    let effect = CanonicalPath::new("caller_checked::sub::effect");
+    let indirect = CanonicalPath::new("caller_checked::has_indirect_effect");
+    assert!(combined.callers_of(&effect).contains(&indirect));
+    assert!(combined
+        .get_callers(&effect)?
+        .iter()
+        .any(|i| i.caller_path == indirect && i.callee_loc.file().ends_with("lib.rs")));
+    let f = CanonicalPath::new("recursion_ex::f");
+    let g = CanonicalPath::new("recursion_ex::g");
+    assert!(combined.callers_of(&f).contains(&g));
+    assert!(combined.callers_of(&g).contains(&f));
+
+    Ok(())
+}
+
+#[test]
+fn unsafe_impls_with_trait_and_type() -> Result<()> {
+    let results = scan_test_package("unsafe-impl-ex")?;
+    let impls = results
+        .unsafe_impl_decls
+        .iter()
+        .map(|i| (i.trait_path.as_str(), i.self_ty.as_str(), i.src_loc.start_line()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        impls,
+        vec![
+            ("core::marker::Send", "unsafe_impl_ex::Shared", 7),
+            ("core::marker::Sync", "unsafe_impl_ex::Shared", 8),
+            ("core::marker::Send", "unsafe_impl_ex::RawBox", 12),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn empty_unsafe_block_reported() -> Result<()> {
+    let results = scan_test_package("empty-unsafe-ex")?;
+    let lines = results
+        .empty_unsafe_blocks
+        .iter()
+        .map(|loc| loc.start_line())
+        .collect::<Vec<_>>();
+    // Only the inner of the nested blocks, and not the block around set_len
+    assert_eq!(lines, vec![12, 19]);
+
+    Ok(())
+}
+
+#[test]
+fn no_std_alloc_uses_reported() -> Result<()> {
+    let results = scan_test_package("no-std-alloc-ex")?;
+    assert!(results.no_std.is_some());
+    let lines = results
+        .no_std_alloc_uses()
+        .iter()
+        .map(|loc| loc.start_line())
+        .collect::<Vec<_>>();
+    assert_eq!(lines, vec![3, 5, 8]);
+
+    // Crates that link std aren't reported
+    let results = scan_test_package("alloc-ex")?;
+    assert!(results.no_std.is_none());
+    assert!(results.no_std_alloc_uses().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn stored_closure_effects_at_call_site() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/closure-vec-ex");
+    for quick_mode in [false, true] {
+        let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, quick_mode)?;
+        let mut call_site_effects = results
+            .effects
+            .iter()
+            .filter(|e| e.call_loc().start_line() == 13)
+            .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+            .map(|e| (e.caller_path(), e.callee_path()))
+            .collect::<Vec<_>>();
+        call_site_effects.sort();
+        assert_eq!(
+            call_site_effects,
+            vec![
+                ("closure_vec_ex::run_handlers", "std::fs::remove_file"),
+                ("closure_vec_ex::run_handlers", "std::process::Command::new"),
+            ]
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn set_len_is_sink_call() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/set-len-ex");
+    for quick_mode in [false, true] {
+        let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, quick_mode)?;
+        let sinks = results
+            .effects
+            .iter()
+            .filter_map(|e| match e.eff_type() {
+                Effect::SinkCall(s) => Some((s.as_str(), e.call_loc().start_line())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sinks, vec![("alloc::vec::Vec::set_len", 4)]);
+    }
+
+    // The call is still an unsafe call, which is only known in full mode
+    let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let unsafe_calls = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::UnsafeCall(_)))
+        .map(|e| (e.callee_path(), e.call_loc().start_line()))
+        .collect::<Vec<_>>();
+    assert_eq!(unsafe_calls, vec![("alloc::vec::Vec::set_len", 4)]);
+
+    Ok(())
+}
+
+#[test]
+fn raw_ownership_transfers_are_sink_calls() -> Result<()> {
+    let results = scan_test_package("box-raw-ex")?;
+    let sinks = results
+        .effects
+        .iter()
+        .filter_map(|e| match e.eff_type() {
+            Effect::SinkCall(s) => Some((s.as_str(), e.call_loc().start_line())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sinks,
+        vec![
+            ("alloc::boxed::Box::into_raw", 5),
+            ("alloc::boxed::Box::from_raw", 11),
+            ("alloc::rc::Rc::into_raw", 15),
+            ("alloc::sync::Arc::into_raw", 16),
+            ("alloc::sync::Arc::from_raw", 17),
+        ]
+    );
+
+    // from_raw is unsafe, so it's an unsafe call as well
+    let unsafe_calls = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::UnsafeCall(_)))
+        .map(|e| (e.callee_path(), e.call_loc().start_line()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unsafe_calls,
+        vec![("alloc::boxed::Box::from_raw", 11), ("alloc::sync::Arc::from_raw", 17)]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn raw_pointer_offsets() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/ptr-offset-ex");
+    // Without type information, only offsets of casts to raw pointers are
+    // recognized
+    for (quick_mode, expected) in [(false, vec![4, 9, 10, 15]), (true, vec![15])] {
+        let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, quick_mode)?;
+        let lines = results
+            .effects
+            .iter()
+            .filter(|e| matches!(e.eff_type(), Effect::PtrOffset(_)))
+            .map(|e| e.call_loc().start_line())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, expected);
+    }
+
+    // `wrapping_offset` is safe to call
+    let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, false)?;
+    let rust_unsafe = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::PtrOffset(_)))
+        .map(|e| (e.call_loc().start_line(), e.is_rust_unsafe()))
+        .collect::<Vec<_>>();
+    assert_eq!(rust_unsafe, vec![(4, true), (9, true), (10, true), (15, false)]);
+
+    Ok(())
+}
+
+#[test]
+fn effect_fn_contexts() -> Result<()> {
+    let results = scan_test_package("fn-context-ex")?;
+    let contexts = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+        .map(|e| (e.call_loc().start_line(), e.fn_context().clone()))
+        .collect::<HashMap<_, _>>();
+
+    let store = CanonicalPath::new("fn_context_ex::Store");
+    let load = CanonicalPath::new("fn_context_ex::Load");
+    assert_eq!(contexts[&7], FnContext::TraitDefault);
+    assert_eq!(contexts[&13], FnContext::InherentImpl(store.clone()));
+    assert_eq!(contexts[&19], FnContext::TraitImpl { trait_: load, ty: store });
+    assert_eq!(contexts[&24], FnContext::Free);
+
+    Ok(())
+}
+
+#[test]
+fn symlinked_files_scanned_once() -> Result<()> {
+    // src/b.rs links to src/a.rs, and src/loop links back to src
+    let src_dir = Path::new("./data/test-packages/symlink-ex/src");
+    for follow_symlinks in [true, false] {
+        let files = walk_unique_files_with_extension(src_dir, "rs", follow_symlinks)
+            .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(files, vec!["a.rs", "lib.rs"]);
+    }
+
+    let results = scan_test_package("symlink-ex")?;
+    let var_effects = results
+        .effects
+        .iter()
+        .filter(|e| e.callee().as_str() == "std::env::var")
+        .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+        .collect::<Vec<_>>();
+    assert_eq!(var_effects.len(), 1);
+    assert_eq!(var_effects[0].call_loc().file(), Path::new("a.rs"));
+
+    Ok(())
+}
+
+#[test]
+fn files_over_max_size_skipped() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/large-file-ex");
+    let generated = crate_path.join("src/generated.rs");
+    let generated_size = generated.metadata()?.len();
+    let scan = |max_file_size: Option<u64>| {
+        let options =
+            ScanOptions { quick_mode: true, max_file_size, ..Default::default() };
+        scan_crate_with_options(crate_path, DEFAULT_EFFECT_TYPES, &options)
+    };
+    let generated_effects = |results: &ScanResults| {
+        results
+            .effects
+            .iter()
+            .filter(|e| e.call_loc().file() == Path::new("generated.rs"))
+            .count()
+    };
+
+    let results = scan(None)?;
+    assert!(results.skipped_large_files.is_empty());
+    assert!(generated_effects(&results) > 0);
+
+    let results = scan(Some(generated_size - 1))?;
+    assert_eq!(results.skipped_large_files.len(), 1);
+    let (skipped, size) = &results.skipped_large_files[0];
+    assert!(skipped.ends_with("src/generated.rs"));
+    assert_eq!(*size, generated_size);
+    assert_eq!(generated_effects(&results), 0);
+    // Smaller files are still scanned
+    assert!(results.effects.iter().any(|e| e.callee().as_str() == "std::env::var"));
+
+    Ok(())
+}
+
+#[test]
+fn effects_in_macro_definitions() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/macro-ex");
+    let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, true)?;
+
+    // Macros aren't expanded, so nothing is found at the invocations
+    assert!(results.effects.is_empty());
+
+    assert!(results
+        .macro_def_effects
+        .iter()
+        .all(|e| e.found_via() == EffectProvenance::MacroDefinition));
+    let raw_ptr_writes = results
+        .macro_def_effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::RawPointer(_)))
+        .collect::<Vec<_>>();
+    assert_eq!(raw_ptr_writes.len(), 1);
+    assert_eq!(raw_ptr_writes[0].call_loc().start_line(), 8);
+    assert!(results
+        .macro_def_effects
+        .iter()
+        .any(|e| e.callee_path() == "std::fs::File::create"
+            && e.call_loc().start_line() == 17));
+
+    Ok(())
+}
+
+#[test]
+fn dedup_effects_at_same_location() -> Result<()> {
+    let mut results = scan_test_package("libc-ex")?;
+    let effects = results.effects.clone();
+    assert!(!effects.is_empty());
+
+    // The scan itself shouldn't report an effect twice
+    results.dedup_effects();
+    assert_eq!(results.effects, effects);
+
+    results.effects.extend(effects.iter().cloned());
+    results.dedup_effects();
+    assert_eq!(results.effects, effects);
+
+    Ok(())
+}
+
+#[test]
+fn of_interest_file_sinks() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/permissions-ex");
+    let sinks = Sink::load_from_file(&crate_path.join("of_interest.txt"))?;
+    assert_eq!(sinks.len(), 2);
+    let results =
+        scan_crate_with_sinks(crate_path, sinks, DEFAULT_EFFECT_TYPES, false, &[])?;
+
+    let sink_pattern = |callee: &str| {
+        results
+            .effects
+            .iter()
+            .filter(|e| e.callee().as_str() == callee)
+            .map(|e| match e.eff_type() {
+                Effect::SinkCall(s) => s.as_str().to_string(),
+                _ => panic!("expected a sink call for {}", callee),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(sink_pattern("std::fs::write"), vec!["std::fs::write"; 2]);
+    assert_eq!(
+        sink_pattern("std::process::Command::output"),
+        vec!["std::process::*::output"]
+    );
+    // Callees not in the list still match the default sinks
+    assert_eq!(sink_pattern("std::process::Command::new"), vec!["std::process"]);
+
+    Ok(())
+}
+
+#[test]
+fn network_sinks() {
+    let sinks = Sink::default_sinks();
+    for callee in [
+        "std::net::tcp::TcpStream::connect",
+        "std::net::tcp::TcpListener::bind",
+        "std::net::udp::UdpSocket::bind",
+        "reqwest::blocking::get",
+        "reqwest::async_impl::client::Client::new",
+        "hyper::client::client::Client::new",
+    ] {
+        let sink = Sink::new_match(&CanonicalPath::new(callee), &sinks)
+            .unwrap_or_else(|| panic!("{} should be a sink", callee));
+        assert_eq!(sink.category(), SinkCategory::Network);
+    }
+
+    // Only client constructors are sinks, not everything in the crate
+    let not_sink = CanonicalPath::new("reqwest::header::HeaderMap::new");
+    assert!(Sink::new_match(&not_sink, &sinks).is_none());
+}
+
+#[test]
+fn env_write_sinks() {
+    let sinks = Sink::default_sinks();
+    let category = |callee: &str| {
+        Sink::new_match(&CanonicalPath::new(callee), &sinks).map(|s| s.category())
+    };
+    assert_eq!(category("std::env::set_var"), Some(SinkCategory::EnvWrite));
+    assert_eq!(category("std::env::remove_var"), Some(SinkCategory::EnvWrite));
+    assert_eq!(category("std::env::var"), Some(SinkCategory::Env));
+    assert_eq!("env-write".parse::<SinkCategory>().ok(), Some(SinkCategory::EnvWrite));
+}
+
+#[test]
+fn io_and_path_sinks() {
+    let sinks = Sink::default_sinks();
+    let category = |callee: &str| {
+        Sink::new_match(&CanonicalPath::new(callee), &sinks).map(|s| s.category())
+    };
+    assert_eq!(category("std::io::stdin"), Some(SinkCategory::Io));
+    assert_eq!(category("std::fs::read_to_string"), Some(SinkCategory::Filesystem));
+    assert_eq!(category("std::path::Path::join"), Some(SinkCategory::Other));
+    assert_eq!(category("std::path::Path::exists"), Some(SinkCategory::Filesystem));
+}
+
+#[test]
+fn build_script_effects() -> Result<()> {
+    let results = scan_test_package("build-script-ex")?;
+
+    let (build, lib): (Vec<_>, Vec<_>) =
+        results.effects.iter().partition(|e| e.from_build_script());
+    assert!(build.iter().all(|e| e.call_loc().file().ends_with("build.rs")));
+    assert!(build.iter().any(|e| e.callee_path() == "std::process::Command::new"));
+    assert!(build.iter().any(|e| e.callee_path() == "std::env::var"));
+    assert!(build.iter().any(|e| matches!(e.eff_type(), Effect::FnPtrCreation)));
+
+    assert_eq!(lib.len(), 1);
+    assert_eq!(lib[0].callee_path(), "std::fs::read_to_string");
+
+    Ok(())
+}
+
+#[test]
+fn deref_of_raw_pointer_only() -> Result<()> {
+    let results = scan_test_package("dependency-ex")?;
+
+    let raw_ptr_effects = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::RawPointer(_)))
+        .collect::<Vec<_>>();
+    // `safe_deref` passes a raw pointer to a call, but dereferences the
+    // returned reference
+    assert_eq!(raw_ptr_effects.len(), 1);
+    assert_eq!(raw_ptr_effects[0].caller().as_str(), "dependency_ex::unsafe_deref");
+
+    Ok(())
+}
+
+#[test]
+fn deref_of_cast_to_raw_pointer() -> Result<()> {
+    let results = scan_test_package("raw-deref-ex")?;
+
+    let raw_ptr_callers = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::RawPointer(_)))
+        .map(|e| e.caller().as_str())
+        .collect::<HashSet<_>>();
+    // Casts of a literal and of a reference have no pointer name
+    assert_eq!(
+        raw_ptr_callers,
+        HashSet::from([
+            "raw_deref_ex::read_mmio",
+            "raw_deref_ex::read_local",
+            "raw_deref_ex::read_point",
+        ])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn dyn_trait_call_reaches_all_impls() -> Result<()> {
+    let results = scan_test_package("dyn-ex")?;
+
+    let reachable_callees = |caller: &str| {
+        results
+            .effects_reachable_from(&CanonicalPath::new(caller))
+            .iter()
+            .map(|e| e.callee_path().to_string())
+            .collect::<HashSet<_>>()
+    };
+    // Trait declared in the crate
+    let callees = reachable_callees("dyn_ex::log_dyn");
+    assert!(callees.contains("std::fs::write"));
+    assert!(callees.contains("std::process::Command::new"));
+    // Trait declared in the standard library
+    let callees = reachable_callees("dyn_ex::write_dyn");
+    assert!(callees.contains("std::fs::write"));
+    assert!(callees.contains("std::process::Command::new"));
+
+    Ok(())
+}
+
+#[test]
+fn virtual_dispatch_through_generic_bounds() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/dyn-ex");
+    let scan = |virtual_dispatch: bool| {
+        let options = ScanOptions { virtual_dispatch, ..Default::default() };
+        scan_crate_with_options(crate_path, DEFAULT_EFFECT_TYPES, &options)
+    };
+    let reachable_callees = |results: &ScanResults, caller: &str| {
+        results
+            .effects_reachable_from(&CanonicalPath::new(caller))
+            .iter()
+            .map(|e| e.callee_path().to_string())
+            .collect::<HashSet<_>>()
+    };
+
+    // A trait declared in the crate links its methods to the impls anyway
+    let results = scan(false)?;
+    let callees = reachable_callees(&results, "dyn_ex::log_generic");
+    assert!(callees.contains("std::fs::write"));
+    assert!(callees.contains("std::process::Command::new"));
+    // A trait declared in the standard library doesn't
+    assert!(reachable_callees(&results, "dyn_ex::write_generic").is_empty());
+
+    let results = scan(true)?;
+    let callees = reachable_callees(&results, "dyn_ex::write_generic");
+    assert!(callees.contains("std::fs::write"));
+    assert!(callees.contains("std::process::Command::new"));
+    // Calls on trait objects are unaffected
+    let callees = reachable_callees(&results, "dyn_ex::write_dyn");
+    assert!(callees.contains("std::fs::write"));
+
+    Ok(())
+}
+
+#[test]
+fn feature_gated_code_follows_enabled_features() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/cfg-ex");
+    let foo2_lines = |features: &[String]| -> Result<Vec<usize>> {
+        let results = scan_crate_with_sinks(
+            crate_path,
+            HashSet::new(),
+            DEFAULT_EFFECT_TYPES,
+            true,
+            features,
+        )?;
+        Ok(results
+            .effects
+            .iter()
+            .filter(|e| e.caller().as_str() == "cfg_ex::foo2")
+            .map(|e| e.call_loc().start_line())
+            .collect())
+    };
+
+    // Only one of the two definitions of `foo2` is scanned
+    assert_eq!(foo2_lines(&[])?, vec![24]);
+    assert_eq!(foo2_lines(&["extra".to_string()])?, vec![19]);
+
+    Ok(())
+}
+
+#[test]
+fn command_program_literal_or_dynamic() -> Result<()> {
+    let results = scan_test_package("input-ex")?;
+
+    let program_args = results
+        .effects
+        .iter()
+        .filter(|e| e.caller_path() == "input_ex::run")
+        .map(|e| (e.callee_path(), e.program_arg()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        program_args
+            .iter()
+            .filter(|(callee, _)| *callee == "std::process::Command::new")
+            .map(|(_, p)| *p)
+            .collect::<Vec<_>>(),
+        vec![Some(ProgramArg::Literal), Some(ProgramArg::Dynamic)]
+    );
+    // Only recorded for calls that run a program
+    assert!(program_args
+        .iter()
+        .filter(|(callee, _)| *callee != "std::process::Command::new")
+        .all(|(_, p)| p.is_none()));
+
+    Ok(())
+}
+
+#[test]
+fn loose_files_without_manifest() -> Result<()> {
+    let dir = Path::new("./data/test-packages/loose-files-ex");
+    assert!(scan_crate(dir, DEFAULT_EFFECT_TYPES, true).is_err());
+
+    let results =
+        scan_loose_files(dir, "loose_files", HashSet::new(), DEFAULT_EFFECT_TYPES)?;
+    let mut callees = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+        .map(|e| e.callee_path())
+        .collect::<Vec<_>>();
+    callees.sort();
+    assert_eq!(callees, vec!["std::env::var", "std::fs::read_to_string"]);
+    assert!(results
+        .effects
+        .iter()
+        .all(|e| e.caller().crate_name().as_str() == "loose_files"));
+
+    Ok(())
+}
+
+#[test]
+fn stats_summary_counts() -> Result<()> {
+    let results = scan_test_package("permissions-ex")?;
+    let summary = results.stats_summary();
+
+    // Command::new, two calls to arg, output, and two calls to fs::write
+    assert_eq!(summary.effect_counts.get(&EffectType::SinkCall), Some(&6));
+    assert_eq!(summary.effect_counts.len(), 1);
+    assert_eq!(summary.total_fns, 3);
+    assert_eq!(summary.pub_fns, 3);
+    assert_eq!(summary.fns_with_effects, 3);
+    assert_eq!(summary.total_loc, results.total_loc.get_loc());
+    assert_eq!(summary.skipped_loc, 0);
+
+    assert_eq!(results.fn_effect_count.values().sum::<usize>(), 6);
+    assert_eq!(summary.fn_effects.len(), 3);
+    for f in &summary.fn_effects {
+        assert_eq!(f.effects, results.fn_effect_count[&f.fn_name]);
+        assert!(f.loc > 0);
+    }
+    assert!(summary.fn_effects.windows(2).all(|w| w[0].density() >= w[1].density()));
+
+    let json = serde_json::to_string(&summary)?;
+    assert!(json.contains("\"SinkCall\":6"));
+    assert_eq!(serde_json::from_str::<ScanSummary>(&json)?, summary);
+
+    Ok(())
+}
+
+#[test]
+fn sinks_match_renamed_dependency() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/rename-ex");
+    let sink_callees = |sink: &str, quick_mode: bool| -> Result<Vec<String>> {
+        let sinks = HashSet::from([IdentPath::new(sink)]);
+        let results = scan_crate_with_sinks(
+            crate_path,
+            sinks,
+            DEFAULT_EFFECT_TYPES,
+            quick_mode,
+            &[],
+        )?;
+        Ok(results
+            .effects
+            .iter()
+            .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+            .map(|e| e.callee_path().to_string())
+            .collect())
+    };
+
+    // `perms` is `permissions-ex` renamed; calls resolve to the real name in
+    // full mode and to the alias in quick mode
+    assert_eq!(sink_callees("perms::remove", false)?, vec!["permissions_ex::remove"]);
+    assert_eq!(
+        sink_callees("permissions_ex::remove", false)?,
+        vec!["permissions_ex::remove"]
+    );
+    assert_eq!(sink_callees("permissions_ex::remove", true)?, vec!["perms::remove"]);
+
+    Ok(())
+}
+
+#[test]
+fn streaming_scan_matches_batch() -> Result<()> {
+    // fns-closures has function pointer effects, which are written last, and
+    // build-script-ex has effects in a build script
+    for name in ["caller-checked", "fns-closures", "build-script-ex"] {
+        let crate_path = Path::new("./data/test-packages").join(name);
+        let batch = scan_crate(&crate_path, DEFAULT_EFFECT_TYPES, true)?;
+
+        let mut streamed = Vec::new();
+        let n_written = scan_crate_streaming(
+            &crate_path,
+            HashSet::new(),
+            DEFAULT_EFFECT_TYPES,
+            &[],
+            |e| {
+                streamed.push(e.clone());
+                Ok(())
+            },
+        )?;
+        assert!(!streamed.is_empty());
+        assert_eq!(n_written, streamed.len());
+        assert_eq!(streamed, batch.effects, "streamed effects differ for {}", name);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn scan_file_with_callback() -> Result<()> {
+    let filepath = Path::new("./data/test-packages/permissions-ex/src/lib.rs");
+    let src = std::fs::read_to_string(filepath)?;
+    let syntax_tree = syn::parse_file(&src)?;
+    let enabled_cfg = HashMap::new();
+
+    let mut batch = ScanResults::new();
+    let resolver = HackyResolver::new("permissions_ex", filepath)?;
+    Scanner::new(filepath, resolver, &mut batch, &enabled_cfg).scan_file(&syntax_tree);
+
+    let mut streamed = Vec::new();
+    let mut results = ScanResults::new();
+    let resolver = HackyResolver::new("permissions_ex", filepath)?;
+    Scanner::new(filepath, resolver, &mut results, &enabled_cfg)
+        .scan_file_with(&syntax_tree, &mut |e| streamed.push(e.clone()));
+
+    assert!(!streamed.is_empty());
+    assert_eq!(streamed, batch.effects);
+    assert!(results.effects.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn effects_in_let_branches() -> Result<()> {
+    for quick_mode in [false, true] {
+        let crate_path = Path::new("./data/test-packages/let-else-ex");
+        let results = scan_crate(crate_path, DEFAULT_EFFECT_TYPES, quick_mode)?;
+        let callees = |caller: &str| {
+            results
+                .effects
+                .iter()
+                .filter(|e| e.caller().as_str() == caller)
+                .map(|e| e.callee_path())
+                .collect::<HashSet<_>>()
+        };
+
+        // The else arm of let-else
+        assert!(callees("let_else_ex::let_else").contains("std::process::exit"));
+        // The scrutinee and both arms of if let
+        assert_eq!(
+            callees("let_else_ex::if_let"),
+            HashSet::from([
+                "std::fs::read_to_string",
+                "std::fs::write",
+                "std::fs::remove_file"
+            ])
+        );
+        // The body of while let
+        assert!(callees("let_else_ex::while_let").contains("std::fs::remove_file"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn effects_in_macro_arguments() -> Result<()> {
+    let results = scan_test_package("macro-args-ex")?;
+    let effect_lines = results
+        .effects
+        .iter()
+        .map(|e| (e.callee().as_str(), e.call_loc().start_line()))
+        .collect::<HashSet<_>>();
+
+    assert!(effect_lines.contains(&("std::env::var", 9)));
+    assert!(effect_lines.contains(&("std::fs::read_to_string", 13)));
+    assert!(effect_lines.contains(&("std::fs::metadata", 14)));
+    assert!(effect_lines.contains(&("std::fs::remove_file", 23)));
+    // Effects in the arguments are found via macro expansion
+    for e in &results.effects {
+        let expected = if e.callee_path() == "std::fs::remove_file" {
+            EffectProvenance::Direct
+        } else {
+            EffectProvenance::MacroExpansion
+        };
+        assert_eq!(e.found_via(), expected);
+    }
+    // Arguments of other macros aren't scanned
+    assert!(!effect_lines.iter().any(|(callee, _)| *callee == "std::env::remove_var"));
+    // The definition and the call of `opaque!`
+    assert_eq!(results.skipped_macros.get_instances(), 2);
+    // Only counted, unless the scan records skipped code
+    assert!(results.skipped_report().is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn skipped_code_report() -> Result<()> {
+    let crate_path = Path::new("./data/test-packages/macro-args-ex");
+    let options = ScanOptions { record_skipped: true, ..Default::default() };
+    let results = scan_crate_with_options(crate_path, DEFAULT_EFFECT_TYPES, &options)?;
+
+    let report = results.skipped_report();
+    let skipped_macros = report
+        .iter()
+        .filter(|(kind, _)| *kind == SkippedKind::Macro)
+        .map(|(_, loc)| loc)
+        .collect::<Vec<_>>();
+    assert_eq!(skipped_macros.len(), results.skipped_macros.get_instances());
+    assert_eq!(skipped_macros.len(), 2);
+    assert!(skipped_macros.iter().all(|loc| loc.file() == Path::new("lib.rs")));
+    assert!(skipped_macros[0].start_line() < skipped_macros[1].start_line());
+
+    let total = SkippedKind::all()
+        .into_iter()
+        .map(|kind| results.skipped(kind).get_instances())
+        .sum::<usize>();
+    assert_eq!(report.len(), total);
+
+    Ok(())
+}
+
+#[test]
+fn anonymous_consts_and_closures_resolve() -> Result<()> {
+    let results = scan_test_package("anon-type-ex")?;
+    assert!(!results.quick_mode_fallback);
+
+    let var_lines = results
+        .effects
+        .iter()
+        .filter(|e| e.callee().as_str() == "std::env::var")
+        .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+        .map(|e| e.call_loc().start_line())
+        .collect::<Vec<_>>();
+    assert_eq!(var_lines, vec![24]);
+
+    Ok(())
+}
+
+#[test]
+fn escaping_closures() -> Result<()> {
+    let results = scan_test_package("closure-escape-ex")?;
+    let escapes = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::ClosureCreation))
+        .map(|e| (e.call_loc().start_line(), e.closure_escapes()))
+        .collect::<HashMap<_, _>>();
+
+    assert_eq!(escapes[&8], Some(false));
+    assert_eq!(escapes[&13], Some(true));
+    assert_eq!(escapes[&17], Some(true));
+    assert_eq!(escapes[&22], Some(true));
+
+    // Other effects aren't tagged
+    assert!(results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+        .all(|e| e.closure_escapes().is_none()));
+
+    Ok(())
+}
+
+#[test]
+fn ffi_exports() -> Result<()> {
+    let results = scan_test_package("ffi-export-ex")?;
+    let exports = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::FFIExport(_)))
+        .map(|e| (e.callee_path(), e.call_loc().start_line()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        exports,
+        vec![
+            ("ffi_export_ex::exported_add", 2),
+            ("ffi_export_ex::private_export", 7),
+            ("ffi_export_ex::public_extern", 11),
+            ("ffi_export_ex::Counter::counter_new", 28),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn mem_swap_sinks() -> Result<()> {
+    let results = scan_test_package("mem-swap-ex")?;
+    let sinks = results
+        .effects
+        .iter()
+        .filter(|e| matches!(e.eff_type(), Effect::SinkCall(_)))
+        .map(|e| (e.callee_path(), e.call_loc().start_line()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        sinks,
+        vec![("core::mem::swap", 8), ("core::mem::replace", 12), ("core::mem::take", 13)]
+    );
+
+    Ok(())
+}